@@ -1,20 +1,20 @@
 
 use crate::types::tokens::{Token};
+use crate::types::word::Word;
 
 #[derive(Debug)]
 pub enum AstNode {
-    // Simple command: command [flags...] [args...]
+    // Simple command: the command name followed by its arguments, in the order they
+    // were typed. Telling options from operands is up to the command itself.
     Command {
-        name: String,
-        flags: Vec<String>,
-        args: Vec<String>,
+        words: Vec<Word>,
     },
     
     // Redirection: command > file, command < file, command >> file
     Redirect {
         command: Box<AstNode>,
         operator: Token,  // RedirectOut, RedirectAppend, RedirectIn
-        file: String,
+        file: Word,
     },
     
     // Pipeline: command1 | command2
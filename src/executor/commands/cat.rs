@@ -0,0 +1,224 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::terminal::OutputStream;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+
+pub struct Cat;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("number", HasArg::No, Some('n')),
+    long("number-nonblank", HasArg::No, Some('b')),
+    long("show-all", HasArg::No, Some('A')),
+    long("show-ends", HasArg::No, Some('E')),
+    long("show-nonprinting", HasArg::No, Some('v')),
+    long("show-tabs", HasArg::No, Some('T')),
+    long("squeeze-blank", HasArg::No, Some('s')),
+];
+
+// Files are copied through a buffer of this size, never read whole into memory
+const BUF_SIZE: usize = 8192;
+
+#[derive(Default)]
+struct CatOptions {
+    number: bool,           // -n
+    number_nonblank: bool,  // -b, overrides -n
+    squeeze_blank: bool,    // -s
+    show_ends: bool,        // -E
+    show_tabs: bool,        // -T
+    show_nonprinting: bool, // -v
+}
+
+impl CatOptions {
+    // Without any of these the input can be copied byte for byte
+    fn transforms(&self) -> bool {
+        self.number
+            || self.number_nonblank
+            || self.squeeze_blank
+            || self.show_ends
+            || self.show_tabs
+            || self.show_nonprinting
+    }
+}
+
+// Line numbering and blank-line tracking carry over from one chunk (and file) to the next
+struct LineState {
+    line_number: usize,
+    at_line_start: bool,
+    previous_blank: bool,
+}
+
+impl super::Command for Cat {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, mut operands) = self.parse_args(args)?;
+        if operands.is_empty() {
+            operands.push("-".to_string());
+        }
+
+        let mut state = LineState {
+            line_number: 0,
+            at_line_start: true,
+            previous_blank: false,
+        };
+        let mut status = 0;
+
+        // A missing file is reported and skipped; the rest are still printed
+        for operand in &operands {
+            let result = if operand == "-" {
+                self.copy(stdin, &options, &mut state, stdout)
+            } else {
+                match File::open(operand) {
+                    Ok(mut file) => self.copy(&mut file, &options, &mut state, stdout),
+                    Err(e) => Err(e),
+                }
+            };
+
+            // Directories open fine and only fail on the first read
+            if let Err(e) = result {
+                writeln!(stderr, "cat: {}: {}", operand, error::describe(&e))?;
+                status = 1;
+            }
+        }
+
+        stdout.flush()?;
+        if status != 0 {
+            return Err(ShellError::status(status));
+        }
+        Ok(())
+    }
+}
+
+impl Cat {
+    fn parse_args(&self, args: &[String]) -> Result<(CatOptions, Vec<String>)> {
+        let mut options = CatOptions::default();
+
+        let mut getopt = Getopt::new("cat", args, "AbEensTtv", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            match option.name {
+                "n" => options.number = true,
+                "b" => options.number_nonblank = true,
+                "s" => options.squeeze_blank = true,
+                "E" => options.show_ends = true,
+                "T" => options.show_tabs = true,
+                "v" => options.show_nonprinting = true,
+                "A" => {
+                    options.show_nonprinting = true;
+                    options.show_ends = true;
+                    options.show_tabs = true;
+                }
+                "e" => {
+                    options.show_nonprinting = true;
+                    options.show_ends = true;
+                }
+                "t" => {
+                    options.show_nonprinting = true;
+                    options.show_tabs = true;
+                }
+                _ => {}
+            }
+        }
+
+        Ok((options, getopt.operands()))
+    }
+
+    // Copy one input to stdout a chunk at a time, applying any formatting options.
+    // Read errors are returned as io errors so the caller can report them per file.
+    fn copy(
+        &self,
+        input: &mut dyn Read,
+        options: &CatOptions,
+        state: &mut LineState,
+        stdout: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let mut buffer = [0u8; BUF_SIZE];
+        let mut output = Vec::with_capacity(BUF_SIZE * 2);
+
+        loop {
+            let count = match input.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(count) => count,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            if !options.transforms() {
+                stdout.write_all(&buffer[..count])?;
+                continue;
+            }
+
+            output.clear();
+            format_chunk(&buffer[..count], options, state, &mut output);
+            stdout.write_all(&output)?;
+        }
+    }
+}
+
+fn format_chunk(chunk: &[u8], options: &CatOptions, state: &mut LineState, out: &mut Vec<u8>) {
+    for &byte in chunk {
+        if state.at_line_start {
+            if byte == b'\n' {
+                // A blank line: -s keeps only the first of a run, -b doesn't number it
+                if options.squeeze_blank && state.previous_blank {
+                    continue;
+                }
+                state.previous_blank = true;
+                if options.number && !options.number_nonblank {
+                    push_line_number(state, out);
+                }
+                if options.show_ends {
+                    out.push(b'$');
+                }
+                out.push(b'\n');
+                continue;
+            }
+
+            state.previous_blank = false;
+            state.at_line_start = false;
+            if options.number || options.number_nonblank {
+                push_line_number(state, out);
+            }
+        }
+
+        match byte {
+            b'\n' => {
+                if options.show_ends {
+                    out.push(b'$');
+                }
+                out.push(b'\n');
+                state.at_line_start = true;
+            }
+            b'\t' if options.show_tabs => out.extend_from_slice(b"^I"),
+            b'\t' => out.push(b'\t'),
+            _ if options.show_nonprinting => push_visible(byte, out),
+            _ => out.push(byte),
+        }
+    }
+}
+
+fn push_line_number(state: &mut LineState, out: &mut Vec<u8>) {
+    state.line_number += 1;
+    out.extend_from_slice(format!("{:>6}\t", state.line_number).as_bytes());
+}
+
+// -v notation: control characters as ^X, DEL as ^?, high bytes prefixed with M-
+fn push_visible(byte: u8, out: &mut Vec<u8>) {
+    let mut byte = byte;
+    if byte >= 128 {
+        out.extend_from_slice(b"M-");
+        byte -= 128;
+    }
+    match byte {
+        0..=31 => {
+            out.push(b'^');
+            out.push(byte + 64);
+        }
+        127 => out.extend_from_slice(b"^?"),
+        _ => out.push(byte),
+    }
+}
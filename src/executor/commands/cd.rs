@@ -0,0 +1,125 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::Getopt;
+use crate::utils::terminal::OutputStream;
+use crate::utils::workdir;
+use std::env;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+pub struct Cd;
+
+impl super::Command for Cd {
+    fn execute(
+        &self,
+        args: &[String],
+        _stdin: &mut dyn Read, // cd doesn't need stdin
+        stdout: &mut dyn OutputStream,
+        _stderr: &mut dyn Write, // errors are returned to the executor
+    ) -> Result<()> {
+        let (physical, operands) = self.parse_args(args)?;
+        if operands.len() > 1 {
+            return Err(ShellError::invalid_arguments("cd: too many arguments"));
+        }
+
+        // Work out where to go, and whether the new directory should be printed
+        let (target, print) = match operands.first().map(String::as_str) {
+            None => (self.env_dir("HOME")?, false),
+            Some("-") => (self.env_dir("OLDPWD")?, true),
+            Some(dir) => match self.search_cdpath(dir) {
+                Some(found) => (found, true),
+                None => (PathBuf::from(dir), false),
+            },
+        };
+
+        let old_pwd = workdir::current().ok();
+
+        let new_pwd = if physical {
+            self.change_dir(&target, &target)?;
+            workdir::physical_cwd()?
+        } else {
+            // -L: apply the path to the logical directory and drop ".." textually,
+            // so "cd .." after entering a symlink returns to where the user came from
+            let logical = match &old_pwd {
+                Some(pwd) if target.is_relative() => workdir::normalize(&pwd.join(&target)),
+                _ => workdir::normalize(&target),
+            };
+            // When that path can't be entered it is an error, as POSIX has it, not a
+            // cue to try the physical one
+            self.change_dir(&logical, &target)?;
+            if logical.is_absolute() {
+                logical
+            } else {
+                workdir::physical_cwd()?
+            }
+        };
+
+        workdir::set_pwd(&new_pwd, old_pwd.as_deref());
+
+        if print {
+            writeln!(stdout, "{}", new_pwd.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl Cd {
+    // Returns whether -P (physical) is in effect, and the remaining operands.
+    // Options stop at the first operand, so "cd -" and "cd dir -P" mean what they say.
+    fn parse_args(&self, args: &[String]) -> Result<(bool, Vec<String>)> {
+        let mut physical = false;
+
+        // The last of -L and -P wins
+        let mut getopt = Getopt::new("cd", args, "+LP", &[]);
+        while let Some(option) = getopt.next_option()? {
+            physical = option.name == "P";
+        }
+
+        Ok((physical, getopt.operands()))
+    }
+
+    // Errors name the directory the way it was asked for
+    fn change_dir(&self, path: &Path, asked_for: &Path) -> Result<()> {
+        env::set_current_dir(path).map_err(|e| {
+            ShellError::execution(&format!(
+                "cd: {}: {}",
+                asked_for.display(),
+                error::describe(&e)
+            ))
+        })
+    }
+
+    fn env_dir(&self, name: &str) -> Result<PathBuf> {
+        match env::var(name) {
+            Ok(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+            _ => Err(ShellError::execution(&format!("cd: {} not set", name))),
+        }
+    }
+
+    // Relative names that don't start with . or .. are looked up in each CDPATH entry.
+    // An empty entry stands for the current directory; only matches found through a
+    // non-empty entry are returned, since those are the ones a shell prints.
+    fn search_cdpath(&self, dir: &str) -> Option<PathBuf> {
+        let path = Path::new(dir);
+        if path.is_absolute() || dir == "." || dir == ".." {
+            return None;
+        }
+        if dir.starts_with("./") || dir.starts_with("../") {
+            return None;
+        }
+
+        let cdpath = env::var("CDPATH").ok()?;
+        for entry in cdpath.split(':') {
+            if entry.is_empty() {
+                if path.is_dir() {
+                    return None;
+                }
+                continue;
+            }
+            let candidate = Path::new(entry).join(path);
+            if candidate.is_dir() {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+}
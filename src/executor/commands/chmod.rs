@@ -0,0 +1,484 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::mode::{self, ModeChange};
+use crate::utils::sys;
+use crate::utils::terminal::OutputStream;
+use std::fs::{self, Permissions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+pub struct Chmod;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("changes", HasArg::No, Some('c')),
+    long("quiet", HasArg::No, Some('f')),
+    long("recursive", HasArg::No, Some('R')),
+    long("silent", HasArg::No, Some('f')),
+    long("verbose", HasArg::No, Some('v')),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Follow {
+    Never,       // -P: with -R, even symlinks given as operands are left alone
+    CommandLine, // -H, the default: symlinks given as operands are followed
+    Always,      // -L: every symlink is followed
+}
+
+struct ChmodOptions {
+    recursive: bool, // -R
+    verbose: bool,   // -v: report every file
+    changes: bool,   // -c: report only files whose mode changed
+    silent: bool,    // -f: keep quiet about most errors
+    follow: Follow,  // -H -L -P, with -R
+    // The mode was written like an option ("-w"), where the umask quietly keeping
+    // some bits is likely a surprise worth reporting
+    dashed_mode: bool,
+}
+
+impl super::Command for Chmod {
+    fn execute(
+        &self,
+        args: &[String],
+        _stdin: &mut dyn Read, // chmod doesn't need stdin
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, spec, operands) = self.parse_args(args)?;
+        let change = ModeChange::parse(&spec).ok_or_else(|| {
+            ShellError::invalid_arguments(&format!("chmod: invalid mode: '{}'", spec))
+        })?;
+
+        let mut chmod = Changer {
+            change: &change,
+            options: &options,
+            umask: sys::current_umask(),
+            status: 0,
+            stdout,
+            stderr,
+        };
+        for operand in &operands {
+            let mut ancestors = Vec::new();
+            chmod.change_mode(operand, true, &mut ancestors)?;
+        }
+
+        chmod.stdout.flush()?;
+        if chmod.status != 0 {
+            return Err(ShellError::status(chmod.status));
+        }
+        Ok(())
+    }
+}
+
+impl Chmod {
+    // The options, the mode and the files
+    fn parse_args(&self, args: &[String]) -> Result<(ChmodOptions, String, Vec<String>)> {
+        let mut options = ChmodOptions {
+            recursive: false,
+            verbose: false,
+            changes: false,
+            silent: false,
+            follow: Follow::CommandLine,
+            dashed_mode: false,
+        };
+
+        // A mode such as "-w" or "-rwx,g+s" looks like options, so it's taken out
+        // before they are parsed
+        let mut args = args.to_vec();
+        let end = args
+            .iter()
+            .position(|arg| arg == "--")
+            .unwrap_or(args.len());
+        let mut spec = args[..end]
+            .iter()
+            .position(|arg| is_negative_mode(arg))
+            .map(|index| args.remove(index));
+        options.dashed_mode = spec.is_some();
+
+        let mut getopt = Getopt::new("chmod", &args, "HLPRcfv", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            match option.name {
+                "R" => options.recursive = true,
+                "v" => options.verbose = true,
+                "c" => options.changes = true,
+                "f" => options.silent = true,
+                "H" => options.follow = Follow::CommandLine,
+                "L" => options.follow = Follow::Always,
+                "P" => options.follow = Follow::Never,
+                _ => {}
+            }
+        }
+
+        let mut operands = getopt.operands();
+        if spec.is_none() && !operands.is_empty() {
+            spec = Some(operands.remove(0));
+        }
+        match spec {
+            None => Err(ShellError::invalid_arguments("chmod: missing operand")),
+            Some(spec) if operands.is_empty() => Err(ShellError::invalid_arguments(&format!(
+                "chmod: missing operand after '{}'",
+                spec
+            ))),
+            Some(spec) => Ok((options, spec, operands)),
+        }
+    }
+}
+
+// "-w", "-x,o+t": an argument starting with '-' made only of mode characters
+fn is_negative_mode(arg: &str) -> bool {
+    arg.len() > 1
+        && arg.starts_with('-')
+        && arg[1..]
+            .chars()
+            .all(|c| "rwxXstugoa,+-=01234567".contains(c))
+}
+
+struct Changer<'a> {
+    change: &'a ModeChange,
+    options: &'a ChmodOptions,
+    umask: u32,
+    status: i32,
+    stdout: &'a mut dyn OutputStream,
+    stderr: &'a mut dyn Write,
+}
+
+impl Changer<'_> {
+    // Change one file and, with -R, what's under it. ancestors holds the directories
+    // above, by device and inode, so that following symlinks can't loop forever.
+    fn change_mode(
+        &mut self,
+        path: &str,
+        operand: bool,
+        ancestors: &mut Vec<(u64, u64)>,
+    ) -> io::Result<()> {
+        let follow = match self.options.follow {
+            Follow::Always => true,
+            Follow::CommandLine => operand,
+            // Without -R an operand that is a symlink always means the file it points to
+            Follow::Never => operand && !self.options.recursive,
+        };
+
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                self.not_accessed(path)?;
+                return self.fail("cannot access", path, &e);
+            }
+        };
+        // A symlink's own mode can't be changed and means nothing, so it is
+        // either followed or skipped
+        let metadata = if metadata.file_type().is_symlink() {
+            if !follow {
+                if self.options.verbose {
+                    writeln!(
+                        self.stdout,
+                        "neither symbolic link '{}' nor referent has been changed",
+                        path
+                    )?;
+                }
+                return Ok(());
+            }
+            match fs::metadata(path) {
+                Ok(metadata) => metadata,
+                Err(_) => {
+                    self.not_accessed(path)?;
+                    if !self.options.silent {
+                        writeln!(
+                            self.stderr,
+                            "chmod: cannot operate on dangling symlink '{}'",
+                            path
+                        )?;
+                    }
+                    self.status = 1;
+                    return Ok(());
+                }
+            }
+        } else {
+            metadata
+        };
+
+        let is_dir = metadata.is_dir();
+        let old = metadata.mode() & 0o7777;
+        let new = self.change.apply(old, is_dir, self.umask);
+        if new != old
+            && let Err(e) = fs::set_permissions(path, Permissions::from_mode(new))
+        {
+            self.fail("changing permissions of", path, &e)?;
+        } else {
+            self.report(path, old, new)?;
+            // Clauses with no class are filtered by the umask, which can keep bits
+            // that a "-w" style mode was meant to take away
+            let naive = self.change.apply(old, is_dir, 0);
+            if self.options.dashed_mode && new & !naive != 0 {
+                writeln!(
+                    self.stderr,
+                    "chmod: {}: new permissions are {}, not {}",
+                    path,
+                    mode::permission_string(new),
+                    mode::permission_string(naive)
+                )?;
+                self.status = 1;
+            }
+        }
+
+        if !self.options.recursive || !is_dir {
+            return Ok(());
+        }
+        let id = (metadata.dev(), metadata.ino());
+        if ancestors.contains(&id) {
+            if !self.options.silent {
+                writeln!(self.stderr, "chmod: '{}': File system loop detected", path)?;
+            }
+            self.status = 1;
+            return Ok(());
+        }
+
+        let names = fs::read_dir(path).and_then(|entries| {
+            let mut names = entries
+                .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+                .collect::<io::Result<Vec<_>>>()?;
+            names.sort();
+            Ok(names)
+        });
+        let names = match names {
+            Ok(names) => names,
+            Err(e) => return self.fail("cannot read directory", path, &e),
+        };
+        ancestors.push(id);
+        for name in names {
+            let child = if path.ends_with('/') {
+                format!("{}{}", path, name)
+            } else {
+                format!("{}/{}", path, name)
+            };
+            self.change_mode(&child, false, ancestors)?;
+        }
+        ancestors.pop();
+        Ok(())
+    }
+
+    // -v reports every file, -c only the ones that changed
+    fn report(&mut self, path: &str, old: u32, new: u32) -> io::Result<()> {
+        if new != old && (self.options.verbose || self.options.changes) {
+            writeln!(
+                self.stdout,
+                "mode of '{}' changed from {:04o} ({}) to {:04o} ({})",
+                path,
+                old,
+                mode::permission_string(old),
+                new,
+                mode::permission_string(new)
+            )?;
+        } else if new == old && self.options.verbose {
+            writeln!(
+                self.stdout,
+                "mode of '{}' retained as {:04o} ({})",
+                path,
+                old,
+                mode::permission_string(old)
+            )?;
+        }
+        Ok(())
+    }
+
+    // -v also mentions, on standard output, files it couldn't get to
+    fn not_accessed(&mut self, path: &str) -> io::Result<()> {
+        if self.options.verbose {
+            writeln!(self.stdout, "'{}' could not be accessed", path)?;
+        }
+        Ok(())
+    }
+
+    fn fail(&mut self, action: &str, path: &str, e: &io::Error) -> io::Result<()> {
+        if !self.options.silent {
+            writeln!(
+                self.stderr,
+                "chmod: {} '{}': {}",
+                action,
+                path,
+                error::describe(e)
+            )?;
+        }
+        self.status = 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use crate::utils::scratch::Scratch;
+    use std::os::unix::fs::symlink;
+
+    // Run chmod, giving its stdout, stderr and status
+    fn chmod(args: &[&str]) -> (String, String, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = match Chmod.execute(&args, &mut "".as_bytes(), &mut stdout, &mut stderr) {
+            Ok(()) => 0,
+            Err(e) => e.status_code(),
+        };
+        (
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+            status,
+        )
+    }
+
+    fn mode_of(path: &std::path::Path) -> u32 {
+        fs::metadata(path).unwrap().mode() & 0o7777
+    }
+
+    fn set_mode(path: &std::path::Path, mode: u32) {
+        fs::set_permissions(path, Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn recursive_execute_if_any() {
+        let dir = Scratch::new("chmod-x", &["d/sub/", "d/plain", "d/script"]);
+        set_mode(&dir.path("d/plain"), 0o600);
+        set_mode(&dir.path("d/script"), 0o700);
+        set_mode(&dir.path("d/sub"), 0o700);
+        assert_eq!(chmod(&["-R", "go=rX", &dir.arg("d")]).2, 0);
+        assert_eq!(mode_of(&dir.path("d/plain")), 0o644);
+        assert_eq!(mode_of(&dir.path("d/script")), 0o755);
+        assert_eq!(mode_of(&dir.path("d/sub")), 0o755);
+    }
+
+    #[test]
+    fn symlinks_under_recursion() {
+        let dir = Scratch::new("chmod-links", &["d/f", "outside/g"]);
+        symlink("../outside", dir.path("d/link")).unwrap();
+        symlink("d", dir.path("operand")).unwrap();
+        let reset = || {
+            set_mode(&dir.path("d/f"), 0o600);
+            set_mode(&dir.path("outside/g"), 0o600);
+        };
+
+        // By default (-H) a symlink met on the way down is skipped, and -v says so
+        reset();
+        let (stdout, _, status) = chmod(&["-Rv", "644", &dir.arg("d")]);
+        assert_eq!(status, 0);
+        assert!(stdout.contains(&format!(
+            "neither symbolic link '{}' nor referent has been changed\n",
+            dir.arg("d/link")
+        )));
+        assert_eq!(mode_of(&dir.path("d/f")), 0o644);
+        assert_eq!(mode_of(&dir.path("outside/g")), 0o600);
+
+        // ...but one given as an operand is followed, unless -P
+        reset();
+        assert_eq!(chmod(&["-R", "644", &dir.arg("operand")]).2, 0);
+        assert_eq!(mode_of(&dir.path("d/f")), 0o644);
+        reset();
+        assert_eq!(chmod(&["-RP", "644", &dir.arg("operand")]).2, 0);
+        assert_eq!(mode_of(&dir.path("d/f")), 0o600);
+        // Without -R there is no recursion for -P to apply to
+        assert_eq!(chmod(&["-P", "640", &dir.arg("d/link/g")]).2, 0);
+        assert_eq!(mode_of(&dir.path("outside/g")), 0o640);
+
+        // -L follows them all
+        reset();
+        assert_eq!(chmod(&["-RL", "644", &dir.arg("d")]).2, 0);
+        assert_eq!(mode_of(&dir.path("outside/g")), 0o644);
+    }
+
+    #[test]
+    fn symlink_loops_and_dangling_links() {
+        let dir = Scratch::new("chmod-loops", &["d/f"]);
+        symlink(".", dir.path("d/loop")).unwrap();
+        let (_, stderr, status) = chmod(&["-RL", "755", &dir.arg("d")]);
+        assert_eq!(
+            stderr,
+            format!(
+                "chmod: '{}': File system loop detected\n",
+                dir.arg("d/loop")
+            )
+        );
+        assert_eq!(status, 1);
+        assert_eq!(mode_of(&dir.path("d/f")), 0o755);
+
+        symlink("missing", dir.path("dangling")).unwrap();
+        let (_, stderr, status) = chmod(&["644", &dir.arg("dangling")]);
+        assert_eq!(
+            stderr,
+            format!(
+                "chmod: cannot operate on dangling symlink '{}'\n",
+                dir.arg("dangling")
+            )
+        );
+        assert_eq!(status, 1);
+        assert_eq!(
+            chmod(&["-f", "644", &dir.arg("dangling")]),
+            (String::new(), String::new(), 1)
+        );
+    }
+
+    #[test]
+    fn umask_without_a_class() {
+        let dir = Scratch::new("chmod-umask", &["f"]);
+        let path = dir.arg("f");
+        let options = |dashed_mode| ChmodOptions {
+            recursive: false,
+            verbose: false,
+            changes: true,
+            silent: false,
+            follow: Follow::CommandLine,
+            dashed_mode,
+        };
+        // The umask is fixed here rather than read from the process
+        let run = |spec: &str, dashed_mode| {
+            let change = ModeChange::parse(spec).unwrap();
+            let options = options(dashed_mode);
+            let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+            let mut changer = Changer {
+                change: &change,
+                options: &options,
+                umask: 0o022,
+                status: 0,
+                stdout: &mut stdout,
+                stderr: &mut stderr,
+            };
+            changer.change_mode(&path, true, &mut Vec::new()).unwrap();
+            let status = changer.status;
+            (
+                String::from_utf8(stdout).unwrap(),
+                String::from_utf8(stderr).unwrap(),
+                status,
+            )
+        };
+
+        set_mode(&dir.path("f"), 0o600);
+        assert_eq!(
+            run("+x", false),
+            (
+                format!(
+                    "mode of '{}' changed from 0600 (rw-------) to 0711 (rwx--x--x)\n",
+                    path
+                ),
+                String::new(),
+                0
+            )
+        );
+        assert_eq!(mode_of(&dir.path("f")), 0o711);
+
+        // A mode written like an option warns when the umask kept bits it would remove
+        set_mode(&dir.path("f"), 0o666);
+        let (_, stderr, status) = run("-w", true);
+        assert_eq!(mode_of(&dir.path("f")), 0o466);
+        assert_eq!(
+            stderr,
+            format!(
+                "chmod: {}: new permissions are r--rw-rw-, not r--r--r--\n",
+                path
+            )
+        );
+        assert_eq!(status, 1);
+        // The same mode after "--" isn't dashed, and with a class nothing is kept
+        set_mode(&dir.path("f"), 0o666);
+        assert_eq!(run("-w", false).2, 0);
+        set_mode(&dir.path("f"), 0o666);
+        assert_eq!(run("a-w", true).2, 0);
+        assert_eq!(mode_of(&dir.path("f")), 0o444);
+    }
+}
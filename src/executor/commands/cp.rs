@@ -0,0 +1,277 @@
+use crate::utils::copy::{self, CopyOptions};
+use crate::utils::error::{Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::overwrite;
+use crate::utils::terminal::OutputStream;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+pub struct Cp;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("archive", HasArg::No, Some('a')),
+    long("dereference", HasArg::No, Some('L')),
+    long("no-dereference", HasArg::No, Some('P')),
+    long("preserve", HasArg::No, Some('p')),
+    long("recursive", HasArg::No, Some('r')),
+];
+
+impl super::Command for Cp {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        _stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, mut operands) = self.parse_args(args)?;
+        match operands.len() {
+            0 => return Err(ShellError::invalid_arguments("cp: missing file operand")),
+            1 => {
+                return Err(ShellError::invalid_arguments(&format!(
+                    "cp: missing destination file operand after '{}'",
+                    operands[0]
+                )));
+            }
+            _ => {}
+        }
+
+        let dest = PathBuf::from(operands.pop().unwrap_or_default());
+        let into_dir = dest.is_dir();
+        if operands.len() > 1 && !into_dir {
+            return Err(ShellError::execution(&format!(
+                "cp: target '{}' is not a directory",
+                dest.display()
+            )));
+        }
+
+        let mut ok = true;
+        for operand in &operands {
+            let src = Path::new(operand);
+            // "cp a dir" puts the copy inside dir, under the source's own name
+            let target = match src.file_name() {
+                Some(name) if into_dir => dest.join(name),
+                _ => dest.clone(),
+            };
+            ok &= self.copy_one(src, &target, &options, stdin, stderr)?;
+        }
+
+        if !ok {
+            return Err(ShellError::status(1));
+        }
+        Ok(())
+    }
+}
+
+impl Cp {
+    fn parse_args(&self, args: &[String]) -> Result<(CopyOptions, Vec<String>)> {
+        let mut options = CopyOptions::default();
+        // None until -L or -P is given; the default then depends on -r
+        let mut dereference = None;
+
+        let mut getopt = Getopt::new("cp", args, "aLPpRr", LONG_OPTIONS)
+            .with(overwrite::SHORT_OPTIONS, overwrite::LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            if options.overwrite.apply(&option, "cp")? {
+                continue;
+            }
+            match option.name {
+                "r" | "R" => options.recursive = true,
+                "p" => options.preserve = true,
+                "L" => dereference = Some(true),
+                "P" => dereference = Some(false),
+                "a" => {
+                    options.recursive = true;
+                    options.preserve = true;
+                    dereference = Some(false);
+                }
+                _ => {}
+            }
+        }
+
+        // A recursive copy keeps symlinks as symlinks unless told otherwise
+        options.dereference = dereference.unwrap_or(!options.recursive);
+        Ok((options, getopt.operands()))
+    }
+
+    // Copy one operand, reporting why it can't be done before anything is touched
+    fn copy_one(
+        &self,
+        src: &Path,
+        target: &Path,
+        options: &CopyOptions,
+        stdin: &mut dyn Read,
+        stderr: &mut dyn Write,
+    ) -> Result<bool> {
+        // A symlink copied as a link replaces the target rather than writing through it,
+        // so only the link itself matters; anything else would be truncated by writing
+        let as_link = !options.dereference && src.is_symlink();
+        let (from, to) = if as_link {
+            (fs::symlink_metadata(src), fs::symlink_metadata(target))
+        } else {
+            (fs::metadata(src), fs::metadata(target))
+        };
+        if let (Ok(from), Ok(to)) = (from, to)
+            && from.dev() == to.dev()
+            && from.ino() == to.ino()
+        {
+            writeln!(
+                stderr,
+                "cp: '{}' and '{}' are the same file",
+                src.display(),
+                target.display()
+            )?;
+            return Ok(false);
+        }
+
+        if options.recursive
+            && fs::metadata(src).is_ok_and(|m| m.is_dir())
+            && !as_link
+            && copy::is_inside(src, target)
+        {
+            writeln!(
+                stderr,
+                "cp: cannot copy a directory, '{}', into itself, '{}'",
+                src.display(),
+                target.display()
+            )?;
+            return Ok(false);
+        }
+
+        Ok(copy::copy(src, target, options, "cp", stdin, stderr)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use crate::utils::scratch::Scratch;
+    use crate::utils::sys;
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    // Run cp, giving its stderr and status
+    fn cp(args: &[&str]) -> (String, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = match Cp.execute(&args, &mut "".as_bytes(), &mut stdout, &mut stderr) {
+            Ok(()) => 0,
+            Err(e) => e.status_code(),
+        };
+        (String::from_utf8(stderr).unwrap(), status)
+    }
+
+    #[test]
+    fn recursive_copies_keep_symlinks() {
+        let dir = Scratch::new("cp-recursive", &["a/sub/", "a/empty/"]);
+        dir.write("a/sub/f", "contents");
+        symlink("sub/f", dir.path("a/link")).unwrap();
+
+        assert_eq!(
+            cp(&["-r", &dir.arg("a"), &dir.arg("b")]),
+            (String::new(), 0)
+        );
+        assert_eq!(dir.read("b/sub/f"), "contents");
+        assert!(dir.path("b/empty").is_dir());
+        assert_eq!(
+            fs::read_link(dir.path("b/link")).unwrap(),
+            Path::new("sub/f")
+        );
+
+        // Copying again goes into the existing directory
+        assert_eq!(
+            cp(&["-r", &dir.arg("a"), &dir.arg("b")]),
+            (String::new(), 0)
+        );
+        assert_eq!(dir.read("b/a/sub/f"), "contents");
+    }
+
+    #[test]
+    fn directories_need_recursion_and_somewhere_else_to_go() {
+        let dir = Scratch::new("cp-directories", &["a/f"]);
+        assert_eq!(
+            cp(&[&dir.arg("a"), &dir.arg("b")]),
+            (
+                format!(
+                    "cp: -r not specified; omitting directory '{}'\n",
+                    dir.arg("a")
+                ),
+                1
+            )
+        );
+        assert_eq!(
+            cp(&["-r", &dir.arg("a"), &dir.arg("a/inside")]),
+            (
+                format!(
+                    "cp: cannot copy a directory, '{}', into itself, '{}'\n",
+                    dir.arg("a"),
+                    dir.arg("a/inside")
+                ),
+                1
+            )
+        );
+        assert!(!dir.path("a/inside").exists());
+        assert_eq!(
+            cp(&[&dir.arg("a/f"), &dir.arg("a/f")]),
+            (
+                format!(
+                    "cp: '{}' and '{}' are the same file\n",
+                    dir.arg("a/f"),
+                    dir.arg("a/f")
+                ),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn symlink_loops_are_cut_under_dereference() {
+        let dir = Scratch::new("cp-loop", &["a/sub/f"]);
+        symlink("..", dir.path("a/sub/up")).unwrap();
+        symlink("f", dir.path("a/sub/same")).unwrap();
+
+        // The link back up is reported and skipped, the rest is copied through links
+        let (stderr, status) = cp(&["-rL", &dir.arg("a"), &dir.arg("b")]);
+        assert_eq!(
+            stderr,
+            format!(
+                "cp: cannot copy cyclic symbolic link '{}'\n",
+                dir.arg("a/sub/up")
+            )
+        );
+        assert_eq!(status, 1);
+        assert!(dir.path("b/sub/f").is_file());
+        assert!(!dir.path("b/sub/same").is_symlink());
+        assert!(dir.path("b/sub/same").is_file());
+        assert!(!dir.path("b/sub/up").exists());
+    }
+
+    #[test]
+    fn preserving_mode_and_times() {
+        let dir = Scratch::new("cp-preserve", &["src"]);
+        fs::set_permissions(dir.path("src"), fs::Permissions::from_mode(0o751)).unwrap();
+        sys::set_times(&dir.path("src"), Some((1_000, 0)), Some((2_000, 500)), true).unwrap();
+
+        assert_eq!(
+            cp(&["-p", &dir.arg("src"), &dir.arg("kept")]),
+            (String::new(), 0)
+        );
+        let kept = fs::metadata(dir.path("kept")).unwrap();
+        assert_eq!(kept.mode() & 0o7777, 0o751);
+        assert_eq!(
+            (kept.atime(), kept.mtime(), kept.mtime_nsec()),
+            (1_000, 2_000, 500)
+        );
+
+        // Without -p the copy is new: its mode is limited by the umask, its times are now
+        assert_eq!(
+            cp(&[&dir.arg("src"), &dir.arg("fresh")]),
+            (String::new(), 0)
+        );
+        let fresh = fs::metadata(dir.path("fresh")).unwrap();
+        assert_eq!(fresh.mode() & 0o777, 0o751 & !sys::current_umask());
+        assert!(fresh.mtime() > 2_000);
+    }
+}
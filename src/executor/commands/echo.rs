@@ -1,5 +1,6 @@
 use std::io::{Read, Write};
 use crate::utils::error::Result;
+use crate::utils::terminal::OutputStream;
 
 pub struct Echo;
 
@@ -8,29 +9,48 @@ impl super::Command for Echo {
         &self, 
         args: &[String], 
         _stdin: &mut dyn Read,  // Echo doesn't need stdin
-        stdout: &mut dyn Write, 
+        stdout: &mut dyn OutputStream, 
         _stderr: &mut dyn Write  // Echo doesn't need stderr
     ) -> Result<()> {
-        if args.is_empty() {
-            writeln!(stdout)?;
-            return Ok(());
+        // Leading -n, -e and -E words are options; anything else, "-x" or "--"
+        // included, is printed as is. That's why echo doesn't go through Getopt.
+        let mut newline = true;
+        let mut escapes = true;
+        let mut words = args.iter().peekable();
+        while let Some(word) = words.next_if(|word| {
+            word.len() > 1 && word.starts_with('-') && word[1..].chars().all(|c| "neE".contains(c))
+        }) {
+            for flag in word.chars().skip(1) {
+                match flag {
+                    'n' => newline = false,
+                    'e' => escapes = true,
+                    _ => escapes = false,
+                }
+            }
         }
 
         // Process each argument and handle escape sequences
         let mut output = String::new();
         let mut first = true;
         
-        for arg in args {
+        for arg in words {
             if !first {
                 output.push(' ');
             }
             
             // Process escape sequences in each argument
-            output.push_str(&self.process_escape_sequences(arg));
+            if escapes {
+                output.push_str(&self.process_escape_sequences(arg));
+            } else {
+                output.push_str(arg);
+            }
             first = false;
         }
         
-        writeln!(stdout, "{}", output)?;
+        if newline {
+            output.push('\n');
+        }
+        write!(stdout, "{}", output)?;
         Ok(())
     }
 }
@@ -39,10 +59,9 @@ impl super::Command for Echo {
 impl Echo {
     fn process_escape_sequences(&self, input: &str) -> String {
         let mut result = String::new();
-        let mut chars = input.chars().peekable();
         let mut escaped = false;
         
-        while let Some(c) = chars.next() {
+        for c in input.chars() {
             if escaped {
                 match c {
                     'n' => result.push('\n'),
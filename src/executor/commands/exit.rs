@@ -0,0 +1,95 @@
+use crate::utils::error::{Result, ShellError};
+use crate::utils::getopt::Getopt;
+use crate::utils::terminal::OutputStream;
+use std::io::{Read, Write};
+
+pub struct Exit;
+
+impl super::Command for Exit {
+    fn execute(
+        &self,
+        args: &[String],
+        _stdin: &mut dyn Read, // exit doesn't need stdin
+        _stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        // A negative status as in "exit -1" is an operand, not an option
+        let args = if args.first().is_some_and(|arg| arg.parse::<i64>().is_ok()) {
+            args.to_vec()
+        } else {
+            let mut getopt = Getopt::new("exit", args, "+", &[]);
+            while getopt.next_option()?.is_some() {}
+            getopt.operands()
+        };
+
+        let code = match args.as_slice() {
+            [] => None,
+            [code] => match code.parse::<i64>() {
+                // Only the low byte of a status survives, so 256 is 0 and -1 is 255
+                Ok(code) => Some((code & 0xff) as i32),
+                Err(_) => {
+                    writeln!(stderr, "exit: {}: numeric argument required", code)?;
+                    Some(2)
+                }
+            },
+            _ => {
+                // Like other shells, stay put rather than exit with a status nobody asked for
+                return Err(ShellError::invalid_arguments("exit: too many arguments"));
+            }
+        };
+
+        // The executor fills in the last status for a bare exit, and main ends the process
+        Err(ShellError::Exit(code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+
+    // Run exit, giving the status it asks the shell to end with (None for the last
+    // command's) and what it wrote to stderr
+    fn exit(args: &[&str]) -> (Option<i32>, String) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut stderr = Vec::new();
+        let code = match Exit.execute(&args, &mut "".as_bytes(), &mut Vec::new(), &mut stderr) {
+            Err(ShellError::Exit(code)) => code,
+            Err(e) => panic!("{}", e),
+            Ok(()) => panic!("exit returned"),
+        };
+        (code, String::from_utf8(stderr).unwrap())
+    }
+
+    #[test]
+    fn statuses() {
+        assert_eq!(exit(&[]), (None, String::new()));
+        assert_eq!(exit(&["3"]), (Some(3), String::new()));
+        assert_eq!(exit(&["--", "4"]), (Some(4), String::new()));
+        // Only the low byte is kept
+        assert_eq!(exit(&["256"]), (Some(0), String::new()));
+        assert_eq!(exit(&["300"]), (Some(44), String::new()));
+        assert_eq!(exit(&["-1"]), (Some(255), String::new()));
+    }
+
+    #[test]
+    fn bad_arguments() {
+        assert_eq!(
+            exit(&["abc"]),
+            (
+                Some(2),
+                "exit: abc: numeric argument required\n".to_string()
+            )
+        );
+        // Too many arguments doesn't exit at all
+        let args = ["1".to_string(), "2".to_string()];
+        match Exit.execute(&args, &mut "".as_bytes(), &mut Vec::new(), &mut Vec::new()) {
+            Err(e @ ShellError::InvalidArguments(_)) => {
+                assert_eq!(e.to_string(), "Invalid arguments: exit: too many arguments");
+                assert_eq!(e.status_code(), 2);
+            }
+            Err(e) => panic!("{}", e),
+            Ok(()) => panic!("exit returned"),
+        }
+    }
+}
@@ -0,0 +1,866 @@
+use crate::executor::executor::Executor;
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::glob;
+use crate::utils::terminal::OutputStream;
+use std::ffi::OsString;
+use std::fs::{self, Metadata};
+use std::io::{Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub struct Find;
+
+// -exec ... {} + runs the command once this many paths have piled up
+const BATCH_SIZE: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Follow {
+    Never,       // -P, the default: symlinks are looked at, not through
+    CommandLine, // -H: only the starting points are followed
+    Always,      // -L
+}
+
+struct FindOptions {
+    follow: Follow,
+    min_depth: usize,  // -mindepth
+    max_depth: usize,  // -maxdepth
+    depth_first: bool, // -depth, or implied by -delete: contents before their directory
+}
+
+// -size, -mtime: "+N" is more than N, "-N" less, "N" exactly
+#[derive(Clone, Copy)]
+enum Relation {
+    Less,
+    Exactly,
+    More,
+}
+
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    True, // options such as -maxdepth, which hold for every file
+    Name {
+        pattern: String,
+        ignore_case: bool,
+    },
+    Type(char),
+    Size {
+        relation: Relation,
+        count: u64,
+        unit: u64,
+    },
+    Mtime {
+        relation: Relation,
+        days: i64,
+    },
+    Newer(SystemTime),
+    Empty,
+    Print,
+    Print0,
+    Delete,
+    // -exec ... ;, or with a batch, -exec ... {} +
+    Exec {
+        command: Vec<String>,
+        batch: Option<usize>,
+    },
+}
+
+// The command of an -exec ... {} + and the paths waiting to be given to it
+struct Batch {
+    command: Vec<String>,
+    paths: Vec<PathBuf>,
+}
+
+impl super::Command for Find {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        self.execute_with(&Executor::new(), args, stdin, stdout, stderr)
+    }
+
+    fn execute_with(
+        &self,
+        executor: &Executor,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let mut options = FindOptions {
+            follow: Follow::Never,
+            min_depth: 0,
+            max_depth: usize::MAX,
+            depth_first: false,
+        };
+
+        // find [-H] [-L] [-P] [path...] [expression]
+        let mut position = 0;
+        while let Some(arg) = args.get(position) {
+            match arg.as_str() {
+                "-H" => options.follow = Follow::CommandLine,
+                "-L" => options.follow = Follow::Always,
+                "-P" => options.follow = Follow::Never,
+                _ => break,
+            }
+            position += 1;
+        }
+        let paths_end = args[position..]
+            .iter()
+            .position(|arg| (arg.starts_with('-') && arg.len() > 1) || arg == "(" || arg == "!")
+            .map_or(args.len(), |offset| position + offset);
+        let mut paths: Vec<String> = args[position..paths_end].to_vec();
+        if paths.is_empty() {
+            paths.push(".".to_string());
+        }
+
+        let mut parser = ExprParser {
+            args: &args[paths_end..],
+            position: 0,
+            options: &mut options,
+            has_action: false,
+            batches: Vec::new(),
+        };
+        let mut expr = parser.parse()?;
+        // Without an action, every file the expression is true for is printed
+        if !parser.has_action {
+            expr = Expr::And(Box::new(expr), Box::new(Expr::Print));
+        }
+        let batches = parser.batches;
+
+        let mut finder = Finder {
+            executor,
+            options: &options,
+            batches,
+            now: SystemTime::now(),
+            status: 0,
+            stdin,
+            stdout,
+            stderr,
+        };
+        for path in &paths {
+            let mut ancestors = Vec::new();
+            finder.visit(Path::new(path), 0, &expr, &mut ancestors)?;
+        }
+        finder.run_batches()?;
+
+        finder.stdout.flush()?;
+        if finder.status != 0 {
+            return Err(ShellError::status(finder.status));
+        }
+        Ok(())
+    }
+}
+
+// Recursive descent over the expression: -o binds looser than -a, which may be
+// left out, and ! binds tightest
+struct ExprParser<'a> {
+    args: &'a [String],
+    position: usize,
+    options: &'a mut FindOptions,
+    has_action: bool,
+    batches: Vec<Batch>,
+}
+
+impl ExprParser<'_> {
+    fn parse(&mut self) -> Result<Expr> {
+        if self.args.is_empty() {
+            return Ok(Expr::True);
+        }
+        let expr = self.parse_or()?;
+        if let Some(arg) = self.peek() {
+            return Err(ShellError::invalid_arguments(&format!(
+                "find: unexpected '{}'",
+                arg
+            )));
+        }
+        Ok(expr)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.args.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        let arg = self.args.get(self.position)?;
+        self.position += 1;
+        Some(arg)
+    }
+
+    // The argument a primary such as -name takes
+    fn argument(&mut self, primary: &str) -> Result<String> {
+        self.next().map(str::to_string).ok_or_else(|| {
+            ShellError::invalid_arguments(&format!("find: missing argument to '{}'", primary))
+        })
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut left = self.parse_and()?;
+        while matches!(self.peek(), Some("-o" | "-or")) {
+            self.position += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                None | Some("-o" | "-or" | ")") => return Ok(left),
+                Some("-a" | "-and") => self.position += 1,
+                Some(_) => {}
+            }
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some("!" | "-not") => {
+                self.position += 1;
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Some("(") => {
+                self.position += 1;
+                if self.peek() == Some(")") {
+                    return Err(ShellError::invalid_arguments(
+                        "find: invalid expression; empty parentheses are not allowed.",
+                    ));
+                }
+                let expr = self.parse_or()?;
+                if self.next() != Some(")") {
+                    return Err(ShellError::invalid_arguments(
+                        "find: invalid expression; I was expecting to find a ')' somewhere but did not see one.",
+                    ));
+                }
+                Ok(expr)
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        let previous = self
+            .position
+            .checked_sub(1)
+            .map(|index| self.args[index].clone());
+        let Some(primary) = self.next().map(str::to_string) else {
+            return Err(ShellError::invalid_arguments(&format!(
+                "find: invalid expression; expected something after '{}'",
+                previous.unwrap_or_default()
+            )));
+        };
+
+        Ok(match primary.as_str() {
+            "-name" | "-iname" => Expr::Name {
+                pattern: self.argument(&primary)?,
+                ignore_case: primary == "-iname",
+            },
+            "-type" => {
+                let kind = self.argument(&primary)?;
+                match kind.as_str() {
+                    "f" | "d" | "l" | "p" | "s" | "b" | "c" => {
+                        Expr::Type(kind.chars().next().unwrap_or('f'))
+                    }
+                    _ => {
+                        return Err(ShellError::invalid_arguments(&format!(
+                            "find: Unknown argument to -type: {}",
+                            kind
+                        )));
+                    }
+                }
+            }
+            "-size" => {
+                let size = self.argument(&primary)?;
+                parse_size(&size).ok_or_else(|| {
+                    ShellError::invalid_arguments(&format!(
+                        "find: invalid -size type in '{}'",
+                        size
+                    ))
+                })?
+            }
+            "-mtime" => {
+                let days = self.argument(&primary)?;
+                let (relation, digits) = parse_relation(&days);
+                let days = digits.parse().map_err(|_| {
+                    ShellError::invalid_arguments(&format!(
+                        "find: invalid argument '{}' to '-mtime'",
+                        days
+                    ))
+                })?;
+                Expr::Mtime { relation, days }
+            }
+            "-newer" => {
+                let reference = self.argument(&primary)?;
+                let modified = fs::metadata(&reference).and_then(|metadata| metadata.modified());
+                Expr::Newer(modified.map_err(|e| {
+                    ShellError::invalid_arguments(&format!(
+                        "find: '{}': {}",
+                        reference,
+                        error::describe(&e)
+                    ))
+                })?)
+            }
+            "-maxdepth" | "-mindepth" => {
+                let levels = self.argument(&primary)?;
+                let levels = levels.parse().map_err(|_| {
+                    ShellError::invalid_arguments(&format!(
+                        "find: Expected a positive decimal integer argument to {}, but got '{}'",
+                        primary, levels
+                    ))
+                })?;
+                if primary == "-maxdepth" {
+                    self.options.max_depth = levels;
+                } else {
+                    self.options.min_depth = levels;
+                }
+                Expr::True
+            }
+            "-depth" => {
+                self.options.depth_first = true;
+                Expr::True
+            }
+            "-empty" => Expr::Empty,
+            "-print" => {
+                self.has_action = true;
+                Expr::Print
+            }
+            "-print0" => {
+                self.has_action = true;
+                Expr::Print0
+            }
+            "-delete" => {
+                // A directory can only go once its contents have
+                self.has_action = true;
+                self.options.depth_first = true;
+                Expr::Delete
+            }
+            "-exec" => {
+                self.has_action = true;
+                self.parse_exec()?
+            }
+            _ if primary.starts_with('-') => {
+                return Err(ShellError::invalid_arguments(&format!(
+                    "find: unknown predicate '{}'",
+                    primary
+                )));
+            }
+            _ => {
+                return Err(ShellError::invalid_arguments(&format!(
+                    "find: paths must precede expression: '{}'",
+                    primary
+                )));
+            }
+        })
+    }
+
+    // -exec command ... ; or -exec command ... {} +
+    fn parse_exec(&mut self) -> Result<Expr> {
+        let mut command = Vec::new();
+        loop {
+            let Some(arg) = self.next().map(str::to_string) else {
+                return Err(ShellError::invalid_arguments(
+                    "find: missing argument to '-exec'",
+                ));
+            };
+            if arg == ";" {
+                break;
+            }
+            // "+" only ends the command right after a "{}"
+            if arg == "+" && command.last().is_some_and(|last: &String| last == "{}") {
+                command.pop();
+                if command.is_empty() {
+                    break;
+                }
+                self.batches.push(Batch {
+                    command,
+                    paths: Vec::new(),
+                });
+                return Ok(Expr::Exec {
+                    command: Vec::new(),
+                    batch: Some(self.batches.len() - 1),
+                });
+            }
+            command.push(arg);
+        }
+        if command.is_empty() {
+            return Err(ShellError::invalid_arguments(
+                "find: missing argument to '-exec'",
+            ));
+        }
+        Ok(Expr::Exec {
+            command,
+            batch: None,
+        })
+    }
+}
+
+fn parse_relation(text: &str) -> (Relation, &str) {
+    if let Some(rest) = text.strip_prefix('+') {
+        (Relation::More, rest)
+    } else if let Some(rest) = text.strip_prefix('-') {
+        (Relation::Less, rest)
+    } else {
+        (Relation::Exactly, text)
+    }
+}
+
+// -size [+-]N[cwbkMG], in 512-byte blocks without a unit
+fn parse_size(text: &str) -> Option<Expr> {
+    let (relation, rest) = parse_relation(text);
+    let (digits, unit) = match rest.char_indices().last()? {
+        (index, c) if c.is_ascii_alphabetic() => (&rest[..index], c),
+        _ => (rest, 'b'),
+    };
+    let unit = match unit {
+        'c' => 1,
+        'w' => 2,
+        'b' => 512,
+        'k' => 1024,
+        'M' => 1024 * 1024,
+        'G' => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(Expr::Size {
+        relation,
+        count: digits.parse().ok()?,
+        unit,
+    })
+}
+
+impl Relation {
+    fn holds<T: Ord>(self, value: T, wanted: T) -> bool {
+        match self {
+            Relation::Less => value < wanted,
+            Relation::Exactly => value == wanted,
+            Relation::More => value > wanted,
+        }
+    }
+}
+
+struct Finder<'a> {
+    executor: &'a Executor,
+    options: &'a FindOptions,
+    batches: Vec<Batch>,
+    now: SystemTime,
+    status: i32,
+    stdin: &'a mut dyn Read,
+    stdout: &'a mut dyn OutputStream,
+    stderr: &'a mut dyn Write,
+}
+
+impl Finder<'_> {
+    // Test one file, then everything under it. ancestors holds the directories above
+    // it, by device and inode, so a directory found inside itself is a loop.
+    fn visit(
+        &mut self,
+        path: &Path,
+        depth: usize,
+        expr: &Expr,
+        ancestors: &mut Vec<((u64, u64), PathBuf)>,
+    ) -> Result<()> {
+        let follow = match self.options.follow {
+            Follow::Always => true,
+            Follow::CommandLine => depth == 0,
+            Follow::Never => false,
+        };
+        // A broken symlink being followed is still looked at as a symlink
+        let metadata = if follow {
+            fs::metadata(path).or_else(|_| fs::symlink_metadata(path))
+        } else {
+            fs::symlink_metadata(path)
+        };
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                writeln!(
+                    self.stderr,
+                    "find: '{}': {}",
+                    path.display(),
+                    error::describe(&e)
+                )?;
+                self.status = 1;
+                return Ok(());
+            }
+        };
+
+        let is_dir = metadata.is_dir();
+        let id = (metadata.dev(), metadata.ino());
+        if is_dir && let Some((_, ancestor)) = ancestors.iter().find(|(seen, _)| *seen == id) {
+            writeln!(
+                self.stderr,
+                "find: File system loop detected; '{}' is part of the same file system loop as '{}'.",
+                path.display(),
+                ancestor.display()
+            )?;
+            self.status = 1;
+            return Ok(());
+        }
+
+        let selected = depth >= self.options.min_depth;
+        if selected && !self.options.depth_first {
+            self.evaluate(expr, path, &metadata)?;
+        }
+        if is_dir && depth < self.options.max_depth {
+            match fs::read_dir(path).and_then(|entries| {
+                // Names are kept as they are on disk, UTF-8 or not
+                let mut names = entries
+                    .map(|entry| entry.map(|entry| entry.file_name()))
+                    .collect::<std::io::Result<Vec<OsString>>>()?;
+                names.sort();
+                Ok(names)
+            }) {
+                Ok(names) => {
+                    ancestors.push((id, path.to_path_buf()));
+                    for name in names {
+                        self.visit(&path.join(name), depth + 1, expr, ancestors)?;
+                    }
+                    ancestors.pop();
+                }
+                Err(e) => {
+                    writeln!(
+                        self.stderr,
+                        "find: '{}': {}",
+                        path.display(),
+                        error::describe(&e)
+                    )?;
+                    self.status = 1;
+                }
+            }
+        }
+        if selected && self.options.depth_first {
+            self.evaluate(expr, path, &metadata)?;
+        }
+        Ok(())
+    }
+
+    fn evaluate(&mut self, expr: &Expr, path: &Path, metadata: &Metadata) -> Result<bool> {
+        Ok(match expr {
+            Expr::And(left, right) => {
+                self.evaluate(left, path, metadata)? && self.evaluate(right, path, metadata)?
+            }
+            Expr::Or(left, right) => {
+                self.evaluate(left, path, metadata)? || self.evaluate(right, path, metadata)?
+            }
+            Expr::Not(inner) => !self.evaluate(inner, path, metadata)?,
+            Expr::True => true,
+            Expr::Name {
+                pattern,
+                ignore_case,
+            } => glob::matches(pattern, base_name(&path.to_string_lossy()), *ignore_case),
+            Expr::Type(kind) => {
+                let file_type = metadata.file_type();
+                match kind {
+                    'f' => file_type.is_file(),
+                    'd' => file_type.is_dir(),
+                    'l' => file_type.is_symlink(),
+                    'p' => file_type.is_fifo(),
+                    's' => file_type.is_socket(),
+                    'b' => file_type.is_block_device(),
+                    'c' => file_type.is_char_device(),
+                    _ => false,
+                }
+            }
+            // Sizes are rounded up to whole units, so -size -1M is only empty files
+            Expr::Size {
+                relation,
+                count,
+                unit,
+            } => relation.holds(metadata.len().div_ceil(*unit), *count),
+            Expr::Mtime { relation, days } => {
+                let now = self
+                    .now
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs() as i64);
+                relation.holds((now - metadata.mtime()).div_euclid(86_400), *days)
+            }
+            Expr::Newer(reference) => metadata
+                .modified()
+                .is_ok_and(|modified| modified > *reference),
+            Expr::Empty => {
+                if metadata.is_dir() {
+                    fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+                } else {
+                    metadata.is_file() && metadata.len() == 0
+                }
+            }
+            Expr::Print => {
+                self.stdout.write_all(path.as_os_str().as_bytes())?;
+                self.stdout.write_all(b"\n")?;
+                true
+            }
+            Expr::Print0 => {
+                self.stdout.write_all(path.as_os_str().as_bytes())?;
+                self.stdout.write_all(b"\0")?;
+                true
+            }
+            Expr::Delete => self.delete(path, metadata)?,
+            Expr::Exec {
+                command,
+                batch: None,
+            } => {
+                let path = path.to_string_lossy();
+                let argv: Vec<String> =
+                    command.iter().map(|arg| arg.replace("{}", &path)).collect();
+                self.run(&argv)?
+            }
+            Expr::Exec {
+                batch: Some(index), ..
+            } => {
+                self.batches[*index].paths.push(path.to_path_buf());
+                if self.batches[*index].paths.len() >= BATCH_SIZE {
+                    self.run_batch(*index)?;
+                }
+                true
+            }
+        })
+    }
+
+    fn delete(&mut self, path: &Path, metadata: &Metadata) -> Result<bool> {
+        // The starting directory itself is never removed
+        if path == Path::new(".") {
+            return Ok(true);
+        }
+        let result = if metadata.is_dir() {
+            fs::remove_dir(path)
+        } else {
+            fs::remove_file(path)
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                writeln!(
+                    self.stderr,
+                    "find: cannot delete '{}': {}",
+                    path.display(),
+                    error::describe(&e)
+                )?;
+                self.status = 1;
+                Ok(false)
+            }
+        }
+    }
+
+    // Run a command through the shell, builtins included. Its success is the
+    // result of -exec.
+    fn run(&mut self, argv: &[String]) -> Result<bool> {
+        match self
+            .executor
+            .run_command(argv, self.stdin, self.stdout, self.stderr)
+        {
+            Ok(()) => Ok(true),
+            Err(e @ ShellError::Exit(_)) => Err(e),
+            Err(ShellError::Status(_)) => Ok(false),
+            Err(ShellError::CommandNotFound(name)) => {
+                writeln!(self.stderr, "find: '{}': No such file or directory", name)?;
+                self.status = 1;
+                Ok(false)
+            }
+            Err(e) => {
+                writeln!(self.stderr, "find: {}", e)?;
+                self.status = 1;
+                Ok(false)
+            }
+        }
+    }
+
+    fn run_batch(&mut self, index: usize) -> Result<()> {
+        let paths = std::mem::take(&mut self.batches[index].paths);
+        if paths.is_empty() {
+            return Ok(());
+        }
+        // Commands, builtins included, take their arguments as text
+        let mut argv = self.batches[index].command.clone();
+        argv.extend(paths.iter().map(|path| path.to_string_lossy().into_owned()));
+        // -exec ... + is always true; a failed command shows in find's status
+        if !self.run(&argv)? {
+            self.status = 1;
+        }
+        Ok(())
+    }
+
+    fn run_batches(&mut self) -> Result<()> {
+        for index in 0..self.batches.len() {
+            self.run_batch(index)?;
+        }
+        Ok(())
+    }
+}
+
+// What -name matches against: the last component, ignoring trailing slashes
+fn base_name(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return if path.is_empty() { path } else { "/" };
+    }
+    trimmed.rsplit('/').next().unwrap_or(trimmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use std::os::unix::fs::symlink;
+    use std::{env, process};
+
+    // A fresh tree under the temp directory, removed when the test is done
+    struct Tree(PathBuf);
+
+    impl Tree {
+        fn new(name: &str, files: &[&str]) -> Tree {
+            let root = env::temp_dir().join(format!("find{}-{}", process::id(), name));
+            let _ = fs::remove_dir_all(&root);
+            for file in files {
+                let path = root.join(file);
+                if let Some(directory) = file.strip_suffix('/') {
+                    fs::create_dir_all(root.join(directory)).unwrap();
+                } else {
+                    fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    fs::write(path, "").unwrap();
+                }
+            }
+            Tree(root)
+        }
+
+        // Run find with the tree as its starting point, after any -H, -L or -P,
+        // giving stdout with the tree's path taken out, stderr and the status
+        fn find(&self, args: &[&str]) -> (String, String, i32) {
+            let root = self.0.to_string_lossy().into_owned();
+            let flags = args
+                .iter()
+                .take_while(|arg| matches!(**arg, "-H" | "-L" | "-P"))
+                .count();
+            let args: Vec<String> = args[..flags]
+                .iter()
+                .copied()
+                .chain(std::iter::once(root.as_str()))
+                .chain(args[flags..].iter().copied())
+                .map(str::to_string)
+                .collect();
+            let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+            let status = match Find.execute(&args, &mut "".as_bytes(), &mut stdout, &mut stderr) {
+                Ok(()) => 0,
+                Err(e) => e.status_code(),
+            };
+            let strip = |output: Vec<u8>| String::from_utf8(output).unwrap().replace(&root, "T");
+            (strip(stdout), strip(stderr), status)
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn lines(output: &str) -> Vec<&str> {
+        output.lines().collect()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let tree = Tree::new("precedence", &["a", "b", "sub/a", "sub/b/"]);
+        // -name a -o (-name b -type f)
+        let (stdout, _, status) = tree.find(&["-name", "a", "-o", "-name", "b", "-type", "f"]);
+        assert_eq!(lines(&stdout), ["T/a", "T/b", "T/sub/a"]);
+        assert_eq!(status, 0);
+        let (stdout, _, _) = tree.find(&["(", "-name", "a", "-o", "-name", "b", ")", "-type", "d"]);
+        assert_eq!(lines(&stdout), ["T/sub/b"]);
+        let (stdout, _, _) = tree.find(&["!", "-type", "f", "-a", "-name", "s*"]);
+        assert_eq!(lines(&stdout), ["T/sub"]);
+    }
+
+    #[test]
+    fn print_is_added_around_the_whole_expression() {
+        let tree = Tree::new("print", &["a", "b", "c"]);
+        // Without an action both sides of -o print
+        let (stdout, _, _) = tree.find(&["-name", "a", "-o", "-name", "b"]);
+        assert_eq!(lines(&stdout), ["T/a", "T/b"]);
+        // With one, only what it is reached for does
+        let (stdout, _, _) = tree.find(&["-name", "a", "-o", "-name", "b", "-print"]);
+        assert_eq!(lines(&stdout), ["T/b"]);
+        let (stdout, _, _) = tree.find(&["-name", "c", "-print0"]);
+        assert_eq!(stdout, "T/c\0");
+    }
+
+    #[test]
+    fn exec_plus_runs_the_command_once() {
+        let tree = Tree::new("exec", &["a", "b", "c"]);
+        let (stdout, _, status) = tree.find(&["-type", "f", "-exec", "echo", "x", "{}", "+"]);
+        assert_eq!(lines(&stdout), ["x T/a T/b T/c"]);
+        assert_eq!(status, 0);
+        let (stdout, _, _) = tree.find(&["-type", "f", "-exec", "echo", "x", "{}", ";"]);
+        assert_eq!(lines(&stdout), ["x T/a", "x T/b", "x T/c"]);
+        // A "+" not right after "{}" is an argument like any other
+        let (stdout, _, _) = tree.find(&["-name", "a", "-exec", "echo", "+", ";"]);
+        assert_eq!(lines(&stdout), ["+"]);
+        // -exec ... ; is false when the command fails, -exec ... + makes find fail
+        let (stdout, _, status) = tree.find(&["-name", "a", "-exec", "false", ";", "-o", "-print"]);
+        assert_eq!(lines(&stdout), ["T", "T/a", "T/b", "T/c"]);
+        assert_eq!(status, 0);
+        let (_, _, status) = tree.find(&["-name", "a", "-exec", "false", "{}", "+"]);
+        assert_eq!(status, 1);
+    }
+
+    #[test]
+    fn depth_limits() {
+        let tree = Tree::new("depth", &["a", "sub/b", "sub/deeper/c"]);
+        let (stdout, _, _) = tree.find(&["-maxdepth", "1"]);
+        assert_eq!(lines(&stdout), ["T", "T/a", "T/sub"]);
+        let (stdout, _, _) = tree.find(&["-mindepth", "2"]);
+        assert_eq!(
+            lines(&stdout),
+            ["T/sub/b", "T/sub/deeper", "T/sub/deeper/c"]
+        );
+        let (stdout, _, _) = tree.find(&["-mindepth", "1", "-maxdepth", "1", "-type", "d"]);
+        assert_eq!(lines(&stdout), ["T/sub"]);
+        let (stdout, _, _) = tree.find(&["-maxdepth", "0"]);
+        assert_eq!(lines(&stdout), ["T"]);
+        // -depth lists contents before their directory
+        let (stdout, _, _) = tree.find(&["-depth", "-mindepth", "1"]);
+        assert_eq!(
+            lines(&stdout),
+            ["T/a", "T/sub/b", "T/sub/deeper/c", "T/sub/deeper", "T/sub"]
+        );
+    }
+
+    #[test]
+    fn loops_are_reported_when_following_symlinks() {
+        let tree = Tree::new("loop", &["sub/a"]);
+        symlink("..", tree.0.join("sub/up")).unwrap();
+        // Without -L the link is just a link
+        let (stdout, stderr, status) = tree.find(&[]);
+        assert_eq!(lines(&stdout), ["T", "T/sub", "T/sub/a", "T/sub/up"]);
+        assert_eq!((stderr.as_str(), status), ("", 0));
+
+        let (stdout, stderr, status) = tree.find(&["-L"]);
+        assert_eq!(lines(&stdout), ["T", "T/sub", "T/sub/a"]);
+        assert_eq!(
+            stderr,
+            "find: File system loop detected; 'T/sub/up' is part of the same file system loop as 'T'.\n"
+        );
+        assert_eq!(status, 1);
+    }
+
+    #[test]
+    fn names_that_are_not_utf8() {
+        let tree = Tree::new("bytes", &["d/"]);
+        let name = std::ffi::OsStr::from_bytes(b"a\xffb");
+        fs::write(tree.0.join("d").join(name), "").unwrap();
+        let (_, stderr, status) = tree.find(&["-type", "f", "-delete"]);
+        assert_eq!((stderr.as_str(), status), ("", 0));
+        assert!(fs::read_dir(tree.0.join("d")).unwrap().next().is_none());
+    }
+}
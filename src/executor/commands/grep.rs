@@ -0,0 +1,661 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::regex::{Regex, RegexOptions, Syntax};
+use crate::utils::terminal::OutputStream;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+pub struct Grep;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("after-context", HasArg::Required, Some('A')),
+    long("basic-regexp", HasArg::No, Some('G')),
+    long("before-context", HasArg::Required, Some('B')),
+    long("context", HasArg::Required, Some('C')),
+    long("count", HasArg::No, Some('c')),
+    long("dereference-recursive", HasArg::No, Some('R')),
+    long("extended-regexp", HasArg::No, Some('E')),
+    long("files-with-matches", HasArg::No, Some('l')),
+    long("fixed-strings", HasArg::No, Some('F')),
+    long("ignore-case", HasArg::No, Some('i')),
+    long("invert-match", HasArg::No, Some('v')),
+    long("line-number", HasArg::No, Some('n')),
+    long("line-regexp", HasArg::No, Some('x')),
+    long("no-filename", HasArg::No, Some('h')),
+    long("no-messages", HasArg::No, Some('s')),
+    long("only-matching", HasArg::No, Some('o')),
+    long("quiet", HasArg::No, Some('q')),
+    long("recursive", HasArg::No, Some('r')),
+    long("regexp", HasArg::Required, Some('e')),
+    long("silent", HasArg::No, Some('q')),
+    long("with-filename", HasArg::No, Some('H')),
+    long("word-regexp", HasArg::No, Some('w')),
+];
+
+const STDIN_NAME: &str = "(standard input)";
+
+struct GrepOptions {
+    regex: RegexOptions,         // -E -F -G, -i, -w, -x
+    patterns: Vec<String>,       // -e, or the first operand
+    invert: bool,                // -v
+    line_numbers: bool,          // -n
+    count: bool,                 // -c
+    files_with_matches: bool,    // -l
+    only_matching: bool,         // -o
+    quiet: bool,                 // -q
+    no_messages: bool,           // -s
+    recursive: bool,             // -r
+    dereference: bool,           // -R: -r, following every symlink
+    with_filename: Option<bool>, // -H or -h, otherwise only with several files
+    after: usize,                // -A
+    before: usize,               // -B
+}
+
+// What has been found so far, across every input
+#[derive(Default)]
+struct Progress {
+    selected: bool, // some line was selected
+    errors: bool,   // some input couldn't be read
+    printed: bool,  // some line has been output, so a new group needs "--"
+    done: bool,     // -q found its match: nothing more to read
+}
+
+impl super::Command for Grep {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, operands) = self.parse_args(args)?;
+        let regex = match Regex::new(&options.patterns, &options.regex) {
+            Ok(regex) => regex,
+            Err(message) => {
+                writeln!(stderr, "grep: {}", message)?;
+                return Err(ShellError::status(2));
+            }
+        };
+
+        let mut progress = Progress::default();
+        if operands.is_empty() && options.recursive {
+            // Names found under the working directory are printed without "./"
+            let with_filename = options.with_filename.unwrap_or(true);
+            let mut ancestors = HashSet::new();
+            self.grep_directory(
+                Path::new("."),
+                &regex,
+                &options,
+                with_filename,
+                &mut ancestors,
+                &mut progress,
+                stdout,
+                stderr,
+            )?;
+        } else if operands.is_empty() {
+            let name = options.with_filename.unwrap_or(false).then_some(STDIN_NAME);
+            self.grep_input(
+                STDIN_NAME,
+                name,
+                stdin,
+                &regex,
+                &options,
+                &mut progress,
+                stdout,
+                stderr,
+            )?;
+        }
+
+        for operand in &operands {
+            if progress.done {
+                break;
+            }
+            if operand == "-" {
+                let name = options.with_filename.unwrap_or(operands.len() > 1);
+                let name = name.then_some(STDIN_NAME);
+                self.grep_input(
+                    STDIN_NAME,
+                    name,
+                    stdin,
+                    &regex,
+                    &options,
+                    &mut progress,
+                    stdout,
+                    stderr,
+                )?;
+                continue;
+            }
+
+            let path = Path::new(operand);
+            let is_dir = fs::metadata(path).is_ok_and(|metadata| metadata.is_dir());
+            let with_filename = options
+                .with_filename
+                .unwrap_or(operands.len() > 1 || (options.recursive && is_dir));
+            if is_dir && options.recursive {
+                let mut ancestors = HashSet::new();
+                self.grep_directory(
+                    path,
+                    &regex,
+                    &options,
+                    with_filename,
+                    &mut ancestors,
+                    &mut progress,
+                    stdout,
+                    stderr,
+                )?;
+            } else {
+                self.grep_file(
+                    path,
+                    operand,
+                    &regex,
+                    &options,
+                    with_filename,
+                    &mut progress,
+                    stdout,
+                    stderr,
+                )?;
+            }
+        }
+
+        stdout.flush()?;
+        // A match found with -q counts even when some input failed
+        if progress.selected && (options.quiet || !progress.errors) {
+            Ok(())
+        } else if progress.errors {
+            Err(ShellError::status(2))
+        } else {
+            Err(ShellError::status(1))
+        }
+    }
+}
+
+impl Grep {
+    fn parse_args(&self, args: &[String]) -> Result<(GrepOptions, Vec<String>)> {
+        let mut options = GrepOptions {
+            regex: RegexOptions {
+                syntax: Syntax::Basic,
+                ignore_case: false,
+                whole_words: false,
+                whole_lines: false,
+            },
+            patterns: Vec::new(),
+            invert: false,
+            line_numbers: false,
+            count: false,
+            files_with_matches: false,
+            only_matching: false,
+            quiet: false,
+            no_messages: false,
+            recursive: false,
+            dereference: false,
+            with_filename: None,
+            after: 0,
+            before: 0,
+        };
+        let mut pattern_given = false;
+
+        let mut getopt = Getopt::new("grep", args, "A:B:C:EFGHRce:hilnoqrsvwxy", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            let value = option.value.unwrap_or_default();
+            match option.name {
+                "E" => options.regex.syntax = Syntax::Extended,
+                "F" => options.regex.syntax = Syntax::Fixed,
+                "G" => options.regex.syntax = Syntax::Basic,
+                "e" => {
+                    // A pattern with newlines is one pattern per line
+                    options
+                        .patterns
+                        .extend(value.split('\n').map(str::to_string));
+                    pattern_given = true;
+                }
+                "i" | "y" => options.regex.ignore_case = true,
+                "w" => options.regex.whole_words = true,
+                "x" => options.regex.whole_lines = true,
+                "v" => options.invert = true,
+                "n" => options.line_numbers = true,
+                "c" => options.count = true,
+                "l" => options.files_with_matches = true,
+                "o" => options.only_matching = true,
+                "q" => options.quiet = true,
+                "s" => options.no_messages = true,
+                "r" => options.recursive = true,
+                "R" => {
+                    options.recursive = true;
+                    options.dereference = true;
+                }
+                "H" => options.with_filename = Some(true),
+                "h" => options.with_filename = Some(false),
+                "A" | "B" | "C" => {
+                    let lines = value.parse::<usize>().map_err(|_| {
+                        ShellError::invalid_arguments(&format!(
+                            "grep: {}: invalid context length argument",
+                            value
+                        ))
+                    })?;
+                    if option.name != "B" {
+                        options.after = lines;
+                    }
+                    if option.name != "A" {
+                        options.before = lines;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut operands = getopt.operands();
+        if !pattern_given {
+            if operands.is_empty() {
+                return Err(ShellError::invalid_arguments("grep: missing pattern"));
+            }
+            let pattern = operands.remove(0);
+            options
+                .patterns
+                .extend(pattern.split('\n').map(str::to_string));
+        }
+        Ok((options, operands))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn grep_file(
+        &self,
+        path: &Path,
+        name: &str,
+        regex: &Regex,
+        options: &GrepOptions,
+        with_filename: bool,
+        progress: &mut Progress,
+        stdout: &mut dyn Write,
+        stderr: &mut dyn Write,
+    ) -> io::Result<()> {
+        let file = File::open(path).and_then(|file| {
+            if file.metadata()?.is_dir() {
+                return Err(io::Error::from_raw_os_error(21)); // EISDIR
+            }
+            Ok(file)
+        });
+        match file {
+            Ok(mut file) => {
+                let shown = with_filename.then_some(name);
+                self.grep_input(
+                    name, shown, &mut file, regex, options, progress, stdout, stderr,
+                )
+            }
+            Err(e) => self.report(name, &e, options, progress, stderr),
+        }
+    }
+
+    // Search everything under a directory, in name order. Symlinks met on the way
+    // are skipped, unless -R; then a directory that is its own ancestor is a loop.
+    #[allow(clippy::too_many_arguments)]
+    fn grep_directory(
+        &self,
+        directory: &Path,
+        regex: &Regex,
+        options: &GrepOptions,
+        with_filename: bool,
+        ancestors: &mut HashSet<(u64, u64)>,
+        progress: &mut Progress,
+        stdout: &mut dyn Write,
+        stderr: &mut dyn Write,
+    ) -> io::Result<()> {
+        let id = match fs::metadata(directory) {
+            Ok(metadata) => (metadata.dev(), metadata.ino()),
+            Err(e) => {
+                return self.report(
+                    &directory.display().to_string(),
+                    &e,
+                    options,
+                    progress,
+                    stderr,
+                );
+            }
+        };
+        if !ancestors.insert(id) {
+            if !options.no_messages {
+                writeln!(
+                    stderr,
+                    "grep: {}: warning: recursive directory loop",
+                    directory.display()
+                )?;
+            }
+            return Ok(());
+        }
+
+        let entries = fs::read_dir(directory).and_then(|entries| {
+            let mut paths = entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            paths.sort();
+            Ok(paths)
+        });
+        let entries = match entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                ancestors.remove(&id);
+                return self.report(
+                    &directory.display().to_string(),
+                    &e,
+                    options,
+                    progress,
+                    stderr,
+                );
+            }
+        };
+
+        for path in entries {
+            if progress.done {
+                break;
+            }
+            let path = path
+                .strip_prefix("./")
+                .map(Path::to_path_buf)
+                .unwrap_or(path);
+            let metadata = if options.dereference {
+                fs::metadata(&path)
+            } else {
+                fs::symlink_metadata(&path)
+            };
+            let name = path.display().to_string();
+            match metadata {
+                Ok(metadata) if metadata.is_dir() => {
+                    self.grep_directory(
+                        &path,
+                        regex,
+                        options,
+                        with_filename,
+                        ancestors,
+                        progress,
+                        stdout,
+                        stderr,
+                    )?;
+                }
+                // Devices, FIFOs and sockets are only read when named on the command line
+                Ok(metadata) if metadata.is_file() => {
+                    self.grep_file(
+                        &path,
+                        &name,
+                        regex,
+                        options,
+                        with_filename,
+                        progress,
+                        stdout,
+                        stderr,
+                    )?;
+                }
+                Ok(_) => {}
+                Err(e) => self.report(&name, &e, options, progress, stderr)?,
+            }
+        }
+        ancestors.remove(&id);
+        Ok(())
+    }
+
+    fn report(
+        &self,
+        name: &str,
+        e: &io::Error,
+        options: &GrepOptions,
+        progress: &mut Progress,
+        stderr: &mut dyn Write,
+    ) -> io::Result<()> {
+        progress.errors = true;
+        if !options.no_messages {
+            writeln!(stderr, "grep: {}: {}", name, error::describe(e))?;
+        }
+        Ok(())
+    }
+
+    // Search one input line by line. shown is the name to put before each line, if
+    // any; name is the one for messages.
+    #[allow(clippy::too_many_arguments)]
+    fn grep_input(
+        &self,
+        name: &str,
+        shown: Option<&str>,
+        input: &mut dyn Read,
+        regex: &Regex,
+        options: &GrepOptions,
+        progress: &mut Progress,
+        stdout: &mut dyn Write,
+        stderr: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut reader = BufReader::new(input);
+        // Like GNU grep, a NUL near the start marks a binary file, whose lines
+        // aren't printed
+        let binary = match reader.fill_buf() {
+            Ok(buffer) => buffer.contains(&0),
+            Err(e) => return self.report(name, &e, options, progress, stderr),
+        };
+        let listing = options.quiet || options.count || options.files_with_matches;
+        // Context makes no sense around the pieces -o prints
+        let context = !options.only_matching && (options.before > 0 || options.after > 0);
+
+        let mut before: VecDeque<(u64, Vec<u8>)> = VecDeque::new();
+        let mut after_left = 0;
+        let mut last_printed: Option<u64> = None;
+        let mut count: u64 = 0;
+        let mut number: u64 = 0;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    self.report(name, &e, options, progress, stderr)?;
+                    break;
+                }
+            }
+            number += 1;
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            let text = String::from_utf8_lossy(&line);
+
+            if regex.is_match(&text) == options.invert {
+                if after_left > 0 {
+                    after_left -= 1;
+                    self.print_line(shown, number, '-', &line, options, stdout)?;
+                    last_printed = Some(number);
+                } else if options.before > 0 && context {
+                    before.push_back((number, line.clone()));
+                    if before.len() > options.before {
+                        before.pop_front();
+                    }
+                }
+                continue;
+            }
+
+            count += 1;
+            progress.selected = true;
+            if options.quiet {
+                progress.done = true;
+                return Ok(());
+            }
+            if options.files_with_matches || (binary && !listing) {
+                break;
+            }
+            if listing {
+                continue;
+            }
+
+            if context {
+                // A group that doesn't follow on from the last one gets a separator
+                let first = before.front().map_or(number, |(first, _)| *first);
+                if progress.printed && last_printed.is_none_or(|last| first > last + 1) {
+                    writeln!(stdout, "--")?;
+                }
+                for (context_number, context_line) in before.drain(..) {
+                    self.print_line(shown, context_number, '-', &context_line, options, stdout)?;
+                }
+                after_left = options.after;
+            }
+            if options.only_matching {
+                if !options.invert {
+                    self.print_matches(shown, number, &text, regex, options, stdout)?;
+                }
+            } else {
+                self.print_line(shown, number, ':', &line, options, stdout)?;
+            }
+            last_printed = Some(number);
+            progress.printed = true;
+        }
+
+        if options.count {
+            match shown {
+                Some(shown) => writeln!(stdout, "{}:{}", shown, count)?,
+                None => writeln!(stdout, "{}", count)?,
+            }
+        }
+        if options.files_with_matches && count > 0 {
+            writeln!(stdout, "{}", shown.unwrap_or(name))?;
+        } else if binary && count > 0 && !listing {
+            writeln!(stdout, "Binary file {} matches", name)?;
+        }
+        Ok(())
+    }
+
+    // "name:12:text" for a selected line, "name-12-text" for context
+    fn print_prefix(
+        &self,
+        shown: Option<&str>,
+        number: u64,
+        separator: char,
+        options: &GrepOptions,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        if let Some(shown) = shown {
+            write!(stdout, "{}{}", shown, separator)?;
+        }
+        if options.line_numbers {
+            write!(stdout, "{}{}", number, separator)?;
+        }
+        Ok(())
+    }
+
+    fn print_line(
+        &self,
+        shown: Option<&str>,
+        number: u64,
+        separator: char,
+        line: &[u8],
+        options: &GrepOptions,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.print_prefix(shown, number, separator, options, stdout)?;
+        stdout.write_all(line)?;
+        stdout.write_all(b"\n")
+    }
+
+    // Each non-empty match on its own line
+    fn print_matches(
+        &self,
+        shown: Option<&str>,
+        number: u64,
+        text: &str,
+        regex: &Regex,
+        options: &GrepOptions,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut position = 0;
+        while position <= text.len() {
+            let Some((start, end)) = regex.find_at(text, position) else {
+                break;
+            };
+            if end > start {
+                self.print_prefix(shown, number, ':', options, stdout)?;
+                writeln!(stdout, "{}", &text[start..end])?;
+                position = end;
+            } else {
+                // Step past an empty match so the search moves on
+                position = start + text[start..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+
+    // The exit status, standard output and standard error of grep on the given input
+    fn grep(args: &[&str], input: &str) -> (i32, String, String) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = match Grep.execute(&args, &mut input.as_bytes(), &mut stdout, &mut stderr) {
+            Ok(()) => 0,
+            Err(e) => e.status_code(),
+        };
+        let text = |bytes: Vec<u8>| String::from_utf8(bytes).unwrap();
+        (status, text(stdout), text(stderr))
+    }
+
+    const LINES: &str = "one\nmatch 1\ntwo\nthree\nfour\nmatch 2\nfive\nmatch 3\nsix\n";
+
+    #[test]
+    fn context_groups_are_separated() {
+        let (_, output, _) = grep(&["-A1", "match"], LINES);
+        assert_eq!(output, "match 1\ntwo\n--\nmatch 2\nfive\nmatch 3\nsix\n");
+
+        let (_, output, _) = grep(&["-B1", "-n", "match"], LINES);
+        assert_eq!(
+            output,
+            "1-one\n2:match 1\n--\n5-four\n6:match 2\n7-five\n8:match 3\n"
+        );
+
+        // Groups that touch are merged
+        let (_, output, _) = grep(&["-C2", "match [12]"], LINES);
+        assert_eq!(
+            output,
+            "one\nmatch 1\ntwo\nthree\nfour\nmatch 2\nfive\nmatch 3\n"
+        );
+    }
+
+    #[test]
+    fn only_matching_skips_empty_matches() {
+        let (status, output, _) = grep(&["-o", "b*"], "abba\nxyz\nb\n");
+        assert_eq!(status, 0);
+        assert_eq!(output, "bb\nb\n");
+
+        let (_, output, _) = grep(&["-on", "[0-9]\\+"], "a1b22\nc\n333\n");
+        assert_eq!(output, "1:1\n1:22\n3:333\n");
+    }
+
+    #[test]
+    fn exit_status() {
+        assert_eq!(grep(&["match"], LINES).0, 0);
+        assert_eq!(grep(&["nothing"], LINES), (1, String::new(), String::new()));
+        assert_eq!(
+            grep(&["-c", "nothing"], LINES),
+            (1, "0\n".to_string(), String::new())
+        );
+
+        let (status, _, errors) = grep(&["match", "-", "/nonexistent/file"], LINES);
+        assert_eq!(status, 2);
+        assert_eq!(
+            errors,
+            "grep: /nonexistent/file: No such file or directory\n"
+        );
+        assert_eq!(
+            grep(&["-s", "match", "/nonexistent/file"], ""),
+            (2, String::new(), String::new())
+        );
+
+        // -q succeeds on a match even when another input fails
+        assert_eq!(grep(&["-q", "match", "-", "/nonexistent/file"], LINES).0, 0);
+        assert_eq!(
+            grep(&["-q", "nothing", "-", "/nonexistent/file"], LINES).0,
+            2
+        );
+
+        let (status, _, errors) = grep(&["-E", "(a"], LINES);
+        assert_eq!(status, 2);
+        assert_eq!(errors, "grep: Unmatched ( or \\(\n");
+    }
+}
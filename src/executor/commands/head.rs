@@ -0,0 +1,353 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::size;
+use crate::utils::terminal::OutputStream;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+
+pub struct Head;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("bytes", HasArg::Required, Some('c')),
+    long("lines", HasArg::Required, Some('n')),
+    long("quiet", HasArg::No, Some('q')),
+    long("silent", HasArg::No, Some('q')),
+    long("verbose", HasArg::No, Some('v')),
+];
+
+const BUF_SIZE: usize = 8192;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum Unit {
+    Lines,
+    Bytes,
+}
+
+struct HeadOptions {
+    unit: Unit,            // -n or -c
+    count: u64,            // how many lines or bytes
+    all_but_last: bool,    // -n -N: everything except the last N
+    headers: Option<bool>, // -v or -q, otherwise only with several files
+}
+
+impl super::Command for Head {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, mut operands) = self.parse_args(args)?;
+        if operands.is_empty() {
+            operands.push("-".to_string());
+        }
+        let headers = options.headers.unwrap_or(operands.len() > 1);
+
+        let mut status = 0;
+        let mut first = true;
+        for operand in &operands {
+            let name = if operand == "-" {
+                "standard input"
+            } else {
+                operand
+            };
+            let mut file;
+            let input: &mut dyn Read = if operand == "-" {
+                stdin
+            } else {
+                match File::open(operand) {
+                    Ok(opened) => {
+                        file = opened;
+                        &mut file
+                    }
+                    Err(e) => {
+                        writeln!(
+                            stderr,
+                            "head: cannot open '{}' for reading: {}",
+                            operand,
+                            error::describe(&e)
+                        )?;
+                        status = 1;
+                        continue;
+                    }
+                }
+            };
+
+            if headers {
+                writeln!(stdout, "{}==> {} <==", if first { "" } else { "\n" }, name)?;
+                first = false;
+            }
+            if let Err(e) = self.head(input, &options, stdout) {
+                writeln!(
+                    stderr,
+                    "head: error reading '{}': {}",
+                    name,
+                    error::describe(&e)
+                )?;
+                status = 1;
+            }
+        }
+
+        stdout.flush()?;
+        if status != 0 {
+            return Err(ShellError::status(status));
+        }
+        Ok(())
+    }
+}
+
+impl Head {
+    fn parse_args(&self, args: &[String]) -> Result<(HeadOptions, Vec<String>)> {
+        let mut options = HeadOptions {
+            unit: Unit::Lines,
+            count: 10,
+            all_but_last: false,
+            headers: None,
+        };
+
+        // The old "head -5" form means -n 5
+        let mut args = args.to_vec();
+        if let Some(count) = args.first().and_then(|arg| obsolete_count(arg)) {
+            args.splice(0..1, ["-n".to_string(), count.to_string()]);
+        }
+
+        let mut getopt = Getopt::new("head", &args, "c:n:qv", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            match option.name {
+                "n" | "c" => {
+                    let value = option.value.unwrap_or_default();
+                    options.unit = if option.name == "n" {
+                        Unit::Lines
+                    } else {
+                        Unit::Bytes
+                    };
+                    // +N is the same as N; -N counts back from the end
+                    let (all_but_last, digits) = match value.strip_prefix('-') {
+                        Some(digits) => (true, digits),
+                        None => (false, value.strip_prefix('+').unwrap_or(value)),
+                    };
+                    options.all_but_last = all_but_last;
+                    options.count = size::parse_size(digits).ok_or_else(|| {
+                        ShellError::invalid_arguments(&format!(
+                            "head: invalid number of {}: '{}'",
+                            if options.unit == Unit::Lines {
+                                "lines"
+                            } else {
+                                "bytes"
+                            },
+                            value
+                        ))
+                    })?;
+                }
+                "q" => options.headers = Some(false),
+                "v" => options.headers = Some(true),
+                _ => {}
+            }
+        }
+
+        Ok((options, getopt.operands()))
+    }
+
+    // Read no more than the requested part needs, so a long input is left unread
+    fn head(
+        &self,
+        input: &mut dyn Read,
+        options: &HeadOptions,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        match (options.unit, options.all_but_last) {
+            (Unit::Bytes, false) => {
+                io::copy(&mut input.take(options.count), stdout)?;
+                Ok(())
+            }
+            (Unit::Lines, false) => self.first_lines(input, options.count, stdout),
+            (Unit::Bytes, true) => self.all_but_last_bytes(input, options.count, stdout),
+            (Unit::Lines, true) => self.all_but_last_lines(input, options.count, stdout),
+        }
+    }
+
+    fn first_lines(
+        &self,
+        input: &mut dyn Read,
+        count: u64,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut remaining = count;
+        let mut buffer = [0u8; BUF_SIZE];
+        while remaining > 0 {
+            let read = match input.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            // Up to the newline that ends the last line wanted, or the whole chunk
+            let chunk = &buffer[..read];
+            let mut end = chunk.len();
+            for (index, _) in chunk.iter().enumerate().filter(|&(_, &byte)| byte == b'\n') {
+                remaining -= 1;
+                if remaining == 0 {
+                    end = index + 1;
+                    break;
+                }
+            }
+            stdout.write_all(&chunk[..end])?;
+        }
+        Ok(())
+    }
+
+    // Hold back the last count bytes, writing out whatever falls before them
+    fn all_but_last_bytes(
+        &self,
+        input: &mut dyn Read,
+        count: u64,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+        let mut pending = Vec::new();
+        let mut buffer = [0u8; BUF_SIZE];
+        loop {
+            let read = match input.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            pending.extend_from_slice(&buffer[..read]);
+            if pending.len() > count {
+                let excess = pending.len() - count;
+                stdout.write_all(&pending[..excess])?;
+                pending.drain(..excess);
+            }
+        }
+    }
+
+    fn all_but_last_lines(
+        &self,
+        input: &mut dyn Read,
+        count: u64,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+        let mut reader = BufReader::new(input);
+        let mut pending: VecDeque<Vec<u8>> = VecDeque::new();
+        loop {
+            let mut line = Vec::new();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(());
+            }
+            pending.push_back(line);
+            if pending.len() > count
+                && let Some(line) = pending.pop_front()
+            {
+                stdout.write_all(&line)?;
+            }
+        }
+    }
+}
+
+// "-5" as the first argument, the count of the obsolete "head -5" and "tail -5"
+pub(super) fn obsolete_count(arg: &str) -> Option<&str> {
+    let digits = arg.strip_prefix('-')?;
+    (!digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())).then_some(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use crate::utils::scratch::Scratch;
+
+    // Run head on the given stdin, giving its stdout, stderr and status
+    fn head(args: &[&str], stdin: &str) -> (String, String, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = match Head.execute(&args, &mut stdin.as_bytes(), &mut stdout, &mut stderr) {
+            Ok(()) => 0,
+            Err(e) => e.status_code(),
+        };
+        (
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+            status,
+        )
+    }
+
+    fn output(args: &[&str], stdin: &str) -> String {
+        let (stdout, stderr, status) = head(args, stdin);
+        assert_eq!((stderr.as_str(), status), ("", 0), "head {:?}", args);
+        stdout
+    }
+
+    #[test]
+    fn line_counts() {
+        let input = "1\n2\n3\n4";
+        assert_eq!(output(&["-n", "2"], input), "1\n2\n");
+        assert_eq!(output(&["-2"], input), "1\n2\n");
+        // +N is N from the start, as it is anyway
+        assert_eq!(output(&["-n", "+2"], input), "1\n2\n");
+        assert_eq!(output(&["-n", "-1"], input), "1\n2\n3\n");
+        assert_eq!(output(&["-n", "-10"], input), "");
+        assert_eq!(output(&["-n", "0"], input), "");
+        assert_eq!(output(&[], input), input);
+    }
+
+    #[test]
+    fn byte_counts() {
+        let input = "abcdef\nghi";
+        assert_eq!(output(&["-c", "3"], input), "abc");
+        assert_eq!(output(&["-c", "+3"], input), "abc");
+        assert_eq!(output(&["--bytes=-4"], input), "abcdef");
+        assert_eq!(output(&["-c", "100"], input), input);
+        assert_eq!(output(&["-c", "1k"], &"x".repeat(2000)).len(), 1024);
+        // The last of -n and -c decides the unit
+        assert_eq!(output(&["-c", "2", "-n", "1"], input), "abcdef\n");
+
+        assert_eq!(head(&["-c", "2x"], input).2, 2);
+        match Head.parse_args(&["-c".to_string(), "2x".to_string()]) {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Invalid arguments: head: invalid number of bytes: '2x'"
+            ),
+            Ok(_) => panic!("2x was accepted"),
+        }
+    }
+
+    #[test]
+    fn headers_for_several_files() {
+        let dir = Scratch::new("head-headers", &[]);
+        dir.write("a", "a1\na2\n");
+        dir.write("b", "b1\n");
+        let (stdout, stderr, status) = head(
+            &["-n1", &dir.arg("a"), &dir.arg("missing"), &dir.arg("b")],
+            "",
+        );
+        assert_eq!(
+            stdout,
+            format!(
+                "==> {} <==\na1\n\n==> {} <==\nb1\n",
+                dir.arg("a"),
+                dir.arg("b")
+            )
+        );
+        assert_eq!(
+            stderr,
+            format!(
+                "head: cannot open '{}' for reading: No such file or directory\n",
+                dir.arg("missing")
+            )
+        );
+        assert_eq!(status, 1);
+        assert_eq!(
+            output(&["-q", "-n1", &dir.arg("a"), &dir.arg("b")], ""),
+            "a1\nb1\n"
+        );
+        assert_eq!(
+            output(&["-v", "-n1", "-"], "x\n"),
+            "==> standard input <==\nx\n"
+        );
+    }
+}
@@ -0,0 +1,896 @@
+use crate::utils::colors::{self, LsColors};
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::mode;
+use crate::utils::terminal::{self, OutputStream};
+use crate::utils::time::{self, TimeZone};
+use crate::utils::users;
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::io::{Read, Write};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub struct Ls;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("all", HasArg::No, Some('a')),
+    long("classify", HasArg::No, Some('F')),
+    long("color", HasArg::Optional, None),
+    long("directory", HasArg::No, Some('d')),
+    long("full-time", HasArg::No, None),
+    long("human-readable", HasArg::No, Some('h')),
+    long("recursive", HasArg::No, Some('R')),
+    long("reverse", HasArg::No, Some('r')),
+    long("si", HasArg::No, None),
+    long("time-style", HasArg::Required, None),
+];
+
+#[derive(PartialEq)]
+enum Format {
+    Columns,      // -C, the default on a terminal
+    SingleColumn, // -1, the default everywhere else
+    Long,         // -l
+}
+
+#[derive(Default)]
+enum SortKey {
+    #[default]
+    Name,
+    Time, // -t
+    Size, // -S
+}
+
+#[derive(Clone, Copy)]
+enum ColorMode {
+    Never,
+    Always,
+    Auto, // only when writing to a terminal
+}
+
+// strftime patterns for files older than six months (or in the future) and recent ones
+struct TimeStyle {
+    old: String,
+    recent: String,
+}
+
+impl TimeStyle {
+    fn new(old: &str, recent: &str) -> Self {
+        TimeStyle {
+            old: old.to_string(),
+            recent: recent.to_string(),
+        }
+    }
+
+    // Styles accepted by --time-style; "+FORMAT" may hold a second, recent format after a newline
+    fn parse(style: &str) -> Option<Self> {
+        if let Some(custom) = style.strip_prefix('+') {
+            let (old, recent) = custom.split_once('\n').unwrap_or((custom, custom));
+            return Some(Self::new(old, recent));
+        }
+        // We always run in the C locale, where posix-STYLE means the default style
+        match style {
+            "full-iso" => Some(Self::new(
+                "%Y-%m-%d %H:%M:%S.%N %z",
+                "%Y-%m-%d %H:%M:%S.%N %z",
+            )),
+            "long-iso" => Some(Self::new("%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M")),
+            "iso" => Some(Self::new("%Y-%m-%d ", "%m-%d %H:%M")),
+            "locale" => Some(Self::default()),
+            _ if style.starts_with("posix-") => Some(Self::default()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for TimeStyle {
+    fn default() -> Self {
+        Self::new("%b %e  %Y", "%b %e %H:%M")
+    }
+}
+
+struct LsOptions {
+    format: Format,
+    line_width: usize,
+    sort: SortKey,
+    all: bool,       // -a
+    classify: bool,  // -F
+    recursive: bool, // -R
+    reverse: bool,   // -r
+    directory: bool, // -d
+    colors: Option<LsColors>,
+    human: Option<u64>, // -h (powers of 1024) or --si (powers of 1000)
+    time_style: TimeStyle,
+}
+
+// A single thing to list: the name as it should be printed plus its lstat data
+struct Entry {
+    name: String,
+    path: PathBuf,
+    metadata: Metadata,
+}
+
+// A directory waiting to be listed; command-line operands are reported more severely
+struct PendingDir {
+    entry: Entry,
+    from_command_line: bool,
+}
+
+impl super::Command for Ls {
+    fn execute(
+        &self,
+        args: &[String],
+        _stdin: &mut dyn Read, // ls doesn't need stdin
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, mut operands) = self.parse_args(args, stdout)?;
+        if operands.is_empty() {
+            operands.push(".".to_string());
+        }
+
+        let mut status = 0;
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+
+        // Split operands into plain files (listed together) and directories (listed one by one)
+        for operand in &operands {
+            let path = PathBuf::from(operand);
+            // Without -l or -d a symlink to a directory on the command line is followed
+            let metadata = if options.format == Format::Long || options.directory {
+                fs::symlink_metadata(&path)
+            } else {
+                fs::metadata(&path).or_else(|_| fs::symlink_metadata(&path))
+            };
+
+            match metadata {
+                Ok(metadata) => {
+                    let entry = Entry {
+                        name: operand.clone(),
+                        path,
+                        metadata,
+                    };
+                    if entry.metadata.is_dir() && !options.directory {
+                        dirs.push(entry);
+                    } else {
+                        files.push(entry);
+                    }
+                }
+                Err(e) => {
+                    writeln!(
+                        stderr,
+                        "ls: cannot access '{}': {}",
+                        operand,
+                        error::describe(&e)
+                    )?;
+                    status = 2;
+                }
+            }
+        }
+
+        sort_entries(&mut files, &options);
+        sort_entries(&mut dirs, &options);
+
+        let show_headers = operands.len() > 1 || options.recursive;
+        let mut first = true;
+
+        if !files.is_empty() {
+            self.print_entries(&files, &options, false, stdout)?;
+            first = false;
+        }
+
+        // Directories are listed depth-first, so the stack holds them in reverse order
+        let mut pending: Vec<PendingDir> = dirs
+            .into_iter()
+            .rev()
+            .map(|entry| PendingDir {
+                entry,
+                from_command_line: true,
+            })
+            .collect();
+
+        // (device, inode) of every directory listed for the current operand, so -R
+        // never walks the same directory twice even if the tree contains a cycle
+        let mut visited: HashSet<(u64, u64)> = HashSet::new();
+
+        while let Some(PendingDir {
+            entry: dir,
+            from_command_line,
+        }) = pending.pop()
+        {
+            if from_command_line {
+                visited.clear();
+            }
+
+            if options.recursive && !visited.insert((dir.metadata.dev(), dir.metadata.ino())) {
+                writeln!(
+                    stderr,
+                    "ls: {}: not listing already-listed directory",
+                    dir.name
+                )?;
+                status = 2;
+                continue;
+            }
+
+            if !first {
+                writeln!(stdout)?;
+            }
+            first = false;
+
+            if show_headers {
+                writeln!(stdout, "{}:", dir.name)?;
+            }
+
+            let entries = match self.read_entries(&dir.path, &options) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    writeln!(
+                        stderr,
+                        "ls: cannot open directory '{}': {}",
+                        dir.name,
+                        error::describe(&e)
+                    )?;
+                    status = if from_command_line { 2 } else { status.max(1) };
+                    continue;
+                }
+            };
+
+            self.print_entries(&entries, &options, true, stdout)?;
+
+            if options.recursive {
+                // Entries come from lstat, so symlinks to directories are never descended into
+                for entry in entries.into_iter().rev() {
+                    if !entry.metadata.is_dir() || entry.name == "." || entry.name == ".." {
+                        continue;
+                    }
+                    let name = if dir.name.ends_with('/') {
+                        format!("{}{}", dir.name, entry.name)
+                    } else {
+                        format!("{}/{}", dir.name, entry.name)
+                    };
+                    pending.push(PendingDir {
+                        entry: Entry { name, ..entry },
+                        from_command_line: false,
+                    });
+                }
+            }
+        }
+
+        if status != 0 {
+            return Err(ShellError::status(status));
+        }
+        Ok(())
+    }
+}
+
+impl Ls {
+    fn parse_args(
+        &self,
+        args: &[String],
+        stdout: &dyn OutputStream,
+    ) -> Result<(LsOptions, Vec<String>)> {
+        let mut options = LsOptions {
+            format: if stdout.is_tty() {
+                Format::Columns
+            } else {
+                Format::SingleColumn
+            },
+            line_width: terminal::line_width(stdout),
+            sort: SortKey::Name,
+            all: false,
+            classify: false,
+            recursive: false,
+            reverse: false,
+            directory: false,
+            colors: None,
+            human: None,
+            time_style: TimeStyle::default(),
+        };
+        let mut color_mode = ColorMode::Never;
+
+        // When flags conflict the last one wins
+        let mut getopt = Getopt::new("ls", args, "1aCdFhlRrSt", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            match option.name {
+                "l" => options.format = Format::Long,
+                "1" => options.format = Format::SingleColumn,
+                "C" => options.format = Format::Columns,
+                "a" => options.all = true,
+                "F" => options.classify = true,
+                "R" => options.recursive = true,
+                "r" => options.reverse = true,
+                "d" => options.directory = true,
+                "t" => options.sort = SortKey::Time,
+                "S" => options.sort = SortKey::Size,
+                "h" => options.human = Some(1024),
+                "si" => options.human = Some(1000),
+                "color" => {
+                    color_mode = match option.value {
+                        None | Some("always" | "yes" | "force") => ColorMode::Always,
+                        Some("never" | "no" | "none") => ColorMode::Never,
+                        Some("auto" | "tty" | "if-tty") => ColorMode::Auto,
+                        Some(other) => {
+                            return Err(ShellError::invalid_arguments(&format!(
+                                "ls: invalid argument '{}' for '--color'",
+                                other
+                            )));
+                        }
+                    }
+                }
+                "full-time" => {
+                    options.format = Format::Long;
+                    options.time_style = TimeStyle::parse("full-iso").unwrap_or_default();
+                }
+                "time-style" => {
+                    let style = option.value.unwrap_or_default();
+                    options.time_style = TimeStyle::parse(style).ok_or_else(|| {
+                        ShellError::invalid_arguments(&format!(
+                            "ls: invalid argument '{}' for '--time-style'",
+                            style
+                        ))
+                    })?;
+                }
+                _ => {}
+            }
+        }
+        let operands = getopt.operands();
+
+        // Pipe buffers and redirected files never report a terminal, so auto keeps them clean
+        let use_color = match color_mode {
+            ColorMode::Never => false,
+            ColorMode::Always => true,
+            ColorMode::Auto => stdout.is_tty(),
+        };
+        if use_color {
+            options.colors = Some(LsColors::from_env());
+        }
+
+        Ok((options, operands))
+    }
+
+    // Read the contents of a directory, honouring -a
+    fn read_entries(&self, dir: &Path, options: &LsOptions) -> std::io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+
+        if options.all {
+            for name in [".", ".."] {
+                let path = dir.join(name);
+                let metadata = fs::symlink_metadata(&path)?;
+                entries.push(Entry {
+                    name: name.to_string(),
+                    path,
+                    metadata,
+                });
+            }
+        }
+
+        for dir_entry in fs::read_dir(dir)? {
+            let dir_entry = dir_entry?;
+            let name = dir_entry.file_name().to_string_lossy().into_owned();
+            if !options.all && name.starts_with('.') {
+                continue;
+            }
+
+            // The entry may vanish between readdir and lstat; just skip it then
+            let path = dir_entry.path();
+            if let Ok(metadata) = fs::symlink_metadata(&path) {
+                entries.push(Entry {
+                    name,
+                    path,
+                    metadata,
+                });
+            }
+        }
+
+        sort_entries(&mut entries, options);
+        Ok(entries)
+    }
+
+    fn print_entries(
+        &self,
+        entries: &[Entry],
+        options: &LsOptions,
+        is_directory_listing: bool,
+        stdout: &mut dyn Write,
+    ) -> Result<()> {
+        match options.format {
+            Format::Long => self.print_long(entries, options, is_directory_listing, stdout),
+            Format::Columns => self.print_columns(entries, options, stdout),
+            Format::SingleColumn => {
+                for entry in entries {
+                    writeln!(stdout, "{}", display_name(entry, options).0)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Lay names out top-to-bottom in as many columns as fit the line, like coreutils:
+    // every column but the last is padded to its widest name plus two spaces
+    fn print_columns(
+        &self,
+        entries: &[Entry],
+        options: &LsOptions,
+        stdout: &mut dyn Write,
+    ) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let (names, widths): (Vec<String>, Vec<usize>) =
+            entries.iter().map(|e| display_name(e, options)).unzip();
+
+        // The narrowest possible column is one character plus the two-space gap
+        const MIN_COLUMN_WIDTH: usize = 3;
+        let max_columns = options
+            .line_width
+            .div_ceil(MIN_COLUMN_WIDTH)
+            .clamp(1, names.len());
+        let (rows, column_widths) = (1..=max_columns)
+            .rev()
+            .map(|columns| {
+                let rows = names.len().div_ceil(columns);
+                let mut column_widths = vec![MIN_COLUMN_WIDTH; columns];
+                for (index, width) in widths.iter().enumerate() {
+                    let column = index / rows;
+                    let gap = if column == columns - 1 { 0 } else { 2 };
+                    column_widths[column] = column_widths[column].max(width + gap);
+                }
+                (rows, column_widths)
+            })
+            // Like coreutils, a layout whose columns never grew past the minimum is accepted as is
+            .find(|(_, column_widths)| {
+                column_widths.len() == 1
+                    || column_widths.iter().all(|&w| w == MIN_COLUMN_WIDTH)
+                    || column_widths.iter().sum::<usize>() < options.line_width
+            })
+            .unwrap_or_default();
+
+        for row in 0..rows {
+            let mut line = String::new();
+            let mut position = 0;
+            let mut index = row;
+            for column_width in &column_widths {
+                line.push_str(&names[index]);
+                let name_end = position + widths[index];
+                index += rows;
+                if index >= names.len() {
+                    break;
+                }
+                position += column_width;
+                indent(&mut line, name_end, position);
+            }
+            writeln!(stdout, "{}", line)?;
+        }
+
+        Ok(())
+    }
+
+    fn print_long(
+        &self,
+        entries: &[Entry],
+        options: &LsOptions,
+        is_directory_listing: bool,
+        stdout: &mut dyn Write,
+    ) -> Result<()> {
+        if is_directory_listing {
+            // st_blocks is in 512-byte units, ls reports 1K blocks
+            let blocks: u64 = entries.iter().map(|e| e.metadata.blocks()).sum();
+            let total = match options.human {
+                Some(base) => human_size(blocks * 512, base),
+                None => blocks.div_ceil(2).to_string(),
+            };
+            writeln!(stdout, "total {}", total)?;
+        }
+
+        let db = users::system();
+        let rows: Vec<[String; 4]> = entries
+            .iter()
+            .map(|e| {
+                [
+                    e.metadata.nlink().to_string(),
+                    db.user_name(e.metadata.uid()),
+                    db.group_name(e.metadata.gid()),
+                    size_field(&e.metadata, options.human),
+                ]
+            })
+            .collect();
+
+        let width = |column: usize| {
+            rows.iter()
+                .map(|r| r[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        };
+        let (links_w, owner_w, group_w, size_w) = (width(0), width(1), width(2), width(3));
+
+        let now = SystemTime::now();
+        for (entry, row) in entries.iter().zip(&rows) {
+            let mut line = format!(
+                "{} {:>links_w$} {:<owner_w$} {:<group_w$} {:>size_w$} {} {}",
+                mode_string(&entry.metadata),
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                format_time(entry.metadata.modified().ok(), &options.time_style, now),
+                paint_name(entry, options),
+            );
+
+            if entry.metadata.file_type().is_symlink() {
+                if let Ok(target) = fs::read_link(&entry.path) {
+                    let target = target.to_string_lossy();
+                    let target_metadata = fs::metadata(&entry.path);
+                    line.push_str(" -> ");
+
+                    // The target is coloured by what it points to, or as missing
+                    let style = options
+                        .colors
+                        .as_ref()
+                        .and_then(|colors| match &target_metadata {
+                            Ok(metadata) => colors.style(&target, &entry.path, metadata),
+                            Err(_) => colors.missing(),
+                        });
+                    line.push_str(&colors::paint(style, &target));
+
+                    // -F classifies what the link points to
+                    if options.classify
+                        && let Ok(target_metadata) = &target_metadata
+                    {
+                        line.extend(indicator(target_metadata));
+                    }
+                }
+            } else if options.classify {
+                line.extend(indicator(&entry.metadata));
+            }
+
+            writeln!(stdout, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Names compare byte by byte (like the C locale) so output is reproducible anywhere;
+// -t and -S fall back to the name when their keys tie
+fn sort_entries(entries: &mut [Entry], options: &LsOptions) {
+    entries.sort_by(|a, b| {
+        let by_name = a.name.as_bytes().cmp(b.name.as_bytes());
+        let ordering = match options.sort {
+            SortKey::Name => by_name,
+            SortKey::Time => {
+                let a_time = (a.metadata.mtime(), a.metadata.mtime_nsec());
+                let b_time = (b.metadata.mtime(), b.metadata.mtime_nsec());
+                b_time.cmp(&a_time).then(by_name)
+            }
+            SortKey::Size => b.metadata.len().cmp(&a.metadata.len()).then(by_name),
+        };
+        if options.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+// Pad from one screen column to another the way coreutils does, using a tab
+// whenever it lands exactly on or before the next 8-column tab stop
+fn indent(line: &mut String, mut from: usize, to: usize) {
+    const TAB_SIZE: usize = 8;
+    while from < to {
+        if to / TAB_SIZE > (from + 1) / TAB_SIZE {
+            line.push('\t');
+            from += TAB_SIZE - from % TAB_SIZE;
+        } else {
+            line.push(' ');
+            from += 1;
+        }
+    }
+}
+
+// The name as printed in short listings, along with how many columns it takes up.
+// The -F indicator sits outside the colour sequence, as in coreutils.
+fn display_name(entry: &Entry, options: &LsOptions) -> (String, usize) {
+    let mut name = paint_name(entry, options);
+    let mut width = terminal::display_width(&entry.name);
+    if options.classify
+        && let Some(c) = indicator(&entry.metadata)
+    {
+        name.push(c);
+        width += 1;
+    }
+    (name, width)
+}
+
+fn paint_name(entry: &Entry, options: &LsOptions) -> String {
+    let style = options
+        .colors
+        .as_ref()
+        .and_then(|colors| colors.style(&entry.name, &entry.path, &entry.metadata));
+    colors::paint(style, &entry.name)
+}
+
+// The -F suffix for a file type: / * @ | =
+fn indicator(metadata: &Metadata) -> Option<char> {
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        Some('/')
+    } else if file_type.is_symlink() {
+        Some('@')
+    } else if file_type.is_fifo() {
+        Some('|')
+    } else if file_type.is_socket() {
+        Some('=')
+    } else if file_type.is_file() && metadata.mode() & 0o111 != 0 {
+        Some('*')
+    } else {
+        None
+    }
+}
+
+// Build "drwxr-xr-x" style permission strings, including setuid/setgid/sticky bits
+fn mode_string(metadata: &Metadata) -> String {
+    let file_type = metadata.file_type();
+    let mode = metadata.mode();
+
+    let type_char = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_block_device() {
+        'b'
+    } else if file_type.is_char_device() {
+        'c'
+    } else {
+        '-'
+    };
+
+    format!("{}{}", type_char, mode::permission_string(mode))
+}
+
+// Device files show "major, minor" instead of a byte size
+fn size_field(metadata: &Metadata, human: Option<u64>) -> String {
+    let file_type = metadata.file_type();
+    if file_type.is_block_device() || file_type.is_char_device() {
+        let rdev = metadata.rdev();
+        let major = ((rdev >> 8) & 0xfff) | ((rdev >> 32) & !0xfff);
+        let minor = (rdev & 0xff) | ((rdev >> 12) & !0xff);
+        format!("{}, {}", major, minor)
+    } else if let Some(base) = human {
+        human_size(metadata.len(), base)
+    } else {
+        metadata.len().to_string()
+    }
+}
+
+// Sizes like "4.0K" or "12M", always rounded up as coreutils does: one decimal
+// below ten, whole numbers above. --si uses powers of 1000 and a lowercase "k".
+fn human_size(size: u64, base: u64) -> String {
+    let suffixes: &[char] = if base == 1000 {
+        &['k', 'M', 'G', 'T', 'P', 'E', 'Z', 'Y']
+    } else {
+        &['K', 'M', 'G', 'T', 'P', 'E', 'Z', 'Y']
+    };
+    if size < base {
+        return size.to_string();
+    }
+
+    let size = size as u128;
+    let base = base as u128;
+    let mut exponent = 0;
+    let mut divisor = 1u128;
+    while size >= divisor * base && exponent < suffixes.len() {
+        divisor *= base;
+        exponent += 1;
+    }
+
+    let tenths = (size * 10).div_ceil(divisor);
+    if tenths < 100 {
+        return format!("{}.{}{}", tenths / 10, tenths % 10, suffixes[exponent - 1]);
+    }
+
+    let whole = size.div_ceil(divisor);
+    if whole >= base && exponent < suffixes.len() {
+        // Rounding up spilled into the next unit
+        format!("1.0{}", suffixes[exponent])
+    } else {
+        format!("{}{}", whole, suffixes[exponent - 1])
+    }
+}
+
+// Recent files show the recent format, everything else the old-file format
+fn format_time(time: Option<SystemTime>, style: &TimeStyle, now: SystemTime) -> String {
+    let Some(time) = time else {
+        return "?".repeat(12);
+    };
+
+    let recent = time::is_recent(time::split_time(time), time::split_time(now));
+    let local = TimeZone::local().to_local(time);
+    local.format(if recent { &style.recent } else { &style.old })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use crate::utils::scratch::Scratch;
+    use crate::utils::sys;
+    use std::fs::Permissions;
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    fn ls(args: &[&str]) -> (String, String, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = match Ls.execute(&args, &mut "".as_bytes(), &mut stdout, &mut stderr) {
+            Ok(()) => 0,
+            Err(e) => e.status_code(),
+        };
+        (
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+            status,
+        )
+    }
+
+    #[test]
+    fn long_format() {
+        let dir = Scratch::new("ls-long", &["d/"]);
+        dir.write("a", "hello");
+        dir.write("bb", &"x".repeat(12345));
+        dir.write("s", "");
+        symlink("a", dir.path("link")).unwrap();
+        for (name, mode) in [("a", 0o644), ("bb", 0o755), ("d", 0o755), ("s", 0o4644)] {
+            fs::set_permissions(dir.path(name), Permissions::from_mode(mode)).unwrap();
+        }
+        for name in ["a", "bb", "d", "s", "link"] {
+            sys::set_times(&dir.path(name), None, Some((1_000_000_000, 0)), false).unwrap();
+        }
+
+        let root = dir.arg("");
+        let (stdout, stderr, status) = ls(&["-lF", "--time-style=+%Y", &root]);
+        assert_eq!((stderr.as_str(), status), ("", 0));
+
+        let metadata = fs::metadata(dir.root()).unwrap();
+        let db = users::system();
+        let (owner, group) = (db.user_name(metadata.uid()), db.group_name(metadata.gid()));
+        let dir_size = fs::metadata(dir.path("d")).unwrap().len();
+        let mut lines = stdout.lines();
+        assert!(lines.next().unwrap().starts_with("total "));
+        // Counts and sizes line up on the right, names on the left
+        assert_eq!(
+            lines.collect::<Vec<_>>(),
+            [
+                format!("-rw-r--r-- 1 {owner} {group}     5 2001 a"),
+                format!("-rwxr-xr-x 1 {owner} {group} 12345 2001 bb*"),
+                format!("drwxr-xr-x 2 {owner} {group} {dir_size:>5} 2001 d/"),
+                format!("lrwxrwxrwx 1 {owner} {group}     1 2001 link -> a"),
+                format!("-rwSr--r-- 1 {owner} {group}     0 2001 s"),
+            ]
+        );
+
+        // -l shows a symlink operand itself rather than the directory it may point to
+        let (stdout, _, _) = ls(&["-lF", "--time-style=+%Y", &dir.arg("link")]);
+        assert!(stdout.ends_with(&format!("2001 {} -> a\n", dir.arg("link"))));
+    }
+
+    #[test]
+    fn recursive_listings_do_not_follow_symlinks() {
+        let dir = Scratch::new("ls-recursive", &["a", "sub/inner/f"]);
+        // A link back up would make -R endless if it were descended into
+        symlink("..", dir.path("sub/up")).unwrap();
+        let root = dir.arg("");
+        let (stdout, stderr, status) = ls(&["-R", &root]);
+        assert_eq!(
+            stdout.replace(&root, "T/"),
+            "T/:\na\nsub\n\nT/sub:\ninner\nup\n\nT/sub/inner:\nf\n"
+        );
+        assert_eq!((stderr.as_str(), status), ("", 0));
+
+        // Two operands are listed one after the other, each under its name
+        let (stdout, _, _) = ls(&["-R", &dir.arg("sub/inner"), &dir.arg("sub")]);
+        assert_eq!(
+            stdout.replace(&root, "T/"),
+            "T/sub:\ninner\nup\n\nT/sub/inner:\nf\n\nT/sub/inner:\nf\n"
+        );
+    }
+
+    #[test]
+    fn sort_orders() {
+        let dir = Scratch::new("ls-sort", &[]);
+        for (name, size, mtime) in [("b", 3, 300), ("a", 1, 200), ("c", 2, 100), ("d", 2, 200)] {
+            dir.write(name, &"x".repeat(size));
+            sys::set_times(&dir.path(name), None, Some((mtime, 0)), true).unwrap();
+        }
+        let root = dir.arg("");
+        let listing = |flags: &str| ls(&[flags, &root]).0.lines().collect::<Vec<_>>().join(" ");
+        assert_eq!(listing("-1"), "a b c d");
+        assert_eq!(listing("-1r"), "d c b a");
+        // Ties in time or size fall back to the name
+        assert_eq!(listing("-1t"), "b a d c");
+        assert_eq!(listing("-1S"), "b c d a");
+        assert_eq!(listing("-1Sr"), "a d c b");
+    }
+
+    #[test]
+    fn directories_as_themselves() {
+        let dir = Scratch::new("ls-directory", &["sub/f"]);
+        let (stdout, _, _) = ls(&["-1d", &dir.arg("sub")]);
+        assert_eq!(stdout, format!("{}\n", dir.arg("sub")));
+        // Files on the command line come first, then each directory under a header
+        let (stdout, _, _) = ls(&["-1", &dir.arg("sub"), &dir.arg("sub/f")]);
+        assert_eq!(
+            stdout,
+            format!("{}\n\n{}:\nf\n", dir.arg("sub/f"), dir.arg("sub"))
+        );
+    }
+
+    // Names laid out in columns for a line of the given width
+    fn columns(name: &str, files: &[&str], line_width: usize) -> String {
+        let dir = Scratch::new(name, files);
+        let args = vec!["-C".to_string()];
+        let (mut options, _) = Ls.parse_args(&args, &Vec::new()).unwrap();
+        options.line_width = line_width;
+        let entries = Ls.read_entries(dir.root(), &options).unwrap();
+        let mut stdout = Vec::new();
+        Ls.print_columns(&entries, &options, &mut stdout).unwrap();
+        String::from_utf8(stdout).unwrap()
+    }
+
+    #[test]
+    fn column_layout() {
+        let files = [
+            "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta", "iota", "kappa",
+        ];
+        assert_eq!(
+            columns("ls-columns-wide", &files, 80),
+            "alpha  beta  delta  epsilon  eta  gamma  iota  kappa  theta  zeta\n"
+        );
+        // Filled top to bottom, padded with tabs where coreutils would use them
+        assert_eq!(
+            columns("ls-columns-narrow", &files, 30),
+            "alpha  epsilon\tiota   zeta\nbeta   eta\tkappa\ndelta  gamma\ttheta\n"
+        );
+        assert_eq!(
+            columns("ls-columns-single", &files[..3], 1),
+            "alpha\nbeta\ngamma\n"
+        );
+        // Wide characters take two columns each
+        assert_eq!(
+            columns("ls-columns-cjk", &["日本語", "a", "bb", "ccc", "dddd"], 20),
+            "a   ccc   日本語\nbb  dddd\n"
+        );
+    }
+
+    #[test]
+    fn human_readable_sizes() {
+        assert_eq!(human_size(1023, 1024), "1023");
+        assert_eq!(human_size(1024, 1024), "1.0K");
+        // Always rounded up
+        assert_eq!(human_size(1025, 1024), "1.1K");
+        assert_eq!(human_size(10 * 1024, 1024), "10K");
+        assert_eq!(human_size(10 * 1024 + 1, 1024), "11K");
+        assert_eq!(human_size(1024 * 1024 - 1, 1024), "1.0M");
+        assert_eq!(human_size(1000, 1000), "1.0k");
+        assert_eq!(human_size(999_999, 1000), "1.0M");
+    }
+
+    #[test]
+    fn operand_errors() {
+        let dir = Scratch::new("ls-errors", &["a"]);
+        let (stdout, stderr, status) = ls(&["-1", &dir.arg("a"), &dir.arg("missing")]);
+        assert_eq!(stdout, format!("{}\n", dir.arg("a")));
+        assert_eq!(
+            stderr,
+            format!(
+                "ls: cannot access '{}': No such file or directory\n",
+                dir.arg("missing")
+            )
+        );
+        assert_eq!(status, 2);
+    }
+}
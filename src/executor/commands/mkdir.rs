@@ -0,0 +1,255 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::mode::ModeChange;
+use crate::utils::sys;
+use crate::utils::terminal::OutputStream;
+use std::fs::{self, DirBuilder, Permissions};
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::Path;
+
+pub struct Mkdir;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("mode", HasArg::Required, Some('m')),
+    long("parents", HasArg::No, Some('p')),
+    long("verbose", HasArg::No, Some('v')),
+];
+
+#[derive(Default)]
+struct MkdirOptions {
+    parents: bool,            // -p
+    mode: Option<ModeChange>, // -m
+    verbose: bool,            // -v
+}
+
+impl super::Command for Mkdir {
+    fn execute(
+        &self,
+        args: &[String],
+        _stdin: &mut dyn Read, // mkdir doesn't need stdin
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, operands) = self.parse_args(args)?;
+        if operands.is_empty() {
+            return Err(ShellError::invalid_arguments("mkdir: missing operand"));
+        }
+
+        let umask = sys::current_umask();
+        // -m is relative to a+rwx, and set exactly rather than filtered by the umask
+        let mode = options
+            .mode
+            .as_ref()
+            .map(|change| change.apply(0o777, true, umask));
+
+        // A failed operand is reported and the rest are still created
+        let mut status = 0;
+        for operand in &operands {
+            let path = Path::new(operand);
+            let result = if options.parents {
+                self.create_parents(path, mode, umask, &options, stdout)
+            } else {
+                self.create(path, mode, &options, stdout)
+            };
+            if let Err(e) = result {
+                writeln!(
+                    stderr,
+                    "mkdir: cannot create directory '{}': {}",
+                    operand,
+                    error::describe(&e)
+                )?;
+                status = 1;
+            }
+        }
+
+        if status != 0 {
+            return Err(ShellError::status(status));
+        }
+        Ok(())
+    }
+}
+
+impl Mkdir {
+    fn parse_args(&self, args: &[String]) -> Result<(MkdirOptions, Vec<String>)> {
+        let mut options = MkdirOptions::default();
+
+        let mut getopt = Getopt::new("mkdir", args, "m:pv", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            match option.name {
+                "p" => options.parents = true,
+                "v" => options.verbose = true,
+                "m" => {
+                    let spec = option.value.unwrap_or_default();
+                    let mode = ModeChange::parse(spec).ok_or_else(|| {
+                        ShellError::invalid_arguments(&format!("mkdir: invalid mode '{}'", spec))
+                    })?;
+                    options.mode = Some(mode);
+                }
+                _ => {}
+            }
+        }
+
+        Ok((options, getopt.operands()))
+    }
+
+    fn create(
+        &self,
+        path: &Path,
+        mode: Option<u32>,
+        options: &MkdirOptions,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        DirBuilder::new().mode(mode.unwrap_or(0o777)).create(path)?;
+        // mkdir(2) filters the mode through the umask, so set it again explicitly
+        if let Some(mode) = mode {
+            fs::set_permissions(path, Permissions::from_mode(mode))?;
+        }
+        if options.verbose {
+            writeln!(stdout, "mkdir: created directory '{}'", path.display())?;
+        }
+        Ok(())
+    }
+
+    // -p: create each missing ancestor in turn. Existing directories along the way,
+    // including the operand itself, are fine and keep their modes.
+    fn create_parents(
+        &self,
+        path: &Path,
+        mode: Option<u32>,
+        umask: u32,
+        options: &MkdirOptions,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut ancestors: Vec<&Path> = path
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .collect();
+        ancestors.reverse();
+
+        for (index, ancestor) in ancestors.iter().enumerate() {
+            if ancestor.is_dir() {
+                continue;
+            }
+
+            let last = index + 1 == ancestors.len();
+            let result = if last {
+                self.create(ancestor, mode, options, stdout)
+            } else {
+                // Parents are always made writable and searchable by their owner,
+                // whatever the umask says, so the rest of the path can be created
+                let parent_mode = (0o777 & !umask) | 0o300;
+                self.create(ancestor, Some(parent_mode), options, stdout)
+            };
+
+            match result {
+                Ok(()) => {}
+                // Someone else may have created it in the meantime
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && ancestor.is_dir() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use crate::utils::scratch::Scratch;
+    use std::os::unix::fs::MetadataExt;
+
+    // Run mkdir, giving its stdout, stderr and status
+    fn mkdir(args: &[&str]) -> (String, String, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = match Mkdir.execute(&args, &mut "".as_bytes(), &mut stdout, &mut stderr) {
+            Ok(()) => 0,
+            Err(e) => e.status_code(),
+        };
+        (
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+            status,
+        )
+    }
+
+    fn mode_of(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().mode() & 0o7777
+    }
+
+    #[test]
+    fn parents_are_writable_and_the_mode_is_for_the_last() {
+        let dir = Scratch::new("mkdir-parents", &[]);
+        let umask = sys::current_umask();
+        let (stdout, _, status) = mkdir(&["-pv", "-m", "500", &dir.arg("a/b/c")]);
+        assert_eq!(status, 0);
+        assert_eq!(
+            stdout,
+            format!(
+                "mkdir: created directory '{0}/a'\nmkdir: created directory '{0}/a/b'\n\
+                 mkdir: created directory '{0}/a/b/c'\n",
+                dir.root().display()
+            )
+        );
+        // Parents get the umask's mode, but always u+wx
+        let parent_mode = (0o777 & !umask) | 0o300;
+        assert_eq!(mode_of(&dir.path("a")), parent_mode);
+        assert_eq!(mode_of(&dir.path("a/b")), parent_mode);
+        // -m is set exactly, whatever the umask
+        assert_eq!(mode_of(&dir.path("a/b/c")), 0o500);
+
+        // Existing directories along the way keep their modes, and -p is content
+        // with a directory that is already there
+        fs::set_permissions(dir.path("a"), Permissions::from_mode(0o711)).unwrap();
+        let (stdout, _, status) = mkdir(&["-pv", &dir.arg("a/b/d")]);
+        assert_eq!(status, 0);
+        assert_eq!(
+            stdout,
+            format!("mkdir: created directory '{}'\n", dir.arg("a/b/d"))
+        );
+        assert_eq!(mode_of(&dir.path("a")), 0o711);
+        assert_eq!(mode_of(&dir.path("a/b/d")), 0o777 & !umask);
+        assert_eq!(
+            mkdir(&["-p", &dir.arg("a/b")]),
+            (String::new(), String::new(), 0)
+        );
+    }
+
+    #[test]
+    fn symbolic_modes_start_from_rwx_for_all() {
+        let dir = Scratch::new("mkdir-symbolic", &[]);
+        assert_eq!(mkdir(&["-m", "go-w", &dir.arg("a")]).2, 0);
+        assert_eq!(mode_of(&dir.path("a")), 0o755);
+        assert_eq!(mkdir(&["-m", "a=rx,u+w", &dir.arg("b")]).2, 0);
+        assert_eq!(mode_of(&dir.path("b")), 0o755);
+        assert_eq!(mkdir(&["-m", "1777", &dir.arg("c")]).2, 0);
+        assert_eq!(mode_of(&dir.path("c")), 0o1777);
+        match Mkdir.parse_args(&["-m".to_string(), "u+q".to_string()]) {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Invalid arguments: mkdir: invalid mode 'u+q'"
+            ),
+            Ok(_) => panic!("u+q was accepted"),
+        }
+    }
+
+    #[test]
+    fn failures_are_reported_per_operand() {
+        let dir = Scratch::new("mkdir-failures", &["exists/", "file"]);
+        let (_, stderr, status) = mkdir(&[&dir.arg("exists"), &dir.arg("new"), &dir.arg("file/x")]);
+        assert_eq!(
+            stderr,
+            format!(
+                "mkdir: cannot create directory '{}': File exists\n\
+                 mkdir: cannot create directory '{}': Not a directory\n",
+                dir.arg("exists"),
+                dir.arg("file/x")
+            )
+        );
+        assert_eq!(status, 1);
+        assert!(dir.path("new").is_dir());
+        assert_eq!(mkdir(&[]).2, 2);
+    }
+}
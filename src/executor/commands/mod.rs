@@ -8,8 +8,19 @@ pub mod rm;
 pub mod mv;
 pub mod mkdir;
 pub mod exit;
+pub mod touch;
+pub mod head;
+pub mod tail;
+pub mod wc;
+pub mod grep;
+pub mod sort;
+pub mod uniq;
+pub mod find;
+pub mod chmod;
 
+use super::executor::Executor;
 use crate::utils::error::Result;
+use crate::utils::terminal::OutputStream;
 use std::io::{Read, Write};
 
 pub trait Command {
@@ -17,24 +28,46 @@ pub trait Command {
         &self, 
         args: &[String], 
         stdin: &mut dyn Read,
-        stdout: &mut dyn Write, 
+        stdout: &mut dyn OutputStream, 
         stderr: &mut dyn Write
     ) -> Result<()>;
+
+    // Commands that run other commands, like find -exec, override this to use the
+    // shell's executor; the rest don't need it
+    fn execute_with(
+        &self,
+        _executor: &Executor,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        self.execute(args, stdin, stdout, stderr)
+    }
 }
 
 
 pub fn get_command(name: &str) -> Option<Box<dyn Command>> {
     match name {
         "echo" => Some(Box::new(echo::Echo)),
-        // "cd" => Some(Box::new(cd::Cd)),
-        // "ls" => Some(Box::new(ls::Ls)),
-        // "pwd" => Some(Box::new(pwd::Pwd)),
-        // "cat" => Some(Box::new(cat::Cat)),
-        // "cp" => Some(Box::new(cp::Cp)),
-        // "rm" => Some(Box::new(rm::Rm)),
-        // "mv" => Some(Box::new(mv::Mv)),
-        // "mkdir" => Some(Box::new(mkdir::Mkdir)),
-        // "exit" => Some(Box::new(exit::Exit)),
+        "cd" => Some(Box::new(cd::Cd)),
+        "ls" => Some(Box::new(ls::Ls)),
+        "pwd" => Some(Box::new(pwd::Pwd)),
+        "cat" => Some(Box::new(cat::Cat)),
+        "cp" => Some(Box::new(cp::Cp)),
+        "rm" => Some(Box::new(rm::Rm)),
+        "mv" => Some(Box::new(mv::Mv)),
+        "mkdir" => Some(Box::new(mkdir::Mkdir)),
+        "exit" => Some(Box::new(exit::Exit)),
+        "touch" => Some(Box::new(touch::Touch)),
+        "head" => Some(Box::new(head::Head)),
+        "tail" => Some(Box::new(tail::Tail)),
+        "wc" => Some(Box::new(wc::Wc)),
+        "grep" => Some(Box::new(grep::Grep)),
+        "sort" => Some(Box::new(sort::Sort)),
+        "uniq" => Some(Box::new(uniq::Uniq)),
+        "find" => Some(Box::new(find::Find)),
+        "chmod" => Some(Box::new(chmod::Chmod)),
         _ => None,
     }
 }
\ No newline at end of file
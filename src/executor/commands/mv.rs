@@ -0,0 +1,404 @@
+use crate::utils::copy::{self, CopyOptions};
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::overwrite::{self, OverwritePolicy};
+use crate::utils::sys;
+use crate::utils::terminal::OutputStream;
+use std::fs::{self, Metadata};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process;
+
+pub struct Mv;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("no-target-directory", HasArg::No, Some('T')),
+    long("target-directory", HasArg::Required, Some('t')),
+    long("verbose", HasArg::No, Some('v')),
+];
+
+#[derive(Default)]
+struct MvOptions {
+    overwrite: OverwritePolicy,
+    no_target_directory: bool,         // -T
+    target_directory: Option<PathBuf>, // -t DIR
+    verbose: bool,
+}
+
+impl super::Command for Mv {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (mut options, mut operands) = self.parse_args(args)?;
+
+        let (dest, into_dir) = match options.target_directory.take() {
+            Some(dir) => {
+                if !dir.is_dir() {
+                    return Err(ShellError::execution(&format!(
+                        "mv: target directory '{}' is not a directory",
+                        dir.display()
+                    )));
+                }
+                (dir, true)
+            }
+            None => {
+                match operands.len() {
+                    0 => return Err(ShellError::invalid_arguments("mv: missing file operand")),
+                    1 => {
+                        return Err(ShellError::invalid_arguments(&format!(
+                            "mv: missing destination file operand after '{}'",
+                            operands[0]
+                        )));
+                    }
+                    n if n > 2 && options.no_target_directory => {
+                        return Err(ShellError::invalid_arguments(&format!(
+                            "mv: extra operand '{}'",
+                            operands[2]
+                        )));
+                    }
+                    _ => {}
+                }
+                let dest = PathBuf::from(operands.pop().unwrap_or_default());
+                let into_dir = !options.no_target_directory && dest.is_dir();
+                if operands.len() > 1 && !into_dir {
+                    return Err(ShellError::execution(&format!(
+                        "mv: target '{}' is not a directory",
+                        dest.display()
+                    )));
+                }
+                (dest, into_dir)
+            }
+        };
+        if operands.is_empty() {
+            return Err(ShellError::invalid_arguments("mv: missing file operand"));
+        }
+
+        let mut ok = true;
+        for operand in &operands {
+            let src = Path::new(operand);
+            // "mv a dir" moves a into dir under its own name
+            let target = match src.file_name() {
+                Some(name) if into_dir => dest.join(name),
+                _ => dest.clone(),
+            };
+            ok &= self.move_one(src, &target, &options, stdin, stdout, stderr)?;
+        }
+
+        if !ok {
+            return Err(ShellError::status(1));
+        }
+        Ok(())
+    }
+}
+
+impl Mv {
+    fn parse_args(&self, args: &[String]) -> Result<(MvOptions, Vec<String>)> {
+        let mut options = MvOptions::default();
+
+        let mut getopt = Getopt::new("mv", args, "Tt:v", LONG_OPTIONS)
+            .with(overwrite::SHORT_OPTIONS, overwrite::LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            if options.overwrite.apply(&option, "mv")? {
+                continue;
+            }
+            match option.name {
+                "T" => options.no_target_directory = true,
+                "t" => options.target_directory = option.value.map(PathBuf::from),
+                "v" => options.verbose = true,
+                _ => {}
+            }
+        }
+
+        if options.no_target_directory && options.target_directory.is_some() {
+            return Err(ShellError::invalid_arguments(
+                "mv: cannot combine --target-directory (-t) and --no-target-directory (-T)",
+            ));
+        }
+        Ok((options, getopt.operands()))
+    }
+
+    // Move one source into place, checking first for the cases rename would get wrong
+    // or that the user should be asked about. Returns whether the move happened cleanly.
+    fn move_one(
+        &self,
+        src: &Path,
+        target: &Path,
+        options: &MvOptions,
+        stdin: &mut dyn Read,
+        stdout: &mut dyn Write,
+        stderr: &mut dyn Write,
+    ) -> io::Result<bool> {
+        let metadata = match fs::symlink_metadata(src) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                writeln!(
+                    stderr,
+                    "mv: cannot stat '{}': {}",
+                    src.display(),
+                    error::describe(&e)
+                )?;
+                return Ok(false);
+            }
+        };
+
+        if let Ok(existing) = fs::symlink_metadata(target) {
+            if existing.dev() == metadata.dev() && existing.ino() == metadata.ino() {
+                writeln!(
+                    stderr,
+                    "mv: '{}' and '{}' are the same file",
+                    src.display(),
+                    target.display()
+                )?;
+                return Ok(false);
+            }
+            if existing.is_dir() && !metadata.is_dir() {
+                writeln!(
+                    stderr,
+                    "mv: cannot overwrite directory '{}' with non-directory",
+                    target.display()
+                )?;
+                return Ok(false);
+            }
+            if !existing.is_dir() && metadata.is_dir() {
+                writeln!(
+                    stderr,
+                    "mv: cannot overwrite non-directory '{}' with directory '{}'",
+                    target.display(),
+                    src.display()
+                )?;
+                return Ok(false);
+            }
+        }
+
+        if metadata.is_dir() && copy::is_inside(src, target) {
+            writeln!(
+                stderr,
+                "mv: cannot move '{}' to a subdirectory of itself, '{}'",
+                src.display(),
+                target.display()
+            )?;
+            return Ok(false);
+        }
+
+        let policy = &options.overwrite;
+        if !policy.allows(&metadata, target, "mv", stdin, stderr)? {
+            return Ok(true);
+        }
+        let backup = match policy.back_up(target) {
+            Ok(backup) => backup,
+            Err(e) => {
+                writeln!(
+                    stderr,
+                    "mv: cannot backup '{}': {}",
+                    target.display(),
+                    error::describe(&e)
+                )?;
+                return Ok(false);
+            }
+        };
+
+        let moved = match fs::rename(src, target) {
+            Ok(()) => true,
+            Err(e) if e.raw_os_error() == Some(sys::EXDEV) => {
+                self.move_across(src, target, &metadata, stderr)?
+            }
+            Err(e) => {
+                writeln!(
+                    stderr,
+                    "mv: cannot move '{}' to '{}': {}",
+                    src.display(),
+                    target.display(),
+                    error::describe(&e)
+                )?;
+                false
+            }
+        };
+
+        // A move that failed before anything took the target's place would leave only
+        // the backup behind, so it goes back where it was
+        if !moved
+            && let Some(backup) = backup
+            && fs::symlink_metadata(target).is_err()
+            && let Err(e) = fs::rename(&backup, target)
+        {
+            writeln!(
+                stderr,
+                "mv: cannot restore '{}' from '{}': {}",
+                target.display(),
+                backup.display(),
+                error::describe(&e)
+            )?;
+        }
+
+        if moved && options.verbose {
+            writeln!(
+                stdout,
+                "renamed '{}' -> '{}'",
+                src.display(),
+                target.display()
+            )?;
+        }
+        Ok(moved)
+    }
+
+    // Different filesystems: copy with all metadata, then delete the source. The copy
+    // is built under a temporary name next to the target and renamed into place only
+    // once complete, so a failure partway leaves neither a half-copied tree nor a
+    // missing source behind.
+    fn move_across(
+        &self,
+        src: &Path,
+        target: &Path,
+        metadata: &Metadata,
+        stderr: &mut dyn Write,
+    ) -> io::Result<bool> {
+        let name = target.file_name().unwrap_or(src.as_os_str());
+        let temporary =
+            target.with_file_name(format!(".{}.mv-{}", name.to_string_lossy(), process::id()));
+
+        let options = CopyOptions {
+            recursive: true,
+            preserve: true,
+            dereference: false,
+            ..Default::default()
+        };
+        let copied = copy::copy(src, &temporary, &options, "mv", &mut io::empty(), stderr)?;
+        let installed = copied
+            && match fs::rename(&temporary, target) {
+                Ok(()) => true,
+                Err(e) => {
+                    writeln!(
+                        stderr,
+                        "mv: cannot move '{}' to '{}': {}",
+                        src.display(),
+                        target.display(),
+                        error::describe(&e)
+                    )?;
+                    false
+                }
+            };
+        if !installed {
+            let _ = remove_any(&temporary);
+            return Ok(false);
+        }
+
+        let removed = if metadata.is_dir() {
+            fs::remove_dir_all(src)
+        } else {
+            fs::remove_file(src)
+        };
+        if let Err(e) = removed {
+            writeln!(
+                stderr,
+                "mv: cannot remove '{}': {}",
+                src.display(),
+                error::describe(&e)
+            )?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+fn remove_any(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use crate::utils::scratch::Scratch;
+    use std::os::unix::fs::symlink;
+
+    // Run mv, giving its stdout, stderr and status
+    fn mv(args: &[&str]) -> (String, String, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = match Mv.execute(&args, &mut "".as_bytes(), &mut stdout, &mut stderr) {
+            Ok(()) => 0,
+            Err(e) => e.status_code(),
+        };
+        (
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+            status,
+        )
+    }
+
+    #[test]
+    fn backups_of_replaced_files() {
+        let dir = Scratch::new("mv-backup", &[]);
+        dir.write("new", "new");
+        dir.write("old", "old");
+        let (stdout, _, status) = mv(&["-v", "--backup=simple", &dir.arg("new"), &dir.arg("old")]);
+        assert_eq!(status, 0);
+        assert_eq!(
+            stdout,
+            format!("renamed '{}' -> '{}'\n", dir.arg("new"), dir.arg("old"))
+        );
+        assert_eq!(dir.read("old"), "new");
+        assert_eq!(dir.read("old~"), "old");
+        assert!(!dir.path("new").exists());
+    }
+
+    #[test]
+    fn failed_moves_put_the_backup_back() {
+        // The source is reached through the link that is being replaced, so once
+        // the link is backed up the source can no longer be found
+        let dir = Scratch::new("mv-restore", &["real/f"]);
+        symlink("real", dir.path("link")).unwrap();
+        let (_, stderr, status) = mv(&[
+            "-T",
+            "--backup=simple",
+            &dir.arg("link/f"),
+            &dir.arg("link"),
+        ]);
+        assert_eq!(
+            stderr,
+            format!(
+                "mv: cannot move '{}' to '{}': No such file or directory\n",
+                dir.arg("link/f"),
+                dir.arg("link")
+            )
+        );
+        assert_eq!(status, 1);
+        assert_eq!(fs::read_link(dir.path("link")).unwrap(), Path::new("real"));
+        assert!(!dir.path("link~").exists());
+        assert!(dir.path("real/f").exists());
+    }
+
+    #[test]
+    fn several_sources_need_a_directory() {
+        let dir = Scratch::new("mv-several", &["a", "b", "c", "d/"]);
+        let (_, stderr, status) = mv(&[&dir.arg("a"), &dir.arg("b"), &dir.arg("c")]);
+        assert_eq!(status, 1);
+        assert!(stderr.is_empty());
+        assert!(dir.path("a").exists());
+
+        assert_eq!(
+            mv(&["-t", &dir.arg("d"), &dir.arg("a"), &dir.arg("b")]).2,
+            0
+        );
+        assert!(dir.path("d/a").exists() && dir.path("d/b").exists());
+        // -T treats the directory as a name to replace, which a file can't do
+        let (_, stderr, status) = mv(&["-T", &dir.arg("c"), &dir.arg("d")]);
+        assert_eq!(
+            stderr,
+            format!(
+                "mv: cannot overwrite directory '{}' with non-directory\n",
+                dir.arg("d")
+            )
+        );
+        assert_eq!(status, 1);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::Getopt;
+use crate::utils::terminal::OutputStream;
+use crate::utils::workdir;
+use std::env;
+use std::io::{Read, Write};
+
+pub struct Pwd;
+
+impl super::Command for Pwd {
+    fn execute(
+        &self,
+        args: &[String],
+        _stdin: &mut dyn Read, // pwd doesn't need stdin
+        stdout: &mut dyn OutputStream,
+        _stderr: &mut dyn Write, // errors are returned to the executor
+    ) -> Result<()> {
+        let physical = self.parse_args(args)?;
+
+        let path = if physical {
+            workdir::physical_cwd().map_err(|e| {
+                ShellError::execution(&format!(
+                    "pwd: error retrieving current directory: {}",
+                    error::describe(&e)
+                ))
+            })?
+        } else {
+            match workdir::logical_cwd() {
+                Some(pwd) => pwd,
+                None => match workdir::physical_cwd() {
+                    Ok(cwd) => cwd,
+                    // The directory was removed under us: $PWD is still the best answer
+                    Err(e) => env::var_os("PWD").map(Into::into).ok_or_else(|| {
+                        ShellError::execution(&format!(
+                            "pwd: error retrieving current directory: {}",
+                            error::describe(&e)
+                        ))
+                    })?,
+                },
+            }
+        };
+
+        writeln!(stdout, "{}", path.display())?;
+        Ok(())
+    }
+}
+
+impl Pwd {
+    // Returns whether -P (physical) is in effect; the last of -L and -P wins
+    fn parse_args(&self, args: &[String]) -> Result<bool> {
+        let mut physical = false;
+
+        let mut getopt = Getopt::new("pwd", args, "+LP", &[]);
+        while let Some(option) = getopt.next_option()? {
+            physical = option.name == "P";
+        }
+        if !getopt.operands().is_empty() {
+            return Err(ShellError::invalid_arguments("pwd: too many arguments"));
+        }
+
+        Ok(physical)
+    }
+}
@@ -0,0 +1,556 @@
+use crate::executor::executor::Executor;
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::prompt;
+use crate::utils::sys;
+use crate::utils::terminal::OutputStream;
+use crate::utils::workdir;
+use std::fs::{self, Metadata};
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+
+pub struct Rm;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("dir", HasArg::No, Some('d')),
+    long("force", HasArg::No, Some('f')),
+    long("interactive", HasArg::Optional, None),
+    long("no-preserve-root", HasArg::No, None),
+    long("preserve-root", HasArg::No, None),
+    long("recursive", HasArg::No, Some('r')),
+    long("verbose", HasArg::No, Some('v')),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Interactive {
+    Never,  // -f
+    Once,   // -I: one question before a large or recursive removal
+    Always, // -i: ask about every file
+}
+
+struct RmOptions {
+    interactive: Interactive,
+    force: bool, // -f: missing files are not an error
+    recursive: bool,
+    dir: bool, // -d: remove empty directories too
+    verbose: bool,
+    preserve_root: bool,
+    terminal: bool, // stdin is a terminal, so write-protected files can be asked about
+}
+
+// A pending removal. Directories are visited twice: once to queue their contents,
+// and again, after those are gone, to remove the directory itself.
+struct Frame {
+    path: PathBuf,
+    expanded: bool,
+    keep: bool, // something inside was left behind, so the directory can't go
+}
+
+impl super::Command for Rm {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        // Outside the shell there's no telling whether stdin is a terminal, so only
+        // -i and -I ask anything
+        self.remove(args, false, stdin, stdout, stderr)
+    }
+
+    // The executor knows whether the stdin rm was given is a terminal
+    fn execute_with(
+        &self,
+        executor: &Executor,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        self.remove(args, executor.stdin_is_terminal(), stdin, stdout, stderr)
+    }
+}
+
+impl Rm {
+    fn remove(
+        &self,
+        args: &[String],
+        terminal: bool,
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (mut options, operands) = self.parse_args(args)?;
+        options.terminal = terminal;
+        if operands.is_empty() {
+            if options.force {
+                return Ok(());
+            }
+            return Err(ShellError::invalid_arguments("rm: missing operand"));
+        }
+
+        if options.interactive == Interactive::Once && (options.recursive || operands.len() > 3) {
+            let count = operands.len();
+            let question = format!(
+                "rm: remove {} argument{}{}? ",
+                count,
+                if count == 1 { "" } else { "s" },
+                if options.recursive {
+                    " recursively"
+                } else {
+                    ""
+                }
+            );
+            if !prompt::confirm(stdin, stderr, &question)? {
+                return Ok(());
+            }
+        }
+
+        let mut ok = true;
+        for operand in &operands {
+            ok &= self.remove_operand(Path::new(operand), &options, stdin, stdout, stderr)?;
+        }
+
+        if !ok {
+            return Err(ShellError::status(1));
+        }
+        Ok(())
+    }
+
+    fn parse_args(&self, args: &[String]) -> Result<(RmOptions, Vec<String>)> {
+        let mut options = RmOptions {
+            interactive: Interactive::Never,
+            force: false,
+            recursive: false,
+            dir: false,
+            verbose: false,
+            preserve_root: true,
+            terminal: false,
+        };
+
+        // Of -f, -i and -I the last one wins
+        let mut getopt = Getopt::new("rm", args, "dfIiRrv", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            match option.name {
+                "f" => options.set_force(),
+                "i" => options.set_interactive(Interactive::Always),
+                "I" => options.set_interactive(Interactive::Once),
+                "r" | "R" => options.recursive = true,
+                "d" => options.dir = true,
+                "v" => options.verbose = true,
+                "preserve-root" => options.preserve_root = true,
+                "no-preserve-root" => options.preserve_root = false,
+                "interactive" => match option.value {
+                    None | Some("always" | "yes") => options.set_interactive(Interactive::Always),
+                    Some("once") => options.set_interactive(Interactive::Once),
+                    Some("never" | "no" | "none") => options.interactive = Interactive::Never,
+                    Some(other) => {
+                        return Err(ShellError::invalid_arguments(&format!(
+                            "rm: invalid argument '{}' for '--interactive'",
+                            other
+                        )));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        Ok((options, getopt.operands()))
+    }
+
+    // Check an operand against the safety rules, then remove it and anything below it.
+    // Returns false if anything failed. What the user declined to remove was kept on
+    // purpose, and like in coreutils that is not a failure.
+    fn remove_operand(
+        &self,
+        path: &Path,
+        options: &RmOptions,
+        stdin: &mut dyn Read,
+        stdout: &mut dyn Write,
+        stderr: &mut dyn Write,
+    ) -> io::Result<bool> {
+        if is_dot_or_dot_dot(path) {
+            writeln!(
+                stderr,
+                "rm: refusing to remove '.' or '..' directory: skipping '{}'",
+                path.display()
+            )?;
+            return Ok(false);
+        }
+
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if options.force && e.kind() == io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => {
+                writeln!(
+                    stderr,
+                    "rm: cannot remove '{}': {}",
+                    path.display(),
+                    error::describe(&e)
+                )?;
+                return Ok(false);
+            }
+        };
+
+        if metadata.is_dir() && options.recursive {
+            let resolved = fs::canonicalize(path).ok();
+            if options.preserve_root && resolved.as_deref() == Some(Path::new("/")) {
+                if path == Path::new("/") {
+                    writeln!(stderr, "rm: it is dangerous to operate recursively on '/'")?;
+                } else {
+                    writeln!(
+                        stderr,
+                        "rm: it is dangerous to operate recursively on '{}' (same as '/')",
+                        path.display()
+                    )?;
+                }
+                writeln!(
+                    stderr,
+                    "rm: use --no-preserve-root to override this failsafe"
+                )?;
+                return Ok(false);
+            }
+
+            // Removing the directory the shell is in (or one above it) leaves the
+            // shell somewhere that no longer exists, so make sure that's intended
+            if let (Some(resolved), Ok(cwd)) = (resolved, workdir::physical_cwd())
+                && cwd.starts_with(&resolved)
+            {
+                writeln!(
+                    stderr,
+                    "rm: warning: '{}' contains the shell's current directory",
+                    path.display()
+                )?;
+                if !options.force && !prompt::confirm(stdin, stderr, "rm: remove it anyway? ")? {
+                    // Skipped at the user's word, not failed
+                    return Ok(true);
+                }
+            }
+        }
+
+        if metadata.is_dir() && !options.recursive && !options.dir {
+            writeln!(
+                stderr,
+                "rm: cannot remove '{}': Is a directory",
+                path.display()
+            )?;
+            return Ok(false);
+        }
+
+        self.remove_tree(path, options, stdin, stdout, stderr)
+    }
+
+    // Depth-first removal with an explicit stack, so the depth of the tree is limited
+    // only by memory and not by the call stack. As for operands, files the user
+    // declined to remove are not failures.
+    fn remove_tree(
+        &self,
+        root: &Path,
+        options: &RmOptions,
+        stdin: &mut dyn Read,
+        stdout: &mut dyn Write,
+        stderr: &mut dyn Write,
+    ) -> io::Result<bool> {
+        let mut ok = true;
+        let mut stack = vec![Frame {
+            path: root.to_path_buf(),
+            expanded: false,
+            keep: false,
+        }];
+
+        while let Some(frame) = stack.pop() {
+            let path = frame.path;
+            if frame.keep {
+                keep_parent(&mut stack);
+                continue;
+            }
+
+            let metadata = match fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    if !(options.force && e.kind() == io::ErrorKind::NotFound) {
+                        report(stderr, &path, &e)?;
+                        ok = false;
+                        keep_parent(&mut stack);
+                    }
+                    continue;
+                }
+            };
+
+            // Under -d alone a directory is removed only if it is already empty
+            if metadata.is_dir() && !frame.expanded && options.recursive {
+                let mut entries = match fs::read_dir(&path) {
+                    Ok(entries) => entries.peekable(),
+                    Err(e) => {
+                        writeln!(
+                            stderr,
+                            "rm: cannot open directory '{}': {}",
+                            path.display(),
+                            error::describe(&e)
+                        )?;
+                        ok = false;
+                        keep_parent(&mut stack);
+                        continue;
+                    }
+                };
+
+                if entries.peek().is_some() {
+                    if options.interactive == Interactive::Always
+                        && !self.ask(&path, &metadata, "descend into ", stdin, stderr)?
+                    {
+                        keep_parent(&mut stack);
+                        continue;
+                    }
+
+                    stack.push(Frame {
+                        path: path.clone(),
+                        expanded: true,
+                        keep: false,
+                    });
+                    for entry in entries {
+                        match entry {
+                            Ok(entry) => stack.push(Frame {
+                                path: entry.path(),
+                                expanded: false,
+                                keep: false,
+                            }),
+                            Err(e) => {
+                                report(stderr, &path, &e)?;
+                                ok = false;
+                                keep_parent(&mut stack);
+                                break;
+                            }
+                        }
+                    }
+                    continue;
+                }
+            }
+
+            if self.needs_asking(&path, &metadata, options)
+                && !self.ask(&path, &metadata, "remove ", stdin, stderr)?
+            {
+                keep_parent(&mut stack);
+                continue;
+            }
+
+            let result = if metadata.is_dir() {
+                fs::remove_dir(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            match result {
+                Ok(()) => {
+                    if options.verbose {
+                        if metadata.is_dir() {
+                            writeln!(stdout, "removed directory '{}'", path.display())?;
+                        } else {
+                            writeln!(stdout, "removed '{}'", path.display())?;
+                        }
+                    }
+                }
+                Err(e) if options.force && e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    report(stderr, &path, &e)?;
+                    ok = false;
+                    keep_parent(&mut stack);
+                }
+            }
+        }
+
+        Ok(ok)
+    }
+
+    // -i asks about everything; otherwise, like coreutils, only write-protected files
+    // are asked about, and only when someone is at the terminal to answer
+    fn needs_asking(&self, path: &Path, metadata: &Metadata, options: &RmOptions) -> bool {
+        match options.interactive {
+            Interactive::Always => true,
+            Interactive::Never if options.force => false,
+            _ => !metadata.file_type().is_symlink() && options.terminal && !sys::is_writable(path),
+        }
+    }
+
+    fn ask(
+        &self,
+        path: &Path,
+        metadata: &Metadata,
+        action: &str,
+        stdin: &mut dyn Read,
+        stderr: &mut dyn Write,
+    ) -> io::Result<bool> {
+        let protected = !metadata.file_type().is_symlink() && !sys::is_writable(path);
+        let question = format!(
+            "rm: {}{}{} '{}'? ",
+            action,
+            if protected { "write-protected " } else { "" },
+            file_kind(metadata),
+            path.display()
+        );
+        prompt::confirm(stdin, stderr, &question)
+    }
+}
+
+impl RmOptions {
+    fn set_force(&mut self) {
+        self.interactive = Interactive::Never;
+        self.force = true;
+    }
+
+    fn set_interactive(&mut self, interactive: Interactive) {
+        self.interactive = interactive;
+        self.force = false;
+    }
+}
+
+// Something below the innermost directory being emptied stays, so it has to stay too.
+// The expanded frames on the stack are exactly the ancestors of the current path.
+fn keep_parent(stack: &mut [Frame]) {
+    if let Some(parent) = stack.iter_mut().rev().find(|frame| frame.expanded) {
+        parent.keep = true;
+    }
+}
+
+// Whether the last component is "." or "..", however many slashes follow it
+fn is_dot_or_dot_dot(path: &Path) -> bool {
+    let bytes = path.as_os_str().as_bytes();
+    let end = bytes.iter().rposition(|&b| b != b'/').map_or(0, |i| i + 1);
+    let start = bytes[..end]
+        .iter()
+        .rposition(|&b| b == b'/')
+        .map_or(0, |i| i + 1);
+    matches!(&bytes[start..end], b"." | b"..")
+}
+
+fn report(stderr: &mut dyn Write, path: &Path, e: &io::Error) -> io::Result<()> {
+    writeln!(
+        stderr,
+        "rm: cannot remove '{}': {}",
+        path.display(),
+        error::describe(e)
+    )
+}
+
+// How coreutils names a file in its questions
+fn file_kind(metadata: &Metadata) -> &'static str {
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_symlink() {
+        "symbolic link"
+    } else if file_type.is_file() && metadata.len() == 0 {
+        "regular empty file"
+    } else if file_type.is_file() {
+        "regular file"
+    } else if file_type.is_fifo() {
+        "fifo"
+    } else if file_type.is_socket() {
+        "socket"
+    } else if file_type.is_block_device() {
+        "block special file"
+    } else if file_type.is_char_device() {
+        "character special file"
+    } else {
+        "file"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use crate::utils::scratch::Scratch;
+    use std::os::unix::fs::PermissionsExt;
+
+    // Run rm with these answers on stdin, giving its stdout, stderr and status
+    fn rm(args: &[&str], answers: &str) -> (String, String, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = match Rm.execute(&args, &mut answers.as_bytes(), &mut stdout, &mut stderr) {
+            Ok(()) => 0,
+            Err(e) => e.status_code(),
+        };
+        (
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+            status,
+        )
+    }
+
+    #[test]
+    fn declined_files_are_kept_without_failing() {
+        let dir = Scratch::new("rm-declined", &["a", "b"]);
+        let (_, stderr, status) = rm(&["-i", &dir.arg("a"), &dir.arg("b")], "n\ny\n");
+        assert_eq!(
+            stderr,
+            format!(
+                "rm: remove regular empty file '{}'? rm: remove regular empty file '{}'? ",
+                dir.arg("a"),
+                dir.arg("b")
+            )
+        );
+        assert_eq!(status, 0);
+        assert!(dir.path("a").exists());
+        assert!(!dir.path("b").exists());
+
+        // A directory kept from -i keeps its parent too
+        let dir = Scratch::new("rm-declined-tree", &["d/keep", "d/go"]);
+        let (_, _, status) = rm(&["-ri", &dir.arg("d")], "y\nn\ny\ny\n");
+        assert_eq!(status, 0);
+        // Which entry comes first depends on the directory order
+        assert!(dir.path("d/keep").exists() != dir.path("d/go").exists());
+        assert!(dir.path("d").is_dir());
+    }
+
+    #[test]
+    fn directories_need_recursion() {
+        let dir = Scratch::new("rm-directories", &["d/sub/f", "empty/"]);
+        let (_, stderr, status) = rm(&[&dir.arg("d")], "");
+        assert_eq!(
+            (stderr, status),
+            (
+                format!("rm: cannot remove '{}': Is a directory\n", dir.arg("d")),
+                1
+            )
+        );
+        assert_eq!(rm(&["-d", &dir.arg("empty")], "").2, 0);
+        assert!(!dir.path("empty").exists());
+
+        let (stdout, _, status) = rm(&["-rv", &dir.arg("d")], "");
+        assert_eq!(status, 0);
+        assert_eq!(
+            stdout,
+            format!(
+                "removed '{0}/sub/f'\nremoved directory '{0}/sub'\nremoved directory '{0}'\n",
+                dir.arg("d")
+            )
+        );
+    }
+
+    #[test]
+    fn missing_files_and_protected_ones() {
+        let dir = Scratch::new("rm-missing", &["ro"]);
+        let missing = dir.arg("missing");
+        assert_eq!(
+            rm(&[&missing], ""),
+            (
+                String::new(),
+                format!(
+                    "rm: cannot remove '{}': No such file or directory\n",
+                    missing
+                ),
+                1
+            )
+        );
+        assert_eq!(rm(&["-f", &missing], ""), (String::new(), String::new(), 0));
+        assert_eq!(rm(&["-f"], "").2, 0);
+        assert_eq!(rm(&[], "").2, 2);
+
+        // Nobody is at a terminal to ask, so a write-protected file just goes
+        fs::set_permissions(dir.path("ro"), fs::Permissions::from_mode(0o444)).unwrap();
+        assert_eq!(rm(&[&dir.arg("ro")], ""), (String::new(), String::new(), 0));
+        assert!(!dir.path("ro").exists());
+    }
+}
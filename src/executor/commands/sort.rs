@@ -0,0 +1,724 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::terminal::OutputStream;
+use std::cmp::Ordering;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+pub struct Sort;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("human-numeric-sort", HasArg::No, Some('h')),
+    long("ignore-case", HasArg::No, Some('f')),
+    long("ignore-leading-blanks", HasArg::No, Some('b')),
+    long("key", HasArg::Required, Some('k')),
+    long("numeric-sort", HasArg::No, Some('n')),
+    long("reverse", HasArg::No, Some('r')),
+    long("stable", HasArg::No, Some('s')),
+    long("field-separator", HasArg::Required, Some('t')),
+    long("unique", HasArg::No, Some('u')),
+];
+
+// Once the lines held in memory take more than this, by default, they are sorted
+// and written out to a temporary file, and the files are merged at the end
+const MEMORY_LIMIT: usize = 64 << 20;
+
+// Bookkeeping for each line held, on top of its bytes
+const LINE_OVERHEAD: usize = 32;
+
+// How many sorted runs are merged at once, as in GNU sort
+const MERGE_FAN_IN: usize = 16;
+
+// Size suffixes in the order -h ranks them
+const HUMAN_UNITS: &[u8] = b"KMGTPEZYRQ";
+
+// Temporary files made by this process, for unique names
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Default, PartialEq)]
+struct SortOrder {
+    numeric: bool, // n
+    human: bool,   // h
+    fold: bool,    // f
+    blanks: bool,  // b: ignore leading blanks
+    reverse: bool, // r
+}
+
+// -k F[.C][OPTS][,F[.C][OPTS]]: from character C of field F to character C of
+// field F, both counted from 1. An end character of 0 is the end of the field.
+struct Key {
+    start_field: usize,
+    start_char: usize,
+    end: Option<(usize, usize)>,
+    ordering: SortOrder,
+}
+
+struct SortOptions {
+    ordering: SortOrder,   // -n -h -f -b -r, for keys without their own
+    keys: Vec<Key>,        // -k
+    separator: Option<u8>, // -t
+    unique: bool,          // -u
+    stable: bool,          // -s: no last-resort comparison of whole lines
+}
+
+impl super::Command for Sort {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, mut operands) = self.parse_args(args)?;
+        if operands.is_empty() {
+            operands.push("-".to_string());
+        }
+
+        let mut sorter = Sorter::new(&options, MEMORY_LIMIT);
+        for operand in &operands {
+            let result = if operand == "-" {
+                sorter.read(stdin)
+            } else {
+                File::open(operand).and_then(|mut file| sorter.read(&mut file))
+            };
+            // Like GNU sort, nothing is output when any input fails
+            if let Err(e) = result {
+                writeln!(
+                    stderr,
+                    "sort: cannot read: {}: {}",
+                    operand,
+                    error::describe(&e)
+                )?;
+                return Err(ShellError::status(2));
+            }
+        }
+
+        if let Err(e) = sorter.finish(stdout) {
+            writeln!(stderr, "sort: {}", error::describe(&e))?;
+            return Err(ShellError::status(2));
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+impl Sort {
+    fn parse_args(&self, args: &[String]) -> Result<(SortOptions, Vec<String>)> {
+        let mut options = SortOptions {
+            ordering: SortOrder::default(),
+            keys: Vec::new(),
+            separator: None,
+            unique: false,
+            stable: false,
+        };
+
+        let mut getopt = Getopt::new("sort", args, "bfhk:nrst:u", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            let value = option.value.unwrap_or_default();
+            match option.name {
+                "b" => options.ordering.blanks = true,
+                "f" => options.ordering.fold = true,
+                "h" => options.ordering.human = true,
+                "n" => options.ordering.numeric = true,
+                "r" => options.ordering.reverse = true,
+                "s" => options.stable = true,
+                "u" => options.unique = true,
+                "k" => options.keys.push(parse_key(value).ok_or_else(|| {
+                    ShellError::invalid_arguments(&format!(
+                        "sort: invalid field specification '{}'",
+                        value
+                    ))
+                })?),
+                "t" => {
+                    if value.len() != 1 {
+                        return Err(ShellError::invalid_arguments(&format!(
+                            "sort: multi-character tab '{}'",
+                            value
+                        )));
+                    }
+                    options.separator = Some(value.as_bytes()[0]);
+                }
+                _ => {}
+            }
+        }
+
+        // Keys with no ordering options of their own take the global ones
+        for key in &mut options.keys {
+            if key.ordering == SortOrder::default() {
+                key.ordering = options.ordering;
+            }
+        }
+        Ok((options, getopt.operands()))
+    }
+}
+
+fn parse_key(spec: &str) -> Option<Key> {
+    let (start, end) = match spec.split_once(',') {
+        Some((start, end)) => (start, Some(end)),
+        None => (spec, None),
+    };
+
+    let mut ordering = SortOrder::default();
+    let (start_field, start_char) = parse_position(start, &mut ordering)?;
+    if start_field == 0 || start_char == Some(0) {
+        return None;
+    }
+    let end = match end {
+        Some(end) => {
+            let (field, char) = parse_position(end, &mut ordering)?;
+            if field == 0 {
+                return None;
+            }
+            Some((field, char.unwrap_or(0)))
+        }
+        None => None,
+    };
+    Some(Key {
+        start_field,
+        start_char: start_char.unwrap_or(1),
+        end,
+        ordering,
+    })
+}
+
+// "F[.C][OPTS]", with the option letters added to ordering
+fn parse_position(text: &str, ordering: &mut SortOrder) -> Option<(usize, Option<usize>)> {
+    let digits_end = |text: &str| {
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len())
+    };
+    let split = digits_end(text);
+    let field = text[..split].parse().ok()?;
+    let mut rest = &text[split..];
+
+    let mut char = None;
+    if let Some(after_dot) = rest.strip_prefix('.') {
+        let split = digits_end(after_dot);
+        char = Some(after_dot[..split].parse().ok()?);
+        rest = &after_dot[split..];
+    }
+    for letter in rest.chars() {
+        match letter {
+            'b' => ordering.blanks = true,
+            'f' => ordering.fold = true,
+            'h' => ordering.human = true,
+            'n' => ordering.numeric = true,
+            'r' => ordering.reverse = true,
+            _ => return None,
+        }
+    }
+    Some((field, char))
+}
+
+// Lines are gathered in memory until they take more than memory_limit bytes, then
+// sorted and spilled to a temporary file. finish() merges the spilled runs with
+// whatever is left.
+struct Sorter<'a> {
+    options: &'a SortOptions,
+    memory_limit: usize,
+    lines: Vec<Vec<u8>>,
+    held: usize,
+    runs: Vec<TempFile>,
+}
+
+impl<'a> Sorter<'a> {
+    fn new(options: &'a SortOptions, memory_limit: usize) -> Self {
+        Sorter {
+            options,
+            memory_limit,
+            lines: Vec::new(),
+            held: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    fn read(&mut self, input: &mut dyn Read) -> io::Result<()> {
+        let mut reader = BufReader::new(input);
+        loop {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => return Ok(()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            self.held += line.len() + LINE_OVERHEAD;
+            self.lines.push(line);
+            if self.held > self.memory_limit {
+                self.spill()?;
+            }
+        }
+    }
+
+    fn sort_lines(&mut self) {
+        let options = self.options;
+        self.lines.sort_by(|a, b| compare(a, b, options));
+        if options.unique {
+            self.lines.dedup_by(|later, earlier| {
+                compare_keys(earlier, later, options) == Ordering::Equal
+            });
+        }
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.sort_lines();
+        let (run, file) = TempFile::create()?;
+        let mut writer = BufWriter::new(file);
+        for line in self.lines.drain(..) {
+            writer.write_all(&line)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        self.runs.push(run);
+        self.held = 0;
+        Ok(())
+    }
+
+    fn finish(mut self, stdout: &mut dyn Write) -> io::Result<()> {
+        let mut writer = BufWriter::new(stdout);
+        if self.runs.is_empty() {
+            self.sort_lines();
+            for line in &self.lines {
+                writer.write_all(line)?;
+                writer.write_all(b"\n")?;
+            }
+            return writer.flush();
+        }
+
+        if !self.lines.is_empty() {
+            self.spill()?;
+        }
+        // Merge the oldest runs first, so equal lines keep their input order
+        while self.runs.len() > MERGE_FAN_IN {
+            let (merged, file) = TempFile::create()?;
+            let mut output = BufWriter::new(file);
+            merge(&self.runs[..MERGE_FAN_IN], &mut output, self.options)?;
+            output.flush()?;
+            self.runs.splice(..MERGE_FAN_IN, [merged]);
+        }
+        merge(&self.runs, &mut writer, self.options)?;
+        writer.flush()
+    }
+}
+
+// Write the lines of sorted runs in order. On a tie the earlier run goes first.
+fn merge(runs: &[TempFile], output: &mut dyn Write, options: &SortOptions) -> io::Result<()> {
+    let mut readers = Vec::new();
+    let mut heads = Vec::new();
+    for run in runs {
+        let mut reader = BufReader::new(File::open(&run.path)?);
+        heads.push(next_line(&mut reader)?);
+        readers.push(reader);
+    }
+
+    let mut last: Option<Vec<u8>> = None;
+    loop {
+        let mut smallest: Option<usize> = None;
+        for (index, head) in heads.iter().enumerate() {
+            let Some(line) = head else {
+                continue;
+            };
+            let smaller = smallest.is_none_or(|best| {
+                let best_line = heads[best].as_deref().unwrap_or_default();
+                compare(line, best_line, options) == Ordering::Less
+            });
+            if smaller {
+                smallest = Some(index);
+            }
+        }
+        let Some(index) = smallest else {
+            return Ok(());
+        };
+
+        let line = heads[index].take().unwrap_or_default();
+        heads[index] = next_line(&mut readers[index])?;
+        if options.unique
+            && let Some(last) = &last
+            && compare_keys(last, &line, options) == Ordering::Equal
+        {
+            continue;
+        }
+        output.write_all(&line)?;
+        output.write_all(b"\n")?;
+        last = Some(line);
+    }
+}
+
+fn next_line(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    Ok(Some(line))
+}
+
+// A file in the temporary directory, removed when dropped
+struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    // A new file only its owner can read, since it holds lines of the input, opened
+    // for writing. The run is written through this handle rather than by reopening
+    // the name, which someone else could have replaced in the meantime.
+    fn create() -> io::Result<(Self, File)> {
+        loop {
+            let number = TEMP_FILES.fetch_add(1, AtomicOrdering::Relaxed);
+            let path = env::temp_dir().join(format!("sort{}-{}", process::id(), number));
+            let opened = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path);
+            match opened {
+                Ok(file) => return Ok((TempFile { path }, file)),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// The full comparison: by keys, then, unless -s or -u, byte by byte
+fn compare(a: &[u8], b: &[u8], options: &SortOptions) -> Ordering {
+    let ordering = compare_keys(a, b, options);
+    if ordering != Ordering::Equal || options.stable || options.unique {
+        return ordering;
+    }
+    let last_resort = a.cmp(b);
+    if options.ordering.reverse {
+        last_resort.reverse()
+    } else {
+        last_resort
+    }
+}
+
+// Lines that compare equal here are duplicates for -u
+fn compare_keys(a: &[u8], b: &[u8], options: &SortOptions) -> Ordering {
+    if options.keys.is_empty() {
+        return compare_text(a, b, options.ordering);
+    }
+    for key in &options.keys {
+        let a_key = key_text(a, key, options.separator);
+        let b_key = key_text(b, key, options.separator);
+        let ordering = compare_text(a_key, b_key, key.ordering);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+fn compare_text(a: &[u8], b: &[u8], ordering: SortOrder) -> Ordering {
+    let (a, b) = if ordering.blanks || ordering.numeric || ordering.human {
+        (skip_blanks(a, 0), skip_blanks(b, 0))
+    } else {
+        (a, b)
+    };
+    let result = if ordering.human {
+        compare_human(a, b)
+    } else if ordering.numeric {
+        compare_numbers(a, b)
+    } else if ordering.fold {
+        let upper = |text: &[u8]| text.iter().map(u8::to_ascii_uppercase).collect::<Vec<_>>();
+        upper(a).cmp(&upper(b))
+    } else {
+        a.cmp(b)
+    };
+    if ordering.reverse {
+        result.reverse()
+    } else {
+        result
+    }
+}
+
+fn is_blank(byte: u8) -> bool {
+    byte == b' ' || byte == b'\t'
+}
+
+fn skip_blanks(text: &[u8], from: usize) -> &[u8] {
+    let start = text[from..]
+        .iter()
+        .position(|&byte| !is_blank(byte))
+        .map_or(text.len(), |offset| from + offset);
+    &text[start..]
+}
+
+// Where field number `field` (from 1) starts. Without -t a field is blanks and
+// then non-blanks, so the blanks before it belong to it.
+fn field_start(line: &[u8], field: usize, separator: Option<u8>) -> usize {
+    let mut position = 0;
+    for _ in 1..field {
+        position = field_end(line, position, separator);
+        if separator.is_some() && position < line.len() {
+            position += 1; // past the separator
+        }
+    }
+    position
+}
+
+fn field_end(line: &[u8], start: usize, separator: Option<u8>) -> usize {
+    let rest = &line[start..];
+    let length = match separator {
+        Some(separator) => rest.iter().position(|&byte| byte == separator),
+        None => {
+            let blanks = rest.iter().take_while(|&&byte| is_blank(byte)).count();
+            rest[blanks..]
+                .iter()
+                .position(|&byte| is_blank(byte))
+                .map(|offset| blanks + offset)
+        }
+    };
+    start + length.unwrap_or(rest.len())
+}
+
+fn key_text<'a>(line: &'a [u8], key: &Key, separator: Option<u8>) -> &'a [u8] {
+    let skip = |position: usize| {
+        if key.ordering.blanks {
+            line.len() - skip_blanks(line, position).len()
+        } else {
+            position
+        }
+    };
+
+    let start = skip(field_start(line, key.start_field, separator));
+    let start = (start + key.start_char - 1).min(line.len());
+    let end = match key.end {
+        None => line.len(),
+        Some((field, 0)) => field_end(line, field_start(line, field, separator), separator),
+        Some((field, char)) => {
+            let field_begin = field_start(line, field, separator);
+            let field_stop = field_end(line, field_begin, separator);
+            (skip(field_begin) + char).min(field_stop)
+        }
+    };
+    if end <= start { &[] } else { &line[start..end] }
+}
+
+// A number's parts: whether it's negative, its integer digits without leading
+// zeros, its fraction digits without trailing zeros, and what follows
+fn split_number(text: &[u8]) -> (bool, &[u8], &[u8], &[u8]) {
+    let (negative, text) = match text.first() {
+        Some(b'-') => (true, &text[1..]),
+        _ => (false, text),
+    };
+    let digits = text.iter().take_while(|byte| byte.is_ascii_digit()).count();
+    let integer = &text[..digits];
+    let mut rest = &text[digits..];
+    let mut fraction: &[u8] = &[];
+    if rest.first() == Some(&b'.') {
+        let digits = rest[1..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        fraction = &rest[1..1 + digits];
+        rest = &rest[1 + digits..];
+    }
+
+    let leading = integer.iter().take_while(|&&byte| byte == b'0').count();
+    let integer = &integer[leading..];
+    let trailing = fraction
+        .iter()
+        .rev()
+        .take_while(|&&byte| byte == b'0')
+        .count();
+    let fraction = &fraction[..fraction.len() - trailing];
+    // -0 is 0
+    let negative = negative && !(integer.is_empty() && fraction.is_empty());
+    (negative, integer, fraction, rest)
+}
+
+// Compare digit strings as numbers, however long they are. Anything that isn't
+// a number counts as 0.
+fn compare_numbers(a: &[u8], b: &[u8]) -> Ordering {
+    let (a_negative, a_integer, a_fraction, _) = split_number(a);
+    let (b_negative, b_integer, b_fraction, _) = split_number(b);
+    compare_signed(
+        (a_negative, a_integer, a_fraction),
+        (b_negative, b_integer, b_fraction),
+        Ordering::Equal,
+    )
+}
+
+// -h: "2K" and "1M" sort by their unit first, then by their number
+fn compare_human(a: &[u8], b: &[u8]) -> Ordering {
+    let (a_negative, a_integer, a_fraction, a_rest) = split_number(a);
+    let (b_negative, b_integer, b_fraction, b_rest) = split_number(b);
+    let unit = |rest: &[u8]| {
+        rest.first()
+            .and_then(|&byte| {
+                let byte = if byte == b'k' { b'K' } else { byte };
+                HUMAN_UNITS.iter().position(|&unit| unit == byte)
+            })
+            .map_or(0, |index| index + 1)
+    };
+    compare_signed(
+        (a_negative, a_integer, a_fraction),
+        (b_negative, b_integer, b_fraction),
+        unit(a_rest).cmp(&unit(b_rest)),
+    )
+}
+
+// Negative numbers sort first, and among them the larger magnitude first.
+// first_by decides between magnitudes before their digits do.
+fn compare_signed(
+    (a_negative, a_integer, a_fraction): (bool, &[u8], &[u8]),
+    (b_negative, b_integer, b_fraction): (bool, &[u8], &[u8]),
+    first_by: Ordering,
+) -> Ordering {
+    if a_negative != b_negative {
+        return if a_negative {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        };
+    }
+    let magnitude = first_by
+        .then(a_integer.len().cmp(&b_integer.len()))
+        .then(a_integer.cmp(b_integer))
+        .then(a_fraction.cmp(b_fraction));
+    if a_negative {
+        magnitude.reverse()
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn sort_with_limit(args: &[&str], input: &str, memory_limit: usize) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (options, _) = Sort.parse_args(&args).unwrap_or_else(|e| panic!("{}", e));
+        let mut sorter = Sorter::new(&options, memory_limit);
+        sorter.read(&mut input.as_bytes()).unwrap();
+        let mut output = Vec::new();
+        sorter.finish(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn sort(args: &[&str], input: &str) -> String {
+        sort_with_limit(args, input, MEMORY_LIMIT)
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(sort(&["-k2,2"], "a 3\nb 1\nc 2\n"), "b 1\nc 2\na 3\n");
+        assert_eq!(sort(&["-k1.3"], "xxb\nyya\nzzc\n"), "yya\nxxb\nzzc\n");
+        assert_eq!(
+            sort(&["-t:", "-k3,3n"], "root:x:0\nbin:x:10\nsys:x:9\n"),
+            "root:x:0\nsys:x:9\nbin:x:10\n"
+        );
+        // A later key breaks ties in an earlier one
+        assert_eq!(
+            sort(&["-k1,1", "-k2,2nr"], "b 1\na 1\na 10\nb 2\n"),
+            "a 10\na 1\nb 2\nb 1\n"
+        );
+    }
+
+    #[test]
+    fn leading_blanks() {
+        // Without -b the blanks before a field are part of it
+        assert_eq!(sort(&["-k2,2"], "a   b\nx a\n"), "a   b\nx a\n");
+        assert_eq!(sort(&["-b", "-k2,2"], "a   b\nx a\n"), "x a\na   b\n");
+        assert_eq!(sort(&["-k2b,2"], "a   b\nx a\n"), "x a\na   b\n");
+    }
+
+    #[test]
+    fn numeric_and_human_orders() {
+        assert_eq!(
+            sort(&["-n"], "10\n9\n-1\n 2.5\nx\n"),
+            "-1\nx\n 2.5\n9\n10\n"
+        );
+        // Units rank first, then the number
+        assert_eq!(
+            sort(&["-h"], "10K\n1M\n512\n1G\n2K\n-3M\n0.5K\n"),
+            "-3M\n512\n0.5K\n2K\n10K\n1M\n1G\n"
+        );
+        assert_eq!(sort(&["-hr"], "1K\n2\n1M\n"), "1M\n1K\n2\n");
+    }
+
+    #[test]
+    fn unique_keeps_the_first_of_equal_keys() {
+        assert_eq!(sort(&["-u", "-k1,1"], "a 2\nb 1\na 1\n"), "a 2\nb 1\n");
+        assert_eq!(sort(&["-u", "-f"], "B\na\nb\nA\n"), "a\nB\n");
+        assert_eq!(sort(&["-u"], "b\na\nb\na\n"), "a\nb\n");
+    }
+
+    #[test]
+    fn stable_and_reverse() {
+        assert_eq!(
+            sort(&["-s", "-r", "-k1,1"], "a 2\nb 1\na 1\n"),
+            "b 1\na 2\na 1\n"
+        );
+        assert_eq!(sort(&["-f"], "b\na\nB\n"), "a\nB\nb\n");
+        assert_eq!(sort(&["-r"], "b\na\nc\n"), "c\nb\na\n");
+    }
+
+    #[test]
+    fn spilled_runs_are_merged() {
+        let input: String = (0..200)
+            .map(|n| format!("{} {}\n", (n * 37) % 101, n))
+            .collect();
+        let expected = sort(&["-n", "-k1,1"], &input);
+        let unique = sort(&["-n", "-u", "-k1,1"], &input);
+        assert_eq!(unique.lines().count(), 101);
+
+        let run_files = || {
+            let prefix = format!("sort{}-", process::id());
+            fs::read_dir(env::temp_dir())
+                .unwrap()
+                .filter(|entry| {
+                    let name = entry.as_ref().unwrap().file_name();
+                    name.to_string_lossy().starts_with(&prefix)
+                })
+                .count()
+        };
+
+        // A few runs, merged at once, and then so many that merges have to be merged
+        for (memory_limit, runs) in [(1000, 4..=MERGE_FAN_IN), (0, MERGE_FAN_IN + 1..=200)] {
+            let args: Vec<String> = ["-n", "-k1,1"].iter().map(|arg| arg.to_string()).collect();
+            let (options, _) = Sort.parse_args(&args).unwrap_or_else(|e| panic!("{}", e));
+            let mut sorter = Sorter::new(&options, memory_limit);
+            sorter.read(&mut input.as_bytes()).unwrap();
+            assert!(
+                runs.contains(&sorter.runs.len()),
+                "{} runs",
+                sorter.runs.len()
+            );
+            // Runs hold lines of the input, so only their owner may read them
+            for run in &sorter.runs {
+                let mode = fs::metadata(&run.path).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600);
+            }
+            let mut output = Vec::new();
+            sorter.finish(&mut output).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+
+            assert_eq!(
+                sort_with_limit(&["-n", "-u", "-k1,1"], &input, memory_limit),
+                unique
+            );
+        }
+        assert_eq!(run_files(), 0);
+    }
+}
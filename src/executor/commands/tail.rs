@@ -0,0 +1,580 @@
+use super::head::{Unit, obsolete_count};
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::signals::InterruptGuard;
+use crate::utils::size;
+use crate::utils::terminal::OutputStream;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::thread;
+use std::time::Duration;
+
+pub struct Tail;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("bytes", HasArg::Required, Some('c')),
+    long("follow", HasArg::Optional, Some('f')),
+    long("lines", HasArg::Required, Some('n')),
+    long("quiet", HasArg::No, Some('q')),
+    long("retry", HasArg::No, None),
+    long("silent", HasArg::No, Some('q')),
+    long("sleep-interval", HasArg::Required, Some('s')),
+    long("verbose", HasArg::No, Some('v')),
+];
+
+const BUF_SIZE: usize = 8192;
+
+// How often a follow loop looks for Ctrl+C while it waits
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Follow {
+    Descriptor, // -f: keep reading the file that was opened, wherever it moves
+    Name,       // -F: reopen the name when the file is rotated or recreated
+}
+
+struct TailOptions {
+    unit: Unit,             // -n or -c
+    count: u64,             // how many lines or bytes
+    from_start: bool,       // -n +N: start at line N instead of counting from the end
+    follow: Option<Follow>, // -f, -F, --follow
+    retry: bool,            // keep trying names that can't be opened
+    sleep: Duration,        // -s: between looks at followed files
+    headers: Option<bool>,  // -v or -q, otherwise only with several files
+}
+
+// A file being followed. file is None while the name can't be opened.
+struct Followed {
+    name: String,
+    file: Option<File>,
+    identity: Option<(u64, u64)>, // device and inode of the open file
+}
+
+impl super::Command for Tail {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, mut operands) = self.parse_args(args)?;
+        if operands.is_empty() {
+            operands.push("-".to_string());
+        }
+        let headers = options.headers.unwrap_or(operands.len() > 1);
+
+        let mut status = 0;
+        let mut last_shown = None;
+        let mut followed = Vec::new();
+        for (index, operand) in operands.iter().enumerate() {
+            if operand == "-" {
+                // Standard input is never followed: it is read to the end first
+                self.print_header(headers, &mut last_shown, index, "standard input", stdout)?;
+                if let Err(e) = self.tail_stream(stdin, &options, stdout) {
+                    writeln!(
+                        stderr,
+                        "tail: error reading 'standard input': {}",
+                        error::describe(&e)
+                    )?;
+                    status = 1;
+                }
+                continue;
+            }
+
+            let mut file = match File::open(operand) {
+                Ok(file) => file,
+                Err(e) => {
+                    writeln!(
+                        stderr,
+                        "tail: cannot open '{}' for reading: {}",
+                        operand,
+                        error::describe(&e)
+                    )?;
+                    status = 1;
+                    if options.retry {
+                        followed.push(Followed {
+                            name: operand.clone(),
+                            file: None,
+                            identity: None,
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            self.print_header(headers, &mut last_shown, index, operand, stdout)?;
+            match self.tail_file(&mut file, &options, stdout) {
+                Ok(()) => {
+                    let identity = file
+                        .metadata()
+                        .ok()
+                        .map(|metadata| (metadata.dev(), metadata.ino()));
+                    followed.push(Followed {
+                        name: operand.clone(),
+                        file: Some(file),
+                        identity,
+                    });
+                }
+                Err(e) => {
+                    writeln!(
+                        stderr,
+                        "tail: error reading '{}': {}",
+                        operand,
+                        error::describe(&e)
+                    )?;
+                    status = 1;
+                }
+            }
+        }
+        stdout.flush()?;
+
+        if let Some(mode) = options.follow {
+            if !followed.is_empty() {
+                return self.follow(
+                    followed, mode, &options, headers, last_shown, stdout, stderr,
+                );
+            }
+            // Every file named failed to open
+            if operands.iter().any(|operand| operand != "-") {
+                writeln!(stderr, "tail: no files remaining")?;
+            }
+        }
+        if status != 0 {
+            return Err(ShellError::status(status));
+        }
+        Ok(())
+    }
+}
+
+impl Tail {
+    fn parse_args(&self, args: &[String]) -> Result<(TailOptions, Vec<String>)> {
+        let mut options = TailOptions {
+            unit: Unit::Lines,
+            count: 10,
+            from_start: false,
+            follow: None,
+            retry: false,
+            sleep: Duration::from_secs(1),
+            headers: None,
+        };
+
+        // The old "tail -5" form means -n 5
+        let mut args = args.to_vec();
+        if let Some(count) = args.first().and_then(|arg| obsolete_count(arg)) {
+            args.splice(0..1, ["-n".to_string(), count.to_string()]);
+        }
+
+        let mut getopt = Getopt::new("tail", &args, "c:Ffn:qs:v", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            let value = option.value.unwrap_or_default();
+            match option.name {
+                "n" | "c" => {
+                    options.unit = if option.name == "n" {
+                        Unit::Lines
+                    } else {
+                        Unit::Bytes
+                    };
+                    // +N starts at the Nth; N and -N are both the last N
+                    let (from_start, digits) = match value.strip_prefix('+') {
+                        Some(digits) => (true, digits),
+                        None => (false, value.strip_prefix('-').unwrap_or(value)),
+                    };
+                    options.from_start = from_start;
+                    options.count = size::parse_size(digits).ok_or_else(|| {
+                        ShellError::invalid_arguments(&format!(
+                            "tail: invalid number of {}: '{}'",
+                            if options.unit == Unit::Lines {
+                                "lines"
+                            } else {
+                                "bytes"
+                            },
+                            value
+                        ))
+                    })?;
+                }
+                "f" => {
+                    options.follow = match option.value {
+                        None | Some("descriptor") => Some(Follow::Descriptor),
+                        Some("name") => Some(Follow::Name),
+                        Some(other) => {
+                            return Err(ShellError::invalid_arguments(&format!(
+                                "tail: invalid argument '{}' for '--follow'",
+                                other
+                            )));
+                        }
+                    }
+                }
+                "F" => {
+                    options.follow = Some(Follow::Name);
+                    options.retry = true;
+                }
+                "retry" => options.retry = true,
+                "s" => {
+                    let seconds = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|s| s.is_finite() && *s >= 0.0);
+                    options.sleep = seconds.map(Duration::from_secs_f64).ok_or_else(|| {
+                        ShellError::invalid_arguments(&format!(
+                            "tail: invalid number of seconds: '{}'",
+                            value
+                        ))
+                    })?;
+                }
+                "q" => options.headers = Some(false),
+                "v" => options.headers = Some(true),
+                _ => {}
+            }
+        }
+
+        Ok((options, getopt.operands()))
+    }
+
+    // "==> name <==" before a file's output, when it isn't the file last shown
+    fn print_header(
+        &self,
+        headers: bool,
+        last_shown: &mut Option<usize>,
+        index: usize,
+        name: &str,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        if headers && *last_shown != Some(index) {
+            let separator = if last_shown.is_some() { "\n" } else { "" };
+            writeln!(stdout, "{}==> {} <==", separator, name)?;
+        }
+        *last_shown = Some(index);
+        Ok(())
+    }
+
+    // A regular file is read from the end backwards instead of from the start, so
+    // the last lines of a large log come back at once. Either way the file is left
+    // positioned at its end, where following it picks up.
+    fn tail_file(
+        &self,
+        file: &mut File,
+        options: &TailOptions,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        if options.from_start || !file.metadata()?.is_file() {
+            return self.tail_stream(file, options, stdout);
+        }
+
+        let size = file.seek(SeekFrom::End(0))?;
+        let start = match options.unit {
+            Unit::Bytes => size.saturating_sub(options.count),
+            Unit::Lines => self.last_lines_start(file, size, options.count)?,
+        };
+        file.seek(SeekFrom::Start(start))?;
+        io::copy(file, stdout)?;
+        Ok(())
+    }
+
+    // The offset where the last count lines of the file begin
+    fn last_lines_start(&self, file: &mut File, size: u64, count: u64) -> io::Result<u64> {
+        if count == 0 {
+            return Ok(size);
+        }
+
+        let mut buffer = [0u8; BUF_SIZE];
+        let mut position = size;
+        let mut newlines = 0;
+        while position > 0 {
+            let length = (position.min(BUF_SIZE as u64)) as usize;
+            position -= length as u64;
+            file.seek(SeekFrom::Start(position))?;
+            file.read_exact(&mut buffer[..length])?;
+
+            for index in (0..length).rev() {
+                let offset = position + index as u64;
+                // The newline ending the last line doesn't start another one
+                if buffer[index] != b'\n' || offset + 1 == size {
+                    continue;
+                }
+                newlines += 1;
+                if newlines == count {
+                    return Ok(offset + 1);
+                }
+            }
+        }
+        Ok(0)
+    }
+
+    // Anything that can only be read forwards, holding on to just the part that
+    // may still turn out to be the tail
+    fn tail_stream(
+        &self,
+        input: &mut dyn Read,
+        options: &TailOptions,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut reader = BufReader::new(input);
+
+        if options.from_start {
+            // +0 and +1 both mean from the beginning
+            let skip = options.count.saturating_sub(1);
+            match options.unit {
+                Unit::Bytes => {
+                    io::copy(&mut (&mut reader).take(skip), &mut io::sink())?;
+                }
+                Unit::Lines => {
+                    let mut line = Vec::new();
+                    for _ in 0..skip {
+                        line.clear();
+                        if reader.read_until(b'\n', &mut line)? == 0 {
+                            break;
+                        }
+                    }
+                }
+            }
+            io::copy(&mut reader, stdout)?;
+            return Ok(());
+        }
+
+        let count = usize::try_from(options.count).unwrap_or(usize::MAX);
+        match options.unit {
+            Unit::Bytes => {
+                let mut kept: VecDeque<u8> = VecDeque::new();
+                let mut buffer = [0u8; BUF_SIZE];
+                loop {
+                    let read = match reader.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(read) => read,
+                        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e),
+                    };
+                    kept.extend(&buffer[..read]);
+                    if kept.len() > count {
+                        kept.drain(..kept.len() - count);
+                    }
+                }
+                let (front, back) = kept.as_slices();
+                stdout.write_all(front)?;
+                stdout.write_all(back)?;
+            }
+            Unit::Lines => {
+                let mut kept: VecDeque<Vec<u8>> = VecDeque::new();
+                loop {
+                    let mut line = Vec::new();
+                    if reader.read_until(b'\n', &mut line)? == 0 {
+                        break;
+                    }
+                    kept.push_back(line);
+                    if kept.len() > count {
+                        kept.pop_front();
+                    }
+                }
+                for line in kept {
+                    stdout.write_all(&line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Print whatever is appended to the files until Ctrl+C
+    #[allow(clippy::too_many_arguments)]
+    fn follow(
+        &self,
+        mut files: Vec<Followed>,
+        mode: Follow,
+        options: &TailOptions,
+        headers: bool,
+        mut last_shown: Option<usize>,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let guard = InterruptGuard::new();
+        loop {
+            for (index, followed) in files.iter_mut().enumerate() {
+                if let Err(e) =
+                    self.read_appended(followed, index, headers, &mut last_shown, stdout, stderr)
+                {
+                    writeln!(
+                        stderr,
+                        "tail: error reading '{}': {}",
+                        followed.name,
+                        error::describe(&e)
+                    )?;
+                    followed.file = None;
+                }
+                if mode == Follow::Name {
+                    self.check_name(followed, stderr)?;
+                }
+            }
+            stdout.flush()?;
+
+            if !options.retry && files.iter().all(|followed| followed.file.is_none()) {
+                writeln!(stderr, "tail: no files remaining")?;
+                return Err(ShellError::status(1));
+            }
+
+            // Sleep in short steps so Ctrl+C is noticed promptly
+            let mut waited = Duration::ZERO;
+            while waited < options.sleep && !guard.interrupted() {
+                let step = INTERRUPT_POLL.min(options.sleep - waited);
+                thread::sleep(step);
+                waited += step;
+            }
+            if guard.interrupted() {
+                // Like a command killed by SIGINT, without taking the shell down with it
+                return Err(ShellError::status(130));
+            }
+        }
+    }
+
+    fn read_appended(
+        &self,
+        followed: &mut Followed,
+        index: usize,
+        headers: bool,
+        last_shown: &mut Option<usize>,
+        stdout: &mut dyn Write,
+        stderr: &mut dyn Write,
+    ) -> io::Result<()> {
+        let Some(file) = &mut followed.file else {
+            return Ok(());
+        };
+
+        // A log cut short in place starts over from its beginning
+        let metadata = file.metadata()?;
+        if metadata.is_file() && metadata.len() < file.stream_position()? {
+            writeln!(stderr, "tail: {}: file truncated", followed.name)?;
+            file.seek(SeekFrom::Start(0))?;
+        }
+
+        let mut buffer = [0u8; BUF_SIZE];
+        loop {
+            let read = match file.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.print_header(headers, last_shown, index, &followed.name, stdout)?;
+            stdout.write_all(&buffer[..read])?;
+        }
+    }
+
+    // -F: switch to whatever file the name refers to now. The old file has already
+    // been read to its end, so nothing written before the rotation is lost.
+    fn check_name(&self, followed: &mut Followed, stderr: &mut dyn Write) -> io::Result<()> {
+        let metadata = match fs::metadata(&followed.name) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                if followed.file.take().is_some() {
+                    writeln!(
+                        stderr,
+                        "tail: '{}' has become inaccessible: {}",
+                        followed.name,
+                        error::describe(&e)
+                    )?;
+                }
+                followed.identity = None;
+                return Ok(());
+            }
+        };
+
+        let identity = (metadata.dev(), metadata.ino());
+        if followed.identity == Some(identity) {
+            return Ok(());
+        }
+        // Not readable yet: try again next time round
+        let Ok(file) = File::open(&followed.name) else {
+            return Ok(());
+        };
+        let change = if followed.file.is_some() {
+            "has been replaced"
+        } else {
+            "has appeared"
+        };
+        writeln!(
+            stderr,
+            "tail: '{}' {};  following new file",
+            followed.name, change
+        )?;
+        followed.file = Some(file);
+        followed.identity = Some(identity);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use crate::utils::scratch::Scratch;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn tail(args: &[&str], stdin: &str) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut stdout = Vec::new();
+        Tail.execute(&args, &mut stdin.as_bytes(), &mut stdout, &mut Vec::new())
+            .unwrap_or_else(|e| panic!("{}", e));
+        String::from_utf8(stdout).unwrap()
+    }
+
+    // The same input read forwards from stdin and backwards from a file, which
+    // have to agree
+    fn both(args: &[&str], input: &str) -> String {
+        // Tests run in parallel, so each call gets its own directory
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let call = CALLS.fetch_add(1, Ordering::Relaxed);
+        let dir = Scratch::new(&format!("tail-{}", call), &[]);
+        dir.write("input", input);
+        let from_stdin = tail(args, input);
+        let mut file_args = args.to_vec();
+        let file = dir.arg("input");
+        file_args.push(&file);
+        assert_eq!(tail(&file_args, ""), from_stdin, "tail {:?}", args);
+        from_stdin
+    }
+
+    #[test]
+    fn line_counts() {
+        let input = "1\n2\n3\n4\n";
+        assert_eq!(both(&["-n", "2"], input), "3\n4\n");
+        assert_eq!(both(&["-2"], input), "3\n4\n");
+        assert_eq!(both(&["-n", "-2"], input), "3\n4\n");
+        assert_eq!(both(&["-n", "0"], input), "");
+        assert_eq!(both(&["-n", "9"], input), input);
+        // A last line without a newline still counts
+        assert_eq!(both(&["-n", "1"], "1\n2"), "2");
+        assert_eq!(both(&["-n", "2"], "1\n\n\n"), "\n\n");
+
+        // +N starts at line N, and +0 is the same as +1
+        assert_eq!(both(&["-n", "+2"], input), "2\n3\n4\n");
+        assert_eq!(both(&["-n", "+1"], input), input);
+        assert_eq!(both(&["-n", "+0"], input), input);
+        assert_eq!(both(&["-n", "+9"], input), "");
+    }
+
+    #[test]
+    fn byte_counts() {
+        let input = "abcdef\ngh";
+        assert_eq!(both(&["-c", "3"], input), "\ngh");
+        assert_eq!(both(&["-c", "-3"], input), "\ngh");
+        assert_eq!(both(&["-c", "+3"], input), "cdef\ngh");
+        assert_eq!(both(&["--bytes=+1"], input), input);
+        assert_eq!(both(&["-c", "100"], input), input);
+        assert_eq!(both(&["-c", "0"], input), "");
+        // The last of -n and -c decides the unit
+        assert_eq!(both(&["-n", "1", "-c", "2"], input), "gh");
+    }
+
+    #[test]
+    fn files_read_backwards_across_blocks() {
+        // Lines long enough that the last ones span several reads from the end
+        let lines: Vec<String> = (0..50)
+            .map(|i| format!("{:04}{}\n", i, "x".repeat(500)))
+            .collect();
+        let input = lines.concat();
+        assert!(input.len() > 2 * BUF_SIZE);
+        assert_eq!(both(&["-n", "20"], &input), lines[30..].concat());
+        assert_eq!(both(&["-n", "+31"], &input), lines[30..].concat());
+        assert_eq!(both(&["-c", "1k"], &input), &input[input.len() - 1024..]);
+    }
+}
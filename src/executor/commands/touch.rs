@@ -0,0 +1,349 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::sys;
+use crate::utils::terminal::OutputStream;
+use crate::utils::time::{self, DateTime, TimeZone};
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::path::Path;
+use std::time::SystemTime;
+
+pub struct Touch;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("date", HasArg::Required, Some('d')),
+    long("no-create", HasArg::No, Some('c')),
+    long("no-dereference", HasArg::No, Some('h')),
+    long("reference", HasArg::Required, Some('r')),
+    long("time", HasArg::Required, None),
+];
+
+// Seconds and nanoseconds since the epoch
+type Timestamp = (i64, u32);
+
+#[derive(Default)]
+struct TouchOptions {
+    access: bool,              // -a: change the access time
+    modify: bool,              // -m: change the modification time
+    no_create: bool,           // -c
+    no_dereference: bool,      // -h: change a symlink itself
+    date: Option<String>,      // -d
+    stamp: Option<String>,     // -t
+    reference: Option<String>, // -r
+}
+
+impl super::Command for Touch {
+    fn execute(
+        &self,
+        args: &[String],
+        _stdin: &mut dyn Read, // touch doesn't need stdin
+        _stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, operands) = self.parse_args(args)?;
+        if operands.is_empty() {
+            return Err(ShellError::invalid_arguments("touch: missing file operand"));
+        }
+
+        let (accessed, modified) = self.times(&options, stderr)?;
+        // Without -a or -m both times change
+        let accessed = (options.access || !options.modify).then_some(accessed);
+        let modified = (options.modify || !options.access).then_some(modified);
+
+        // A failed operand is reported and the rest are still touched
+        let mut status = 0;
+        for operand in &operands {
+            if !self.touch(Path::new(operand), accessed, modified, &options, stderr)? {
+                status = 1;
+            }
+        }
+
+        if status != 0 {
+            return Err(ShellError::status(status));
+        }
+        Ok(())
+    }
+}
+
+impl Touch {
+    fn parse_args(&self, args: &[String]) -> Result<(TouchOptions, Vec<String>)> {
+        let mut options = TouchOptions::default();
+
+        let mut getopt = Getopt::new("touch", args, "acd:fhmr:t:", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            let value = option.value.unwrap_or_default();
+            match option.name {
+                "a" => options.access = true,
+                "m" => options.modify = true,
+                "c" => options.no_create = true,
+                "h" => options.no_dereference = true,
+                "d" => options.date = Some(value.to_string()),
+                "t" => options.stamp = Some(value.to_string()),
+                "r" => options.reference = Some(value.to_string()),
+                "time" => match value {
+                    "access" | "atime" | "use" => options.access = true,
+                    "modify" | "mtime" => options.modify = true,
+                    _ => {
+                        return Err(ShellError::invalid_arguments(&format!(
+                            "touch: invalid argument '{}' for '--time'",
+                            value
+                        )));
+                    }
+                },
+                _ => {} // -f is accepted and ignored, as in coreutils
+            }
+        }
+
+        if options.stamp.is_some() && (options.date.is_some() || options.reference.is_some()) {
+            return Err(ShellError::invalid_arguments(
+                "touch: cannot specify times from more than one source",
+            ));
+        }
+        Ok((options, getopt.operands()))
+    }
+
+    // The new access and modification times: now, or the reference file's times, with
+    // -d read relative to them; or the -t stamp
+    fn times(
+        &self,
+        options: &TouchOptions,
+        stderr: &mut dyn Write,
+    ) -> Result<(Timestamp, Timestamp)> {
+        let zone = TimeZone::local();
+        let now = time::split_time(SystemTime::now());
+
+        if let Some(stamp) = &options.stamp {
+            let time = parse_stamp(stamp, now, zone).ok_or_else(|| {
+                ShellError::invalid_arguments(&format!("touch: invalid date format '{}'", stamp))
+            })?;
+            return Ok((time, time));
+        }
+
+        let (accessed, modified) = match &options.reference {
+            Some(reference) => {
+                let metadata = if options.no_dereference {
+                    fs::symlink_metadata(reference)
+                } else {
+                    fs::metadata(reference)
+                };
+                match metadata {
+                    Ok(metadata) => (
+                        (metadata.atime(), metadata.atime_nsec() as u32),
+                        (metadata.mtime(), metadata.mtime_nsec() as u32),
+                    ),
+                    Err(e) => {
+                        writeln!(
+                            stderr,
+                            "touch: failed to get attributes of '{}': {}",
+                            reference,
+                            error::describe(&e)
+                        )?;
+                        return Err(ShellError::status(1));
+                    }
+                }
+            }
+            None => (now, now),
+        };
+
+        match &options.date {
+            Some(date) => {
+                let parse = |base| {
+                    time::parse_date(date, base, zone).ok_or_else(|| {
+                        ShellError::invalid_arguments(&format!(
+                            "touch: invalid date format '{}'",
+                            date
+                        ))
+                    })
+                };
+                Ok((parse(accessed)?, parse(modified)?))
+            }
+            None => Ok((accessed, modified)),
+        }
+    }
+
+    // Returns false when the failure has been reported
+    fn touch(
+        &self,
+        path: &Path,
+        accessed: Option<Timestamp>,
+        modified: Option<Timestamp>,
+        options: &TouchOptions,
+        stderr: &mut dyn Write,
+    ) -> io::Result<bool> {
+        // Opening creates the file if need be. Whether it fails only matters when
+        // setting the times fails too: directories and files we can't write to can
+        // still have their times changed.
+        let open_error = if options.no_create || options.no_dereference {
+            None
+        } else {
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .custom_flags(sys::O_NONBLOCK | sys::O_NOCTTY) // don't hang on a FIFO
+                .open(path)
+                .err()
+        };
+
+        let to_spec =
+            |time: Option<Timestamp>| time.map(|(seconds, nanos)| (seconds, nanos as i64));
+        let Err(e) = sys::set_times(
+            path,
+            to_spec(accessed),
+            to_spec(modified),
+            !options.no_dereference,
+        ) else {
+            return Ok(true);
+        };
+
+        if let Some(open_error) = open_error {
+            writeln!(
+                stderr,
+                "touch: cannot touch '{}': {}",
+                path.display(),
+                error::describe(&open_error)
+            )?;
+        } else if options.no_create && e.kind() == ErrorKind::NotFound {
+            return Ok(true);
+        } else {
+            writeln!(
+                stderr,
+                "touch: setting times of '{}': {}",
+                path.display(),
+                error::describe(&e)
+            )?;
+        }
+        Ok(false)
+    }
+}
+
+// -t [[CC]YY]MMDDhhmm[.ss] in local time. Without a century, 69-99 are the 1900s and
+// 00-68 the 2000s; without a year, it's the current one.
+fn parse_stamp(stamp: &str, now: Timestamp, zone: &TimeZone) -> Option<Timestamp> {
+    let (digits, seconds) = match stamp.split_once('.') {
+        Some((digits, seconds)) => (digits, Some(seconds)),
+        None => (stamp, None),
+    };
+    let all_digits = |text: &str| text.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(digits) {
+        return None;
+    }
+    let second: u32 = match seconds {
+        Some(seconds) if seconds.len() == 2 && all_digits(seconds) => seconds.parse().ok()?,
+        Some(_) => return None,
+        None => 0,
+    };
+
+    let (year, rest) = match digits.len() {
+        8 => {
+            let today = DateTime::from_unix(now.0, now.1, zone.offset_at(now.0));
+            (today.year, digits)
+        }
+        10 => {
+            let year: i64 = digits[..2].parse().ok()?;
+            (
+                if year >= 69 { 1900 + year } else { 2000 + year },
+                &digits[2..],
+            )
+        }
+        12 => (digits[..4].parse().ok()?, &digits[4..]),
+        _ => return None,
+    };
+    let field = |index: usize| rest[index..index + 2].parse::<u32>().ok();
+    let (month, day, hour, minute) = (field(0)?, field(2)?, field(4)?, field(6)?);
+
+    let valid = (1..=12).contains(&month)
+        && (1..=time::days_in_month(year, month)).contains(&day)
+        && hour < 24
+        && minute < 60
+        && second <= 60;
+    if !valid {
+        return None;
+    }
+
+    let local = time::days_from_civil(year, month, day) * 86_400
+        + (hour * 3600 + minute * 60 + second) as i64;
+    Some((zone.to_utc(local), 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: Timestamp = (1_719_835_200, 0); // 2024-07-01 12:00:00Z
+
+    fn stamp(text: &str) -> Option<i64> {
+        parse_stamp(text, NOW, &TimeZone::utc()).map(|(seconds, nanos)| {
+            assert_eq!(nanos, 0);
+            seconds
+        })
+    }
+
+    #[test]
+    fn digits_for_month_day_hour_minute_and_years() {
+        // MMDDhhmm is in the current year
+        assert_eq!(stamp("07011230"), Some(1_719_837_000));
+        assert_eq!(stamp("2401011230"), stamp("202401011230"));
+        assert_eq!(stamp("202407011230"), Some(1_719_837_000));
+        assert_eq!(stamp("200001010000"), Some(946_684_800));
+    }
+
+    #[test]
+    fn two_digit_years_pivot_at_69() {
+        assert_eq!(stamp("6901010000"), Some(-31_536_000)); // 1969
+        assert_eq!(stamp("6801010000"), Some(3_092_601_600)); // 2068
+        assert_eq!(stamp("9912312359"), stamp("199912312359"));
+        assert_eq!(stamp("0001010000"), Some(946_684_800));
+    }
+
+    #[test]
+    fn seconds() {
+        assert_eq!(stamp("07011230.45"), Some(1_719_837_045));
+        assert_eq!(stamp("202402291200.30"), Some(1_709_208_030));
+        // A leap second is accepted, as POSIX allows
+        assert_eq!(stamp("07011230.60"), Some(1_719_837_060));
+        assert_eq!(stamp("07011230.5"), None);
+        assert_eq!(stamp("07011230.123"), None);
+        assert_eq!(stamp("07011230.61"), None);
+        assert_eq!(stamp("07011230."), None);
+    }
+
+    #[test]
+    fn invalid_stamps() {
+        assert_eq!(stamp("202402301200"), None); // February 30th
+        assert_eq!(stamp("202302291200"), None); // not a leap year
+        assert_eq!(stamp("202404311200"), None);
+        assert_eq!(stamp("13011200"), None);
+        assert_eq!(stamp("00011200"), None);
+        assert_eq!(stamp("07012400"), None);
+        assert_eq!(stamp("07011260"), None);
+        for length in ["0701123", "070112300", "07011230000", "2024070112300"] {
+            assert_eq!(stamp(length), None, "{}", length);
+        }
+        assert_eq!(stamp("0701-230"), None);
+        assert_eq!(stamp("+7011230"), None);
+        assert_eq!(stamp(""), None);
+    }
+
+    #[test]
+    fn local_time() {
+        let zone = TimeZone::parse(&tzif_utc_plus_two()).unwrap();
+        assert_eq!(
+            parse_stamp("07011430", NOW, &zone),
+            Some((1_719_837_000, 0))
+        );
+    }
+
+    // TZif data for a zone two hours east of UTC, with no transitions
+    fn tzif_utc_plus_two() -> Vec<u8> {
+        let mut data = b"TZif".to_vec();
+        data.extend([0; 16]);
+        for count in [0u32, 0, 0, 0, 1, 4] {
+            data.extend(count.to_be_bytes());
+        }
+        data.extend(7200i32.to_be_bytes());
+        data.extend([0, 0]);
+        data.extend(b"XXX\0");
+        data
+    }
+}
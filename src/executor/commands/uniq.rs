@@ -0,0 +1,151 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::terminal::OutputStream;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+
+pub struct Uniq;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("count", HasArg::No, Some('c')),
+    long("repeated", HasArg::No, Some('d')),
+    long("unique", HasArg::No, Some('u')),
+];
+
+#[derive(Default)]
+struct UniqOptions {
+    count: bool,    // -c: prefix each line with how often it occurred
+    repeated: bool, // -d: only lines that occur more than once
+    unique: bool,   // -u: only lines that occur once
+}
+
+impl super::Command for Uniq {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, operands) = self.parse_args(args)?;
+        if operands.len() > 2 {
+            return Err(ShellError::invalid_arguments(&format!(
+                "uniq: extra operand '{}'",
+                operands[2]
+            )));
+        }
+
+        // uniq [INPUT [OUTPUT]]
+        let mut file;
+        let input: &mut dyn Read = match operands.first().map(String::as_str) {
+            None | Some("-") => stdin,
+            Some(path) => match File::open(path) {
+                Ok(opened) => {
+                    file = opened;
+                    &mut file
+                }
+                Err(e) => {
+                    writeln!(stderr, "uniq: {}: {}", path, error::describe(&e))?;
+                    return Err(ShellError::status(1));
+                }
+            },
+        };
+        let mut output_file;
+        let output: &mut dyn Write = match operands.get(1).map(String::as_str) {
+            None | Some("-") => stdout,
+            Some(path) => match File::create(path) {
+                Ok(created) => {
+                    output_file = created;
+                    &mut output_file
+                }
+                Err(e) => {
+                    writeln!(stderr, "uniq: {}: {}", path, error::describe(&e))?;
+                    return Err(ShellError::status(1));
+                }
+            },
+        };
+
+        if let Err(e) = self.uniq(input, output, &options) {
+            writeln!(
+                stderr,
+                "uniq: {}: {}",
+                operands.first().map_or("-", String::as_str),
+                error::describe(&e)
+            )?;
+            return Err(ShellError::status(1));
+        }
+        Ok(())
+    }
+}
+
+impl Uniq {
+    fn parse_args(&self, args: &[String]) -> Result<(UniqOptions, Vec<String>)> {
+        let mut options = UniqOptions::default();
+
+        let mut getopt = Getopt::new("uniq", args, "cdu", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            match option.name {
+                "c" => options.count = true,
+                "d" => options.repeated = true,
+                "u" => options.unique = true,
+                _ => {}
+            }
+        }
+
+        Ok((options, getopt.operands()))
+    }
+
+    // Only adjacent lines are compared, so each run of equal lines is one group
+    fn uniq(
+        &self,
+        input: &mut dyn Read,
+        output: &mut dyn Write,
+        options: &UniqOptions,
+    ) -> io::Result<()> {
+        let mut reader = BufReader::new(input);
+        let mut writer = BufWriter::new(output);
+        let mut group: Option<(Vec<u8>, u64)> = None;
+        loop {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+
+            match &mut group {
+                Some((first, count)) if *first == line => *count += 1,
+                _ => {
+                    if let Some((first, count)) = group.replace((line, 1)) {
+                        self.print_group(&first, count, options, &mut writer)?;
+                    }
+                }
+            }
+        }
+        if let Some((first, count)) = group {
+            self.print_group(&first, count, options, &mut writer)?;
+        }
+        writer.flush()
+    }
+
+    fn print_group(
+        &self,
+        line: &[u8],
+        count: u64,
+        options: &UniqOptions,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        if (options.repeated && count == 1) || (options.unique && count > 1) {
+            return Ok(());
+        }
+        if options.count {
+            write!(writer, "{:>7} ", count)?;
+        }
+        writer.write_all(line)?;
+        writer.write_all(b"\n")
+    }
+}
@@ -0,0 +1,326 @@
+use crate::utils::error::{self, Result, ShellError};
+use crate::utils::getopt::{Getopt, HasArg, LongOption, long};
+use crate::utils::terminal::OutputStream;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read, Write};
+
+pub struct Wc;
+
+const LONG_OPTIONS: &[LongOption] = &[
+    long("bytes", HasArg::No, Some('c')),
+    long("chars", HasArg::No, Some('m')),
+    long("lines", HasArg::No, Some('l')),
+    long("words", HasArg::No, Some('w')),
+];
+
+const BUF_SIZE: usize = 8192;
+
+#[derive(Default)]
+struct WcOptions {
+    lines: bool, // -l
+    words: bool, // -w
+    chars: bool, // -m
+    bytes: bool, // -c
+}
+
+#[derive(Default, Clone, Copy)]
+struct Counts {
+    lines: u64,
+    words: u64,
+    chars: u64,
+    bytes: u64,
+}
+
+impl Counts {
+    fn add(&mut self, other: &Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+    }
+}
+
+impl super::Command for Wc {
+    fn execute(
+        &self,
+        args: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let (options, operands) = self.parse_args(args)?;
+        let width = self.number_width(&operands, &options);
+
+        // Without operands standard input is counted, and no name is printed
+        let inputs: Vec<Option<&str>> = if operands.is_empty() {
+            vec![None]
+        } else {
+            operands
+                .iter()
+                .map(|operand| Some(operand.as_str()))
+                .collect()
+        };
+
+        let mut status = 0;
+        let mut total = Counts::default();
+        for name in &inputs {
+            let result = match name {
+                None | Some("-") => self.count(stdin),
+                Some(path) => match File::open(path) {
+                    Ok(mut file) => self.count(&mut file),
+                    Err(e) => {
+                        writeln!(stderr, "wc: {}: {}", path, error::describe(&e))?;
+                        status = 1;
+                        continue;
+                    }
+                },
+            };
+
+            // A read error still leaves the counts so far to print, as for a directory
+            let counts = match result {
+                Ok(counts) => counts,
+                Err((counts, e)) => {
+                    writeln!(
+                        stderr,
+                        "wc: {}: {}",
+                        name.unwrap_or("-"),
+                        error::describe(&e)
+                    )?;
+                    status = 1;
+                    counts
+                }
+            };
+            self.print(&counts, *name, &options, width, stdout)?;
+            total.add(&counts);
+        }
+        if inputs.len() > 1 {
+            self.print(&total, Some("total"), &options, width, stdout)?;
+        }
+
+        stdout.flush()?;
+        if status != 0 {
+            return Err(ShellError::status(status));
+        }
+        Ok(())
+    }
+}
+
+impl Wc {
+    fn parse_args(&self, args: &[String]) -> Result<(WcOptions, Vec<String>)> {
+        let mut options = WcOptions::default();
+
+        let mut getopt = Getopt::new("wc", args, "clmw", LONG_OPTIONS);
+        while let Some(option) = getopt.next_option()? {
+            match option.name {
+                "l" => options.lines = true,
+                "w" => options.words = true,
+                "m" => options.chars = true,
+                "c" => options.bytes = true,
+                _ => {}
+            }
+        }
+        // By default: lines, words and bytes
+        if !(options.lines || options.words || options.chars || options.bytes) {
+            options.lines = true;
+            options.words = true;
+            options.bytes = true;
+        }
+
+        Ok((options, getopt.operands()))
+    }
+
+    // Columns are as wide as the combined size of the regular files needs, so they
+    // line up without reading anything twice. Input whose size can't be known ahead,
+    // such as standard input, gets at least 7. A single count for a single input
+    // isn't padded at all.
+    fn number_width(&self, operands: &[String], options: &WcOptions) -> usize {
+        let selected = [options.lines, options.words, options.chars, options.bytes]
+            .iter()
+            .filter(|&&selected| selected)
+            .count();
+        if operands.len() <= 1 && selected == 1 {
+            return 1;
+        }
+
+        let mut minimum = 1;
+        let mut regular_total: u64 = 0;
+        if operands.is_empty() {
+            minimum = 7;
+        }
+        for operand in operands {
+            if operand == "-" {
+                minimum = 7;
+                continue;
+            }
+            // Missing files are reported later and take no room
+            match fs::metadata(operand) {
+                Ok(metadata) if metadata.is_file() => regular_total += metadata.len(),
+                Ok(_) => minimum = 7,
+                Err(_) => {}
+            }
+        }
+
+        regular_total.to_string().len().max(minimum)
+    }
+
+    // Count one input a chunk at a time. On a read error the counts up to it come
+    // back with the error.
+    fn count(&self, input: &mut dyn Read) -> std::result::Result<Counts, (Counts, io::Error)> {
+        let mut counts = Counts::default();
+        let mut in_word = false;
+        let mut buffer = [0u8; BUF_SIZE];
+        loop {
+            let read = match input.read(&mut buffer) {
+                Ok(0) => return Ok(counts),
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err((counts, e)),
+            };
+
+            counts.bytes += read as u64;
+            for &byte in &buffer[..read] {
+                if byte == b'\n' {
+                    counts.lines += 1;
+                }
+                // Every byte but a UTF-8 continuation byte starts a character
+                if byte & 0xc0 != 0x80 {
+                    counts.chars += 1;
+                }
+                if byte.is_ascii_whitespace() || byte == b'\x0b' {
+                    in_word = false;
+                } else if !in_word {
+                    in_word = true;
+                    counts.words += 1;
+                }
+            }
+        }
+    }
+
+    fn print(
+        &self,
+        counts: &Counts,
+        name: Option<&str>,
+        options: &WcOptions,
+        width: usize,
+        stdout: &mut dyn Write,
+    ) -> io::Result<()> {
+        let columns = [
+            (options.lines, counts.lines),
+            (options.words, counts.words),
+            (options.chars, counts.chars),
+            (options.bytes, counts.bytes),
+        ];
+        let fields: Vec<String> = columns
+            .iter()
+            .filter(|(selected, _)| *selected)
+            .map(|(_, count)| format!("{:>width$}", count, width = width))
+            .collect();
+
+        let mut line = fields.join(" ");
+        if let Some(name) = name {
+            line.push(' ');
+            line.push_str(name);
+        }
+        writeln!(stdout, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::commands::Command;
+    use crate::utils::scratch::Scratch;
+
+    // Run wc on the given stdin, giving its stdout, stderr and status
+    fn wc(args: &[&str], stdin: &str) -> (String, String, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = match Wc.execute(&args, &mut stdin.as_bytes(), &mut stdout, &mut stderr) {
+            Ok(()) => 0,
+            Err(e) => e.status_code(),
+        };
+        (
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+            status,
+        )
+    }
+
+    #[test]
+    fn counts() {
+        let (stdout, _, _) = wc(&[], "one two\n  three\té\n\x0bfour");
+        assert_eq!(stdout, "      2       5      24\n");
+        assert_eq!(wc(&["-m"], "é\n").0, "2\n");
+        assert_eq!(wc(&["-c"], "é\n").0, "3\n");
+        assert_eq!(wc(&["-lw"], "").0, "      0       0\n");
+    }
+
+    #[test]
+    fn totals_share_the_column_width() {
+        let dir = Scratch::new("wc-totals", &["empty"]);
+        dir.write("big", &"word\n".repeat(250));
+        dir.write("small", "a b\n");
+
+        // 1254 bytes between them, so every column is four wide, the total included
+        let (stdout, stderr, status) =
+            wc(&[&dir.arg("big"), &dir.arg("small"), &dir.arg("empty")], "");
+        assert_eq!(
+            stdout,
+            format!(
+                " 250  250 1250 {}\n   1    2    4 {}\n   0    0    0 {}\n 251  252 1254 total\n",
+                dir.arg("big"),
+                dir.arg("small"),
+                dir.arg("empty")
+            )
+        );
+        assert_eq!((stderr.as_str(), status), ("", 0));
+
+        // One count of one file isn't padded, but with several files it is, to the
+        // width of their combined size
+        assert_eq!(
+            wc(&["-l", &dir.arg("big")], "").0,
+            format!("250 {}\n", dir.arg("big"))
+        );
+        assert_eq!(
+            wc(&["-l", &dir.arg("big"), &dir.arg("small")], "").0,
+            format!(
+                " 250 {}\n   1 {}\n 251 total\n",
+                dir.arg("big"),
+                dir.arg("small")
+            )
+        );
+
+        // Standard input's size isn't known ahead, so it widens everything to 7
+        assert_eq!(
+            wc(&["-l", &dir.arg("small"), "-"], "x\ny\n").0,
+            format!("      1 {}\n      2 -\n      3 total\n", dir.arg("small"))
+        );
+    }
+
+    #[test]
+    fn unreadable_inputs_still_count_towards_the_total() {
+        let dir = Scratch::new("wc-errors", &["sub/"]);
+        dir.write("file", "a\n");
+        let (stdout, stderr, status) = wc(
+            &["-l", &dir.arg("missing"), &dir.arg("sub"), &dir.arg("file")],
+            "",
+        );
+        assert_eq!(
+            stdout,
+            format!(
+                "      0 {}\n      1 {}\n      1 total\n",
+                dir.arg("sub"),
+                dir.arg("file")
+            )
+        );
+        assert_eq!(
+            stderr,
+            format!(
+                "wc: {}: No such file or directory\nwc: {}: Is a directory\n",
+                dir.arg("missing"),
+                dir.arg("sub")
+            )
+        );
+        assert_eq!(status, 1);
+    }
+}
@@ -1,18 +1,34 @@
 use super::commands;
 use crate::ast::nodes::AstNode;
+use crate::types::word::Word;
 use crate::utils::error::{Result, ShellError};
+use crate::utils::sys;
+use crate::utils::terminal::OutputStream;
+use crate::utils::users;
+use std::cell::{Cell, RefCell};
 use std::fs::{File, OpenOptions};
-use std::io::{self, Read, Write};
-use std::process::{Command, Stdio};
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{self, Command, ExitStatus, Stdio};
 
-pub struct Executor;
+pub struct Executor {
+    last_status: Cell<i32>,
+    // Whether the stdin commands are given is a terminal, for prompts like rm's
+    stdin_is_terminal: Cell<bool>,
+    exit_hooks: RefCell<Vec<Box<dyn FnOnce()>>>,
+}
 
 impl Executor {
     pub fn new() -> Self {
-        Executor
+        Executor {
+            last_status: Cell::new(0),
+            stdin_is_terminal: Cell::new(false),
+            exit_hooks: RefCell::new(Vec::new()),
+        }
     }
 
     pub fn execute_ast(&self, ast: &AstNode) -> Result<()> {
+        self.stdin_is_terminal.set(io::stdin().is_terminal());
         self.execute_ast_with_streams(
             ast,
             &mut io::stdin(),  // Use mutable references
@@ -21,17 +37,72 @@ impl Executor {
         )
     }
 
+    // Status of the most recently finished command, 0 for success
+    pub fn last_status(&self) -> i32 {
+        self.last_status.get()
+    }
+
+    // For failures outside any command, such as a line that doesn't parse
+    pub fn set_last_status(&self, code: i32) {
+        self.last_status.set(code);
+    }
+
+    // Whether the stdin of the command being run is a terminal. Redirections and
+    // pipes replace the shell's own stdin, so this is tracked as they are set up.
+    pub fn stdin_is_terminal(&self) -> bool {
+        self.stdin_is_terminal.get()
+    }
+
+    // Run with a stdin other than the one so far, which is a terminal or not
+    fn with_stdin<T>(&self, is_terminal: bool, run: impl FnOnce() -> T) -> T {
+        let previous = self.stdin_is_terminal.replace(is_terminal);
+        let result = run();
+        self.stdin_is_terminal.set(previous);
+        result
+    }
+
+    // Register cleanup to run when the shell exits, most recent first
+    pub fn on_exit(&self, hook: impl FnOnce() + 'static) {
+        self.exit_hooks.borrow_mut().push(Box::new(hook));
+    }
+
+    // Run the exit hooks and end the process with the given status
+    pub fn exit(&self, code: i32) -> ! {
+        let hooks = std::mem::take(&mut *self.exit_hooks.borrow_mut());
+        for hook in hooks.into_iter().rev() {
+            hook();
+        }
+        process::exit(code)
+    }
+
     fn execute_ast_with_streams(
         &self,
         ast: &AstNode,
         stdin: &mut dyn Read,
-        stdout: &mut dyn Write,
+        stdout: &mut dyn OutputStream,
         stderr: &mut dyn Write,
     ) -> Result<()> {
-        match ast {
-            AstNode::Command { name, flags, args } => {
-                self.execute_command(name, flags, args, stdin, stdout, stderr)
+        let result = self.execute_node(ast, stdin, stdout, stderr);
+        match &result {
+            Ok(()) => self.last_status.set(0),
+            // exit with no argument uses the status from before it ran
+            Err(ShellError::Exit(None)) => {
+                return Err(ShellError::Exit(Some(self.last_status.get())));
             }
+            Err(e) => self.last_status.set(e.status_code()),
+        }
+        result
+    }
+
+    fn execute_node(
+        &self,
+        ast: &AstNode,
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        match ast {
+            AstNode::Command { words } => self.execute_command(words, stdin, stdout, stderr),
             AstNode::Redirect {
                 command,
                 operator,
@@ -48,42 +119,54 @@ impl Executor {
 
     fn execute_command(
         &self,
-        name: &str,
-        flags: &[String],
-        args: &[String],
+        words: &[Word],
         stdin: &mut dyn Read,
-        stdout: &mut dyn Write,
+        stdout: &mut dyn OutputStream,
         stderr: &mut dyn Write,
     ) -> Result<()> {
+        // Tilde expansion: ~ and ~user at the start of a word
+        let argv: Vec<String> = words.iter().map(expand_word).collect();
+        self.run_command(&argv, stdin, stdout, stderr)
+    }
+
+    // Run an already expanded command line, a builtin or else a program. This is
+    // also how builtins such as find -exec run commands of their own.
+    pub fn run_command(
+        &self,
+        argv: &[String],
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> Result<()> {
+        let Some((name, args)) = argv.split_first() else {
+            return Ok(());
+        };
+
         // Check if it's a built-in command first
         if let Some(builtin_cmd) = commands::get_command(name) {
-            // Combine flags and args for built-in commands
-            let all_args: Vec<String> = flags.iter().chain(args.iter()).cloned().collect();
-
-            // For built-in commands, just execute normally
-            return builtin_cmd.execute(args, stdin, stdout, stderr);
+            return builtin_cmd.execute_with(self, args, stdin, stdout, stderr);
         }
 
         // For external commands, handle manually with the provided streams
-        self.execute_external_with_streams(name, flags, args, stdin, stdout, stderr)
+        self.execute_external_with_streams(name, args, stdin, stdout, stderr)
     }
 
     fn execute_external_with_streams(
         &self,
         name: &str,
-        flags: &[String],
         args: &[String],
         stdin: &mut dyn Read,
-        stdout: &mut dyn Write,
+        stdout: &mut dyn OutputStream,
         stderr: &mut dyn Write,
     ) -> Result<()> {
+        // The program gets exactly the argv that was typed
         let mut cmd = Command::new(name);
-        cmd.args(flags);
         cmd.args(args);
 
-        // Set up I/O streams using the provided stream objects
+        // Set up I/O streams using the provided stream objects. Output to a terminal,
+        // file or pipe goes there directly, so it streams as the program writes it.
         cmd.stdin(Stdio::piped());
-        cmd.stdout(Stdio::piped());
+        cmd.stdout(stdout.as_stdio().unwrap_or_else(Stdio::piped));
         cmd.stderr(Stdio::piped());
 
         let mut child = cmd.spawn().map_err(|e| {
@@ -110,7 +193,8 @@ impl Executor {
             .wait_with_output()
             .map_err(|e| ShellError::execution(&format!("Failed to get process output: {}", e)))?;
 
-        // Write child's output to our provided streams
+        // Write child's output to our provided streams (nothing was collected when it
+        // went to stdout directly)
         stdout
             .write_all(&output.stdout)
             .map_err(|e| ShellError::execution(&format!("Failed to write stdout: {}", e)))?;
@@ -119,12 +203,9 @@ impl Executor {
             .write_all(&output.stderr)
             .map_err(|e| ShellError::execution(&format!("Failed to write stderr: {}", e)))?;
 
+        // The command has already said what went wrong on its stderr
         if !output.status.success() {
-            return Err(ShellError::execution(&format!(
-                "Command '{}' failed with exit code: {}",
-                name,
-                output.status.code().unwrap_or(-1)
-            )));
+            return Err(ShellError::status(exit_code(output.status)));
         }
 
         Ok(())
@@ -134,11 +215,12 @@ impl Executor {
         &self,
         command: &AstNode,
         operator: &crate::types::tokens::Token,
-        file: &str,
+        file: &Word,
         stdin: &mut dyn Read,
-        stdout: &mut dyn Write,
+        stdout: &mut dyn OutputStream,
         stderr: &mut dyn Write,
     ) -> Result<()> {
+        let file = &expand_word(file);
         match operator {
             crate::types::tokens::Token::RedirectOut => {
                 let mut file_handle = File::create(file).map_err(|e| {
@@ -173,12 +255,14 @@ impl Executor {
                     ShellError::execution(&format!("Cannot open file '{}': {}", file, e))
                 })?;
 
-                self.execute_ast_with_streams(
-                    command,
-                    &mut file_handle, // Use file as stdin
-                    stdout,
-                    stderr,
-                )
+                self.with_stdin(file_handle.is_terminal(), || {
+                    self.execute_ast_with_streams(
+                        command,
+                        &mut file_handle, // Use file as stdin
+                        stdout,
+                        stderr,
+                    )
+                })
             }
             _ => Err(ShellError::execution(&format!(
                 "Unsupported redirection operator: {:?}",
@@ -192,25 +276,76 @@ impl Executor {
         left: &AstNode,
         right: &AstNode,
         stdin: &mut dyn Read,
-        stdout: &mut dyn Write,
+        stdout: &mut dyn OutputStream,
         stderr: &mut dyn Write,
     ) -> Result<()> {
-        // Use a memory buffer for the pipe
-        let mut buffer = Vec::new();
+        // Each side runs in its own forked copy of the shell, joined by a pipe, the
+        // way a subshell would: a cd or exit on either side ends with that copy and
+        // leaves this shell as it was. The two run at the same time, so output flows
+        // as it is written, and the pipeline's status is the right side's.
+        let (mut reader, mut writer) = sys::make_pipe()?;
+        // Whatever is still buffered would otherwise be written by every process
+        stdout.flush()?;
+        stderr.flush()?;
 
-        // Execute left command to buffer
-        {
-            let mut buffer_writer = io::Cursor::new(&mut buffer);
-            self.execute_ast_with_streams(left, stdin, &mut buffer_writer, stderr)?;
+        let left_pid = sys::fork_process()?;
+        if left_pid == 0 {
+            drop(reader);
+            // Once the right side stops reading, the left side ends quietly the way
+            // programs do, instead of reporting a broken pipe
+            // SAFETY: restoring the default disposition has no preconditions
+            unsafe { sys::signal(sys::SIGPIPE, sys::SIG_DFL) };
+            self.run_forked(left, stdin, &mut writer, stderr);
         }
+        drop(writer);
 
-        // Execute right command from buffer
-        {
-            let mut buffer_reader = io::Cursor::new(&buffer);
-            self.execute_ast_with_streams(right, &mut buffer_reader, stdout, stderr)?;
+        let right_pid = match sys::fork_process() {
+            Ok(pid) => pid,
+            Err(e) => {
+                // Closing the read end stops the left side before it is reaped
+                drop(reader);
+                sys::wait_for(left_pid)?;
+                return Err(e.into());
+            }
+        };
+        if right_pid == 0 {
+            // SAFETY: restoring the default disposition has no preconditions
+            unsafe { sys::signal(sys::SIGPIPE, sys::SIG_DFL) };
+            self.stdin_is_terminal.set(false);
+            self.run_forked(right, &mut reader, stdout, stderr);
         }
 
-        Ok(())
+        // Only the children may hold the pipe, or the right side would never see
+        // the end of its input
+        drop(reader);
+        sys::wait_for(left_pid)?;
+        match exit_code(sys::wait_for(right_pid)?) {
+            0 => Ok(()),
+            code => Err(ShellError::status(code)),
+        }
+    }
+
+    // The end of a forked copy of the shell: run the node, report what went wrong the
+    // way the shell would, and leave with its status. Exit hooks belong to the parent
+    // and aren't run here.
+    fn run_forked(
+        &self,
+        ast: &AstNode,
+        stdin: &mut dyn Read,
+        stdout: &mut dyn OutputStream,
+        stderr: &mut dyn Write,
+    ) -> ! {
+        let code = match self.execute_ast_with_streams(ast, stdin, stdout, stderr) {
+            Ok(()) => 0,
+            Err(ShellError::Status(code)) | Err(ShellError::Exit(Some(code))) => code,
+            Err(e) => {
+                let _ = writeln!(stderr, "Error: {}", e);
+                e.status_code()
+            }
+        };
+        let _ = stdout.flush();
+        let _ = stderr.flush();
+        process::exit(code)
     }
 
     fn execute_sequence(
@@ -218,10 +353,35 @@ impl Executor {
         left: &AstNode,
         right: &AstNode,
         stdin: &mut dyn Read,
-        stdout: &mut dyn Write,
+        stdout: &mut dyn OutputStream,
         stderr: &mut dyn Write,
     ) -> Result<()> {
-        self.execute_ast_with_streams(left, stdin, stdout, stderr)?;
+        // The right side runs whether or not the left succeeded; only exit stops it
+        match self.execute_ast_with_streams(left, stdin, stdout, stderr) {
+            Err(e @ ShellError::Exit(_)) => return Err(e),
+            Err(ShellError::Status(_)) | Ok(()) => {}
+            Err(e) => writeln!(stderr, "Error: {}", e)?,
+        }
         self.execute_ast_with_streams(right, stdin, stdout, stderr)
     }
 }
+
+// A finished process's status as the shell reports it: its exit code, or 128 + the
+// signal number if a signal killed it, like other shells
+fn exit_code(status: ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
+}
+
+// The word as the command sees it. A tilde is only expanded when it and the user name
+// after it are unquoted, so '~' and ~"root" stay as typed.
+fn expand_word(word: &Word) -> String {
+    let text = word.text();
+    let prefix = word.leading_literal();
+    if prefix.starts_with('~') && (prefix.contains('/') || word.segments.len() == 1) {
+        return users::expand_tilde(&text).unwrap_or(text);
+    }
+    text
+}
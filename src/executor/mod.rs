@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 pub mod executor;
 pub mod commands;
\ No newline at end of file
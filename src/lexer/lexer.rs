@@ -1,38 +1,29 @@
 use crate::types::tokens::{Token, TokenWithPos};
+use crate::types::word::{Segment, Word};
 use crate::utils::error::{Result, ShellError};
 
 pub struct Lexer {
-    input: String,
-    position: usize,
+    input: Vec<char>,
+    position: usize, // index of current_char; positions count characters, not bytes
     current_char: Option<char>,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Self {
-        let mut lexer = Lexer {
-            input: input.trim().to_string(),
+        let input: Vec<char> = input.chars().collect();
+        let current_char = input.first().copied();
+        Lexer {
+            input,
             position: 0,
-            current_char: None,
-        };
-        lexer.advance();
-        lexer
+            current_char,
+        }
     }
 
     fn advance(&mut self) {
-        if self.position < self.input.len() {
-            self.current_char = Some(self.input.chars().nth(self.position).unwrap());
+        if self.current_char.is_some() {
             self.position += 1;
-        } else {
-            self.current_char = None;
-        }
-    }
-
-    fn peek(&self) -> Option<char> {
-        if self.position < self.input.len() {
-            Some(self.input.chars().nth(self.position).unwrap())
-        } else {
-            None
         }
+        self.current_char = self.input.get(self.position).copied();
     }
 
     pub fn tokenize(&mut self) -> Result<Vec<TokenWithPos>> {
@@ -40,7 +31,7 @@ impl Lexer {
         let mut is_start_of_command = true;
 
         while self.current_char.is_some() {
-            let start = self.position - 1;
+            let start = self.position;
 
             // Skip whitespace
             if self.current_char.unwrap().is_whitespace() {
@@ -70,89 +61,222 @@ impl Lexer {
                     self.advance();
                     Token::Semicolon
                 }
-                '\'' | '"' => {
-                    let word = self.parse_quoted_string()?;
-                    self.classify_word(word, is_start_of_command)
-                }
                 _ => {
                     let word = self.parse_word()?;
+                    // A lone backslash before the newline continues the line, no word
+                    if word.segments.is_empty() {
+                        continue;
+                    }
                     self.classify_word(word, is_start_of_command)
                 }
             };
 
             // Update state for next token
-            is_start_of_command = match &token {
-                Token::Semicolon | Token::Pipe => true,
-                _ => false,
-            };
+            is_start_of_command = matches!(&token, Token::Semicolon | Token::Pipe);
 
-            let end = self.position - 1;
+            let end = self.position;
             tokens.push(TokenWithPos { token, start, end });
         }
 
         Ok(tokens)
     }
 
-    fn classify_word(&self, word: String, is_start_of_command: bool) -> Token {
+    fn classify_word(&self, word: Word, is_start_of_command: bool) -> Token {
         if is_start_of_command {
             Token::Command(word)
-        } else if word.starts_with('-') {
+        } else if word.leading_literal().starts_with('-') {
             Token::Flag(word)
         } else {
             Token::Argument(word)
         }
     }
 
-    fn parse_quoted_string(&mut self) -> Result<String> {
-        let quote_char = self.current_char.unwrap();
-        let quote_start = self.position - 1;
+    // A word runs until unquoted whitespace or an operator, and may join any mix of
+    // plain text, quoted strings and escapes: a"b c"'d' is the single word "ab cd"
+    fn parse_word(&mut self) -> Result<Word> {
+        let start = self.position;
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+
+        while let Some(c) = self.current_char {
+            if c.is_whitespace() || matches!(c, '|' | '>' | '<' | ';') {
+                break;
+            }
+            if !matches!(c, '\'' | '"' | '\\') {
+                literal.push(c);
+                self.advance();
+                continue;
+            }
+
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(std::mem::take(&mut literal)));
+            }
+            match c {
+                '\'' => segments.push(self.parse_single_quoted()?),
+                '"' => self.parse_double_quoted(&mut segments)?,
+                _ => {
+                    self.advance(); // Skip the backslash
+                    match self.current_char {
+                        // A backslash before the newline joins the lines
+                        Some('\n') => self.advance(),
+                        Some(escaped) => {
+                            segments.push(Segment::Escaped(escaped));
+                            self.advance();
+                        }
+                        // Nothing left to escape: keep the backslash itself
+                        None => literal.push('\\'),
+                    }
+                }
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Word {
+            segments,
+            start,
+            end: self.position,
+        })
+    }
+
+    // '...': everything up to the next single quote is literal, backslashes included
+    fn parse_single_quoted(&mut self) -> Result<Segment> {
+        let quote_start = self.position;
         self.advance(); // Skip opening quote
 
         let mut content = String::new();
-        let mut escaped = false;
-
         while let Some(c) = self.current_char {
-            if escaped {
-                content.push(c);
-                escaped = false;
-                self.advance();
-            } else if c == '\\' {
-                escaped = true;
-                self.advance();
-            } else if c == quote_char {
-                self.advance(); // Skip closing quote
-                return Ok(content);
-            } else {
-                content.push(c);
-                self.advance();
+            self.advance();
+            if c == '\'' {
+                return Ok(Segment::SingleQuoted(content));
             }
+            content.push(c);
         }
 
         // If we get here, we reached EOF without closing quote
         Err(ShellError::lexer("Unclosed quote", quote_start))
     }
 
-    fn parse_word(&mut self) -> Result<String> {
-        let mut word = String::new();
-        let mut escaped = false;
+    // "...": a backslash only escapes $ ` " \ and newline, and is kept before anything
+    // else. Escaped characters become segments of their own.
+    fn parse_double_quoted(&mut self, segments: &mut Vec<Segment>) -> Result<()> {
+        let quote_start = self.position;
+        self.advance(); // Skip opening quote
 
+        let first = segments.len();
+        let mut content = String::new();
         while let Some(c) = self.current_char {
-            if escaped {
-                word.push(c);
-                escaped = false;
-                self.advance();
-            } else if c == '\\' {
-                escaped = true;
-                self.advance();
-            } else if c.is_whitespace() || matches!(c, '|' | '>' | '<' | ';') {
-                break;
-            } else {
-                word.push(c);
-                self.advance();
+            self.advance();
+            match c {
+                '"' => {
+                    // "" still makes a word, so keep an empty segment for it
+                    if !content.is_empty() || segments.len() == first {
+                        segments.push(Segment::DoubleQuoted(content));
+                    }
+                    return Ok(());
+                }
+                '\\' => match self.current_char {
+                    Some('\n') => self.advance(),
+                    Some(escaped @ ('$' | '`' | '"' | '\\')) => {
+                        if !content.is_empty() {
+                            segments.push(Segment::DoubleQuoted(std::mem::take(&mut content)));
+                        }
+                        segments.push(Segment::Escaped(escaped));
+                        self.advance();
+                    }
+                    _ => content.push('\\'),
+                },
+                _ => content.push(c),
             }
         }
-        println!("{}", word);
 
-        Ok(word)
+        Err(ShellError::lexer("Unclosed quote", quote_start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Segment::*;
+
+    fn tokenize(line: &str) -> Vec<TokenWithPos> {
+        Lexer::new(line.to_string())
+            .tokenize()
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // The one word after "echo", which must be an argument
+    fn argument(line: &str) -> Word {
+        match tokenize(&format!("echo {}", line)).remove(1).token {
+            Token::Argument(word) => word,
+            other => panic!("not an argument: {:?}", other),
+        }
+    }
+
+    fn literal(s: &str) -> Segment {
+        Literal(s.to_string())
+    }
+
+    #[test]
+    fn quoting_makes_segments() {
+        let word = argument("'a b'");
+        assert_eq!(word.segments, [SingleQuoted("a b".to_string())]);
+        assert_eq!(word.text(), "a b");
+
+        let word = argument(r#""a\"b""#);
+        assert_eq!(
+            word.segments,
+            [
+                DoubleQuoted("a".to_string()),
+                Escaped('"'),
+                DoubleQuoted("b".to_string())
+            ]
+        );
+        assert_eq!(word.text(), "a\"b");
+
+        let word = argument(r"a\ b");
+        assert_eq!(word.segments, [literal("a"), Escaped(' '), literal("b")]);
+        assert_eq!(word.text(), "a b");
+
+        // Only some escapes mean anything inside double quotes, and none inside single
+        assert_eq!(argument(r#""a\nb""#).text(), r"a\nb");
+        assert_eq!(argument(r"'a\b'").text(), r"a\b");
+        assert_eq!(argument(r#""""#).segments, [DoubleQuoted(String::new())]);
+    }
+
+    #[test]
+    fn segments_join_into_one_word() {
+        let word = argument(r#"a"b c"'d'\e"#);
+        assert_eq!(
+            word.segments,
+            [
+                literal("a"),
+                DoubleQuoted("b c".to_string()),
+                SingleQuoted("d".to_string()),
+                Escaped('e')
+            ]
+        );
+        assert_eq!(word.text(), "ab cde");
+        assert_eq!((word.start, word.end), (5, 16));
+
+        // Only an unquoted dash makes a flag
+        let tokens = tokenize("ls -l '-a' \\-b");
+        assert!(matches!(tokens[1].token, Token::Flag(_)));
+        assert!(matches!(tokens[2].token, Token::Argument(_)));
+        assert!(matches!(tokens[3].token, Token::Argument(_)));
+    }
+
+    #[test]
+    fn unterminated_quotes() {
+        for (line, position) in [("echo 'a b", 5), ("echo x\"a", 6), ("é \"a\\\"", 2)] {
+            match Lexer::new(line.to_string()).tokenize() {
+                Err(e) => assert_eq!(
+                    e.to_string(),
+                    format!("Lexer error at position {}: Unclosed quote", position)
+                ),
+                Ok(tokens) => panic!("{:?} was accepted: {:?}", line, tokens),
+            }
+        }
     }
 }
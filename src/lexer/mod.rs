@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod lexer;
 
 pub use lexer::Lexer;
\ No newline at end of file
@@ -7,8 +7,8 @@ mod ast;
 
 use crate::executor::executor::Executor;
 use crate::lexer::Lexer;
-use crate::types::tokens::Token;
 use crate::utils::error::ShellError;
+use crate::utils::workdir;
 use crate::parser::parser::Parser;
 use std::io::{self, Write};
 
@@ -20,7 +20,14 @@ fn main() {
 }
 
 fn run_shell() -> Result<(), ShellError> {
-    let mut executor = Executor::new();
+    let executor = Executor::new();
+    workdir::sync_pwd();
+
+    // Whatever is still buffered has to reach the terminal before the process ends
+    executor.on_exit(|| {
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+    });
 
     loop {
         print!("$ ");
@@ -29,9 +36,9 @@ fn run_shell() -> Result<(), ShellError> {
         let mut input = String::new();
         match io::stdin().read_line(&mut input) {
             Ok(0) => {
-                // Ctrl+D pressed (EOF)
+                // Ctrl+D pressed (EOF): leave as if "exit" had been typed
                 println!();
-                break;
+                executor.exit(executor.last_status());
             }
             Ok(_) => {
                 // Process input
@@ -39,27 +46,26 @@ fn run_shell() -> Result<(), ShellError> {
                     continue;
                 }
 
-                if input.trim() == "exit" {
-                    println!("Goodbye!");
-                    break;
-                }
-
-                // Tokenize and execute
-                let mut lexer = Lexer::new(input.to_string());
-                let tokens_with_pos = lexer.tokenize()?;
-                println!("{:?}", tokens_with_pos);
-
-                // let tokens: Vec<Token> = tokens_with_pos.into_iter().map(|twp| twp.token).collect();
+                // Tokenize and parse. A syntax error only costs this line, not the shell.
+                let ast = Lexer::new(input.to_string())
+                    .tokenize()
+                    .and_then(|tokens_with_pos| Parser::new(tokens_with_pos).parse());
+                let ast = match ast {
+                    Ok(ast) => ast,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        executor.set_last_status(e.status_code());
+                        continue;
+                    }
+                };
 
-                // println!("{:?}", tokens);
-
-                // Parse
-                let mut parser = Parser::new(tokens_with_pos);
-                let ast = parser.parse()?;
-
-                println!("AST: {:?}", ast);
                 match executor.execute_ast(&ast) {
                     Ok(_) =>{} 
+                    // The command already reported what went wrong
+                    Err(ShellError::Status(_)) => {}
+                    Err(ShellError::Exit(code)) => {
+                        executor.exit(code.unwrap_or_else(|| executor.last_status()))
+                    }
                     Err(e) => eprintln!("Error: {}", e),
                 }
             }
@@ -68,6 +74,4 @@ fn run_shell() -> Result<(), ShellError> {
             }
         }
     }
-
-    Ok(())
 }
@@ -1,5 +1,6 @@
 use crate::types::tokens::{Token, TokenWithPos};
 use crate::ast::nodes::AstNode;
+use crate::types::word::Word;
 use crate::utils::error::{ShellError, Result};
 
 pub struct Parser {
@@ -28,14 +29,6 @@ impl Parser {
         }
     }
 
-    fn peek(&self) -> Option<&Token> {
-        if self.position < self.tokens.len() {
-            Some(&self.tokens[self.position].token)
-        } else {
-            None
-        }
-    }
-
     pub fn parse(&mut self) -> Result<AstNode> {
         self.parse_line()
     }
@@ -98,7 +91,7 @@ impl Parser {
         Ok(command)
     }
 
-    fn parse_redirect_file(&mut self) -> Result<String> {
+    fn parse_redirect_file(&mut self) -> Result<Word> {
         if let Some(Token::Argument(file)) = &self.current_token {
             let file_clone = file.clone();
             self.advance();
@@ -111,36 +104,64 @@ impl Parser {
     // simple_command : command (flag | argument)*
     fn parse_simple_command(&mut self) -> Result<AstNode> {
         // Parse command name (must be first token)
-        let name = if let Some(Token::Command(cmd)) = &self.current_token {
-            let cmd_clone = cmd.clone();
+        let mut words = if let Some(Token::Command(name)) = &self.current_token {
+            let name = name.clone();
             self.advance();
-            cmd_clone
+            vec![name]
         } else {
             return Err(ShellError::parser("Expected command name"));
         };
 
-        let mut flags = Vec::new();
-        let mut args = Vec::new();
-
-        // Parse flags and arguments until operator or end
+        // Flags and arguments stay interleaved exactly as typed, so "find . -name x"
+        // reaches find in that order
         while let Some(token) = &self.current_token {
             match token {
-                Token::Flag(flag) => {
-                    flags.push(flag.clone());
-                    self.advance();
-                }
-                Token::Argument(arg) => {
-                    args.push(arg.clone());
+                Token::Flag(word) | Token::Argument(word) => {
+                    words.push(word.clone());
                     self.advance();
                 }
                 _ => break, // Stop at operators: |, ;, >, <, >>
             }
         }
 
-        Ok(AstNode::Command {
-            name,
-            flags,
-            args,
-        })
+        Ok(AstNode::Command { words })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer::Lexer;
+
+    fn parse(line: &str) -> AstNode {
+        let tokens = Lexer::new(line.to_string())
+            .tokenize()
+            .unwrap_or_else(|e| panic!("{}", e));
+        Parser::new(tokens)
+            .parse()
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn words(node: &AstNode) -> Vec<String> {
+        match node {
+            AstNode::Command { words } => words.iter().map(|word| word.text()).collect(),
+            other => panic!("not a command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn words_stay_in_the_order_typed() {
+        assert_eq!(words(&parse("echo a -n b")), ["echo", "a", "-n", "b"]);
+        assert_eq!(
+            words(&parse("ls -l dir --all '-x' other")),
+            ["ls", "-l", "dir", "--all", "-x", "other"]
+        );
+        match parse("grep -v a b > out") {
+            AstNode::Redirect { command, file, .. } => {
+                assert_eq!(words(&command), ["grep", "-v", "a", "b"]);
+                assert_eq!(file.text(), "out");
+            }
+            other => panic!("not a redirect: {:?}", other),
+        }
+    }
+}
@@ -1 +1,2 @@
-pub mod tokens;
\ No newline at end of file
+pub mod tokens;
+pub mod word;
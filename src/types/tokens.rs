@@ -1,9 +1,11 @@
+use crate::types::word::Word;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     // Commands and arguments
-    Command(String),  // First word in a simple command: "ls", "echo"
-    Flag(String),     // Words starting with '-': "-l", "-a", "--all"
-    Argument(String), // Other words: "file.txt", "/home/user"
+    Command(Word),  // First word in a simple command: "ls", "echo"
+    Flag(Word),     // Words starting with an unquoted '-': "-l", "-a", "--all"
+    Argument(Word), // Other words: "file.txt", "/home/user", "-n" in quotes
 
     // Operators
     Pipe,           // "|"
@@ -11,10 +13,10 @@ pub enum Token {
     RedirectAppend, // ">>"
     RedirectIn,     // "<"
     Semicolon,      // ";"
-    EOF,
 }
 
 #[derive(Debug)]
+#[allow(dead_code)] // Positions are kept for error reporting
 pub struct TokenWithPos {
     pub token: Token,
     pub start: usize,
@@ -22,6 +24,7 @@ pub struct TokenWithPos {
 }
 
 // Helper methods
+#[allow(dead_code)]
 impl Token {
     pub fn is_operator(&self) -> bool {
         matches!(
@@ -41,9 +44,9 @@ impl Token {
         )
     }
 
-    pub fn as_str(&self) -> Option<&str> {
+    pub fn as_word(&self) -> Option<&Word> {
         match self {
-            Token::Command(s) | Token::Flag(s) | Token::Argument(s) => Some(s),
+            Token::Command(w) | Token::Flag(w) | Token::Argument(w) => Some(w),
             _ => None,
         }
     }
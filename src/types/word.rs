@@ -0,0 +1,41 @@
+// A shell word as the lexer read it: the pieces it was written in, each with its
+// quoting. "a"b'c'\d is one word made of four segments. Keeping the quoting lets
+// later stages treat quoted text literally, so "~" or "-n" in quotes stays as typed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Literal(String),      // unquoted text
+    SingleQuoted(String), // '...', exactly as written
+    DoubleQuoted(String), // "...", with its \$ \` \" \\ escapes split out as Escaped
+    Escaped(char),        // a backslash-escaped character, inside double quotes or not
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub segments: Vec<Segment>,
+    pub start: usize, // character offsets in the input line, end exclusive
+    pub end: usize,
+}
+
+impl Word {
+    // The word after quote removal, as a command receives it
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(s) | Segment::SingleQuoted(s) | Segment::DoubleQuoted(s) => {
+                    text.push_str(s)
+                }
+                Segment::Escaped(c) => text.push(*c),
+            }
+        }
+        text
+    }
+
+    // The unquoted text the word starts with, empty if it starts with a quote
+    pub fn leading_literal(&self) -> &str {
+        match self.segments.first() {
+            Some(Segment::Literal(s)) => s,
+            _ => "",
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+
+// Built-in colours used when LS_COLORS is unset, matching GNU ls plus a
+// distinct colour for orphaned (broken) symlinks
+const DEFAULTS: &str = "di=01;34:ln=01;36:pi=33:so=01;35:do=01;35:bd=01;33:cd=01;33:\
+or=40;31;01:ex=01;32:su=37;41:sg=30;43:st=37;44:ow=34;42:tw=30;42";
+
+// Parsed LS_COLORS: two-letter file type keys ("di", "ln", ...) and "*.ext" suffix patterns
+pub struct LsColors {
+    types: HashMap<String, String>,
+    suffixes: Vec<(String, String)>,
+}
+
+impl LsColors {
+    pub fn from_env() -> Self {
+        Self::parse(&std::env::var("LS_COLORS").unwrap_or_default())
+    }
+
+    // Entries in `spec` override the built-in defaults; malformed entries are ignored
+    pub fn parse(spec: &str) -> Self {
+        let mut colors = LsColors {
+            types: HashMap::new(),
+            suffixes: Vec::new(),
+        };
+
+        for entry in DEFAULTS.split(':').chain(spec.split(':')) {
+            let Some((key, value)) = entry.split_once('=') else {
+                continue;
+            };
+
+            if let Some(suffix) = key.strip_prefix('*') {
+                // Later patterns win, so keep only the newest definition of a suffix
+                colors.suffixes.retain(|(existing, _)| existing != suffix);
+                colors
+                    .suffixes
+                    .push((suffix.to_string(), value.to_string()));
+            } else if key.len() == 2 {
+                colors.types.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        colors
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.types
+            .get(key)
+            .map(String::as_str)
+            .filter(|code| !code.is_empty())
+    }
+
+    // The SGR code for a file, given its lstat metadata. Symlinks are looked through to
+    // decide whether they are broken and, with "ln=target", to borrow the target's colour.
+    pub fn style(&self, name: &str, path: &Path, metadata: &Metadata) -> Option<&str> {
+        let file_type = metadata.file_type();
+        let mode = metadata.mode();
+
+        if file_type.is_symlink() {
+            return match fs::metadata(path) {
+                Err(_) => self.get("or").or_else(|| self.get("ln")),
+                Ok(target) if self.get("ln") == Some("target") => self.style(name, path, &target),
+                Ok(_) => self.get("ln"),
+            };
+        }
+
+        if file_type.is_dir() {
+            let sticky = mode & 0o1000 != 0;
+            let other_writable = mode & 0o002 != 0;
+            return match (sticky, other_writable) {
+                (true, true) => self.get("tw"),
+                (false, true) => self.get("ow"),
+                (true, false) => self.get("st"),
+                (false, false) => None,
+            }
+            .or_else(|| self.get("di"));
+        }
+
+        if file_type.is_fifo() {
+            return self.get("pi");
+        }
+        if file_type.is_socket() {
+            return self.get("so");
+        }
+        if file_type.is_block_device() {
+            return self.get("bd");
+        }
+        if file_type.is_char_device() {
+            return self.get("cd");
+        }
+
+        let special = if mode & 0o4000 != 0 {
+            self.get("su")
+        } else if mode & 0o2000 != 0 {
+            self.get("sg")
+        } else if mode & 0o111 != 0 {
+            self.get("ex")
+        } else {
+            None
+        };
+
+        special
+            .or_else(|| {
+                self.suffixes
+                    .iter()
+                    .rev()
+                    .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+                    .map(|(_, code)| code.as_str())
+            })
+            .or_else(|| self.get("fi"))
+    }
+
+    // Colour for the target of a symlink whose target does not exist
+    pub fn missing(&self) -> Option<&str> {
+        self.get("mi")
+    }
+}
+
+// Wrap text in an SGR sequence and reset afterwards
+pub fn paint(code: Option<&str>, text: &str) -> String {
+    match code {
+        Some(code) => format!("\x1b[{}m{}\x1b[0m", code, text),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+    use std::fs::Permissions;
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    // The colour of a file in the scratch tree
+    fn style_of<'a>(colors: &'a LsColors, dir: &Scratch, name: &str) -> Option<&'a str> {
+        let path = dir.path(name);
+        let metadata = fs::symlink_metadata(&path).unwrap();
+        colors.style(name, &path, &metadata)
+    }
+
+    fn chmod(dir: &Scratch, name: &str, mode: u32) {
+        fs::set_permissions(dir.path(name), Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn defaults_by_file_type() {
+        let dir = Scratch::new("colors-defaults", &["d/", "t/", "plain", "run"]);
+        chmod(&dir, "t", 0o1777);
+        chmod(&dir, "plain", 0o644);
+        chmod(&dir, "run", 0o755);
+        symlink("plain", dir.path("link")).unwrap();
+        symlink("missing", dir.path("broken")).unwrap();
+
+        let colors = LsColors::parse("");
+        assert_eq!(style_of(&colors, &dir, "d"), Some("01;34"));
+        assert_eq!(style_of(&colors, &dir, "t"), Some("30;42"));
+        assert_eq!(style_of(&colors, &dir, "plain"), None);
+        assert_eq!(style_of(&colors, &dir, "run"), Some("01;32"));
+        assert_eq!(style_of(&colors, &dir, "link"), Some("01;36"));
+        assert_eq!(style_of(&colors, &dir, "broken"), Some("40;31;01"));
+    }
+
+    #[test]
+    fn ls_colors_overrides() {
+        let dir = Scratch::new("colors-overrides", &["d/", "a.tar", "b.TAR", "run.tar"]);
+        chmod(&dir, "run.tar", 0o755);
+        symlink("d", dir.path("link")).unwrap();
+        symlink("missing", dir.path("broken")).unwrap();
+
+        // The last definition of a suffix wins, and matching is case-sensitive
+        let colors = LsColors::parse("di=01;33:*.tar=31:*.tar=32:ln=target:mi=05");
+        assert_eq!(style_of(&colors, &dir, "d"), Some("01;33"));
+        assert_eq!(style_of(&colors, &dir, "a.tar"), Some("32"));
+        assert_eq!(style_of(&colors, &dir, "b.TAR"), None);
+        // Being executable outranks the suffix
+        assert_eq!(style_of(&colors, &dir, "run.tar"), Some("01;32"));
+        // ln=target borrows the colour of what the link points to
+        assert_eq!(style_of(&colors, &dir, "link"), Some("01;33"));
+        assert_eq!(style_of(&colors, &dir, "broken"), Some("40;31;01"));
+        assert_eq!(colors.missing(), Some("05"));
+
+        // An empty code turns a colour off, and malformed entries are skipped
+        let colors = LsColors::parse("ex=:di:junk:or=");
+        assert_eq!(style_of(&colors, &dir, "run.tar"), None);
+        assert_eq!(style_of(&colors, &dir, "d"), Some("01;34"));
+        assert_eq!(style_of(&colors, &dir, "broken"), Some("01;36"));
+    }
+
+    #[test]
+    fn painting() {
+        assert_eq!(paint(Some("01;34"), "dir"), "\x1b[01;34mdir\x1b[0m");
+        assert_eq!(paint(None, "file"), "file");
+    }
+}
@@ -0,0 +1,379 @@
+use crate::utils::error;
+use crate::utils::overwrite::{Mode, OverwritePolicy};
+use crate::utils::sys;
+use std::fs::{self, DirBuilder, Metadata, OpenOptions, Permissions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+// The copy engine behind cp, and behind mv when a rename crosses filesystems
+
+#[derive(Default)]
+pub struct CopyOptions {
+    pub recursive: bool,   // descend into directories
+    pub preserve: bool,    // keep mode, ownership and timestamps
+    pub dereference: bool, // copy what symlinks point to instead of the links
+    pub overwrite: OverwritePolicy,
+}
+
+struct Copier<'a> {
+    options: &'a CopyOptions,
+    program: &'a str,
+    stdin: &'a mut dyn Read,
+    stderr: &'a mut dyn Write,
+    umask: u32,
+    // The directories being copied, by device and inode, so that following
+    // symlinks can't copy a directory into itself forever
+    ancestors: Vec<(u64, u64)>,
+    ok: bool,
+}
+
+// Copy src to dest, recreating whole trees when recursive. A failure on one entry is
+// reported on stderr and the rest are still copied; the result says whether everything
+// made it. Existing files are only replaced as the overwrite policy allows, which may
+// mean asking on stdin. Only errors talking to stdin and stderr themselves are returned.
+pub fn copy(
+    src: &Path,
+    dest: &Path,
+    options: &CopyOptions,
+    program: &str,
+    stdin: &mut dyn Read,
+    stderr: &mut dyn Write,
+) -> io::Result<bool> {
+    let mut copier = Copier {
+        options,
+        program,
+        stdin,
+        stderr,
+        umask: sys::current_umask(),
+        ancestors: Vec::new(),
+        ok: true,
+    };
+    copier.copy_entry(src, dest)?;
+    Ok(copier.ok)
+}
+
+// Whether target is dir itself or somewhere beneath it, after resolving symlinks.
+// target doesn't have to exist yet.
+pub fn is_inside(dir: &Path, target: &Path) -> bool {
+    let Ok(dir) = fs::canonicalize(dir) else {
+        return false;
+    };
+    resolve(target).starts_with(dir)
+}
+
+// Canonicalize as much of the path as exists, keeping the missing tail as written
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(resolved) = fs::canonicalize(path) {
+        return resolved;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => resolve(parent).join(name),
+        (_, Some(name)) => match std::env::current_dir() {
+            Ok(cwd) => cwd.join(name),
+            Err(_) => path.to_path_buf(),
+        },
+        _ => path.to_path_buf(),
+    }
+}
+
+impl Copier<'_> {
+    fn report(&mut self, message: String) -> io::Result<()> {
+        self.ok = false;
+        writeln!(self.stderr, "{}: {}", self.program, message)
+    }
+
+    fn copy_entry(&mut self, src: &Path, dest: &Path) -> io::Result<()> {
+        let metadata = if self.options.dereference {
+            fs::metadata(src)
+        } else {
+            fs::symlink_metadata(src)
+        };
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                return self.report(format!(
+                    "cannot stat '{}': {}",
+                    src.display(),
+                    error::describe(&e)
+                ));
+            }
+        };
+
+        let file_type = metadata.file_type();
+        // Only a followed symlink can lead back to a directory above
+        if file_type.is_dir() && self.ancestors.contains(&(metadata.dev(), metadata.ino())) {
+            return self.report(format!(
+                "cannot copy cyclic symbolic link '{}'",
+                src.display()
+            ));
+        }
+        if !file_type.is_dir() && !self.make_room(src, dest, &metadata)? {
+            return Ok(());
+        }
+
+        if file_type.is_dir() {
+            self.copy_dir(src, dest, &metadata)
+        } else if file_type.is_symlink() {
+            self.copy_symlink(src, dest, &metadata)
+        } else if file_type.is_fifo() && self.options.recursive {
+            // Reading a pipe would block; recreate it instead
+            self.copy_fifo(dest, &metadata)
+        } else if file_type.is_file() || !self.options.recursive {
+            // Without -r special files are copied by reading them, like /dev/null
+            self.copy_file(src, dest, &metadata)
+        } else {
+            self.report(format!("cannot copy special file '{}'", src.display()))
+        }
+    }
+
+    // Apply the overwrite policy to whatever already exists at dest, backing it up if
+    // asked to. Returns false when dest must be left as it is.
+    fn make_room(&mut self, src: &Path, dest: &Path, metadata: &Metadata) -> io::Result<bool> {
+        let Ok(existing) = fs::symlink_metadata(dest) else {
+            return Ok(true);
+        };
+        if existing.is_dir() {
+            self.report(format!(
+                "cannot overwrite directory '{}' with non-directory '{}'",
+                dest.display(),
+                src.display()
+            ))?;
+            return Ok(false);
+        }
+
+        let policy = &self.options.overwrite;
+        if !policy.allows(metadata, dest, self.program, self.stdin, self.stderr)? {
+            return Ok(false);
+        }
+        if let Err(e) = policy.back_up(dest) {
+            self.report(format!(
+                "cannot backup '{}': {}",
+                dest.display(),
+                error::describe(&e)
+            ))?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn copy_file(&mut self, src: &Path, dest: &Path, metadata: &Metadata) -> io::Result<()> {
+        let mut input = match fs::File::open(src) {
+            Ok(file) => file,
+            Err(e) => {
+                return self.report(format!(
+                    "cannot open '{}' for reading: {}",
+                    src.display(),
+                    error::describe(&e)
+                ));
+            }
+        };
+
+        // A new file gets the source's permission bits less the umask; an existing one
+        // is truncated and keeps its own
+        let mut open = OpenOptions::new();
+        open.write(true)
+            .create(true)
+            .truncate(true)
+            .mode(metadata.mode() & 0o777);
+        let mut output = open.open(dest);
+        // -f: a destination that can't be written is removed and created afresh
+        if output.is_err()
+            && self.options.overwrite.mode == Mode::Force
+            && fs::remove_file(dest).is_ok()
+        {
+            output = open.open(dest);
+        }
+        let mut output = match output {
+            Ok(file) => file,
+            Err(e) => {
+                return self.report(format!(
+                    "cannot create regular file '{}': {}",
+                    dest.display(),
+                    error::describe(&e)
+                ));
+            }
+        };
+
+        if let Err(e) = io::copy(&mut input, &mut output) {
+            return self.report(format!(
+                "error copying '{}' to '{}': {}",
+                src.display(),
+                dest.display(),
+                error::describe(&e)
+            ));
+        }
+        drop(output);
+
+        if self.options.preserve {
+            self.preserve(dest, metadata)?;
+        }
+        Ok(())
+    }
+
+    fn copy_dir(&mut self, src: &Path, dest: &Path, metadata: &Metadata) -> io::Result<()> {
+        if !self.options.recursive {
+            return self.report(format!(
+                "-r not specified; omitting directory '{}'",
+                src.display()
+            ));
+        }
+
+        let created = match fs::symlink_metadata(dest) {
+            Ok(existing) if existing.is_dir() => false,
+            Ok(_) => {
+                return self.report(format!(
+                    "cannot overwrite non-directory '{}' with directory '{}'",
+                    dest.display(),
+                    src.display()
+                ));
+            }
+            Err(_) => {
+                // Keep the directory writable until its contents are in place
+                let result = DirBuilder::new()
+                    .mode((metadata.mode() & 0o777) | 0o700)
+                    .create(dest);
+                if let Err(e) = result {
+                    return self.report(format!(
+                        "cannot create directory '{}': {}",
+                        dest.display(),
+                        error::describe(&e)
+                    ));
+                }
+                true
+            }
+        };
+
+        self.ancestors.push((metadata.dev(), metadata.ino()));
+        match fs::read_dir(src) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => {
+                            let name = entry.file_name();
+                            self.copy_entry(&src.join(&name), &dest.join(&name))?;
+                        }
+                        Err(e) => {
+                            self.report(format!(
+                                "cannot access '{}': {}",
+                                src.display(),
+                                error::describe(&e)
+                            ))?;
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                self.report(format!(
+                    "cannot access '{}': {}",
+                    src.display(),
+                    error::describe(&e)
+                ))?;
+            }
+        }
+        self.ancestors.pop();
+
+        // Only now can the final permissions be applied, since they may forbid writing
+        if self.options.preserve {
+            self.preserve(dest, metadata)?;
+        } else if created && metadata.mode() & 0o700 != 0o700 {
+            let mode = metadata.mode() & 0o777 & !self.umask;
+            if let Err(e) = fs::set_permissions(dest, Permissions::from_mode(mode)) {
+                self.report(format!(
+                    "setting permissions for '{}': {}",
+                    dest.display(),
+                    error::describe(&e)
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_symlink(&mut self, src: &Path, dest: &Path, metadata: &Metadata) -> io::Result<()> {
+        let target = match fs::read_link(src) {
+            Ok(target) => target,
+            Err(e) => {
+                return self.report(format!(
+                    "cannot read symbolic link '{}': {}",
+                    src.display(),
+                    error::describe(&e)
+                ));
+            }
+        };
+
+        // A link can't be created over an existing file, so replace it
+        if let Ok(existing) = fs::symlink_metadata(dest)
+            && !existing.is_dir()
+        {
+            let _ = fs::remove_file(dest);
+        }
+        if let Err(e) = std::os::unix::fs::symlink(&target, dest) {
+            return self.report(format!(
+                "cannot create symbolic link '{}': {}",
+                dest.display(),
+                error::describe(&e)
+            ));
+        }
+
+        if self.options.preserve {
+            self.preserve(dest, metadata)?;
+        }
+        Ok(())
+    }
+
+    fn copy_fifo(&mut self, dest: &Path, metadata: &Metadata) -> io::Result<()> {
+        if let Err(e) = sys::make_fifo(dest, metadata.mode() & 0o777) {
+            return self.report(format!(
+                "cannot create fifo '{}': {}",
+                dest.display(),
+                error::describe(&e)
+            ));
+        }
+        if self.options.preserve {
+            self.preserve(dest, metadata)?;
+        }
+        Ok(())
+    }
+
+    // Copy ownership, mode and timestamps from metadata onto dest. Symlinks are
+    // handled themselves rather than what they point to.
+    fn preserve(&mut self, dest: &Path, metadata: &Metadata) -> io::Result<()> {
+        let is_symlink = metadata.file_type().is_symlink();
+
+        // Only root can give files away; like cp, quietly keep our own ownership then.
+        // chown clears set-id bits, so it has to come before the mode.
+        let owned = if is_symlink {
+            std::os::unix::fs::lchown(dest, Some(metadata.uid()), Some(metadata.gid()))
+        } else {
+            std::os::unix::fs::chown(dest, Some(metadata.uid()), Some(metadata.gid()))
+        }
+        .is_ok();
+
+        if !is_symlink {
+            // Set-id bits would be a privilege escalation on a file we couldn't chown
+            let mut mode = metadata.mode() & 0o7777;
+            if !owned {
+                mode &= !0o6000;
+            }
+            if let Err(e) = fs::set_permissions(dest, Permissions::from_mode(mode)) {
+                self.report(format!(
+                    "preserving permissions for '{}': {}",
+                    dest.display(),
+                    error::describe(&e)
+                ))?;
+            }
+        }
+
+        let accessed = (metadata.atime(), metadata.atime_nsec());
+        let modified = (metadata.mtime(), metadata.mtime_nsec());
+        if let Err(e) = sys::set_times(dest, Some(accessed), Some(modified), !is_symlink) {
+            self.report(format!(
+                "preserving times for '{}': {}",
+                dest.display(),
+                error::describe(&e)
+            ))?;
+        }
+        Ok(())
+    }
+}
@@ -9,6 +9,8 @@ pub enum ShellError {
     Execution(String),
     CommandNotFound(String),
     InvalidArguments(String),
+    Status(i32),           // Command already reported its errors, carries the exit status
+    Exit(Option<i32>),     // The exit builtin ran; None means the last command's status
 }
 
 // Implement Display for pretty printing
@@ -21,6 +23,8 @@ impl fmt::Display for ShellError {
             ShellError::Execution(msg) => write!(f, "Execution error: {}", msg),
             ShellError::CommandNotFound(cmd) => write!(f, "Command not found: {}", cmd),
             ShellError::InvalidArguments(msg) => write!(f, "Invalid arguments: {}", msg),
+            ShellError::Status(code) => write!(f, "Exited with status {}", code),
+            ShellError::Exit(_) => write!(f, "exit"),
         }
     }
 }
@@ -32,6 +36,15 @@ impl From<io::Error> for ShellError {
     }
 }
 
+// Strip the trailing " (os error N)" so builtins can print coreutils-style messages
+pub fn describe(error: &io::Error) -> String {
+    let message = error.to_string();
+    match message.find(" (os error ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
 // Our Result type alias
 pub type Result<T> = std::result::Result<T, ShellError>;
 
@@ -56,4 +69,21 @@ impl ShellError {
     pub fn invalid_arguments(message: &str) -> Self {
         ShellError::InvalidArguments(message.to_string())
     }
+    
+    pub fn status(code: i32) -> Self {
+        ShellError::Status(code)
+    }
+
+    // The exit status a failed command leaves behind, as $? would show it
+    pub fn status_code(&self) -> i32 {
+        match self {
+            ShellError::Status(code) => *code,
+            ShellError::Exit(code) => code.unwrap_or(0),
+            ShellError::CommandNotFound(_) => 127,
+            ShellError::InvalidArguments(_) => 2,
+            // Syntax errors, as other shells report them
+            ShellError::Lexer(..) | ShellError::Parser(_) => 2,
+            _ => 1,
+        }
+    }
 }
\ No newline at end of file
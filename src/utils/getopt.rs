@@ -0,0 +1,449 @@
+use crate::utils::error::{Result, ShellError};
+
+// Option parsing for the builtins, after getopt_long(3). Each command declares its
+// short options as a string ("pvm:" — a colon means the letter takes an argument)
+// and its long options as a table, then pulls options out one at a time:
+//
+//     let mut getopt = Getopt::new("mkdir", args, "pvm:", MKDIR_LONG_OPTIONS);
+//     while let Some(option) = getopt.next_option()? {
+//         match option.name { "p" => ..., "m" => ..., _ => {} }
+//     }
+//     let operands = getopt.operands();
+//
+// Short options may be combined (-la) and take their argument attached or as the
+// next word (-m755, -m 755). Long options may be abbreviated to any unambiguous
+// prefix and take theirs as --mode=755 or --mode 755. Options and operands may be
+// mixed; "--" ends the options, and a lone "-" is an operand. A short option string
+// starting with '+' stops at the first operand instead, as POSIX utilities do.
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HasArg {
+    No,
+    Required,
+    Optional, // only as --name=value, never from the next word
+}
+
+pub struct LongOption {
+    pub name: &'static str,
+    pub has_arg: HasArg,
+    pub short: Option<char>, // reported as this short option when set
+}
+
+pub const fn long(name: &'static str, has_arg: HasArg, short: Option<char>) -> LongOption {
+    LongOption {
+        name,
+        has_arg,
+        short,
+    }
+}
+
+// One parsed option. name is the short letter ("m") for options that have one, and
+// the long name ("color") for long-only options.
+pub struct Opt<'a> {
+    pub name: &'static str,
+    pub value: Option<&'a str>,
+}
+
+pub struct Getopt<'a> {
+    program: &'static str,
+    args: &'a [String],
+    shortopts: Vec<&'static str>,
+    longopts: Vec<&'static [LongOption]>,
+    in_order: bool,
+    index: usize,
+    offset: usize, // position inside a word of combined short options, 0 between words
+    operands: Vec<String>,
+}
+
+impl<'a> Getopt<'a> {
+    pub fn new(
+        program: &'static str,
+        args: &'a [String],
+        shortopts: &'static str,
+        longopts: &'static [LongOption],
+    ) -> Self {
+        let in_order = shortopts.starts_with('+');
+        Getopt {
+            program,
+            args,
+            shortopts: vec![shortopts.trim_start_matches('+')],
+            longopts: vec![longopts],
+            in_order,
+            index: 0,
+            offset: 0,
+            operands: Vec::new(),
+        }
+    }
+
+    // Accept another set of options as well, such as the ones cp and mv share
+    pub fn with(mut self, shortopts: &'static str, longopts: &'static [LongOption]) -> Self {
+        self.shortopts.push(shortopts);
+        self.longopts.push(longopts);
+        self
+    }
+
+    // The next option, or None once the options are exhausted. Usage errors come
+    // back as InvalidArguments with a usage line built from the declarations.
+    pub fn next_option(&mut self) -> Result<Option<Opt<'a>>> {
+        let args = self.args;
+        loop {
+            if self.offset > 0 {
+                return self.short_option().map(Some);
+            }
+
+            let Some(arg) = args.get(self.index) else {
+                return Ok(None);
+            };
+            if arg == "--" {
+                self.index += 1;
+                self.finish();
+                return Ok(None);
+            }
+            if arg.starts_with("--") {
+                return self.long_option().map(Some);
+            }
+            if arg.starts_with('-') && arg.len() > 1 {
+                self.offset = 1;
+                continue;
+            }
+
+            if self.in_order {
+                self.finish();
+                return Ok(None);
+            }
+            self.operands.push(arg.clone());
+            self.index += 1;
+        }
+    }
+
+    // The operands, in order. Call once next_option has returned None.
+    pub fn operands(mut self) -> Vec<String> {
+        self.finish();
+        self.operands
+    }
+
+    // Everything from the current word on is an operand
+    fn finish(&mut self) {
+        let rest = self.args.get(self.index..).unwrap_or_default();
+        self.operands.extend(rest.iter().cloned());
+        self.index = self.args.len();
+        self.offset = 0;
+    }
+
+    fn short_option(&mut self) -> Result<Opt<'a>> {
+        let args = self.args;
+        let word = &args[self.index];
+        let letter = word[self.offset..].chars().next().unwrap_or('-');
+        self.offset += letter.len_utf8();
+        let at_end = self.offset >= word.len();
+
+        let Some((name, takes_arg)) = self.find_short(letter) else {
+            return Err(self.usage_error(&format!("invalid option -- '{}'", letter)));
+        };
+
+        if !takes_arg {
+            if at_end {
+                self.index += 1;
+                self.offset = 0;
+            }
+            return Ok(Opt { name, value: None });
+        }
+
+        // The argument is the rest of this word, or else the whole next one
+        let value = if at_end {
+            let value = args.get(self.index + 1).ok_or_else(|| {
+                self.usage_error(&format!("option requires an argument -- '{}'", letter))
+            })?;
+            self.index += 2;
+            value.as_str()
+        } else {
+            let value = &word[self.offset..];
+            self.index += 1;
+            value
+        };
+        self.offset = 0;
+        Ok(Opt {
+            name,
+            value: Some(value),
+        })
+    }
+
+    fn long_option(&mut self) -> Result<Opt<'a>> {
+        let args = self.args;
+        let word = &args[self.index];
+        self.index += 1;
+
+        let body = &word[2..];
+        let (given, attached) = match body.split_once('=') {
+            Some((given, value)) => (given, Some(value)),
+            None => (body, None),
+        };
+        let option = self.find_long(given, word)?;
+
+        let value = match option.has_arg {
+            HasArg::No => {
+                if attached.is_some() {
+                    return Err(self.usage_error(&format!(
+                        "option '--{}' doesn't allow an argument",
+                        option.name
+                    )));
+                }
+                None
+            }
+            HasArg::Optional => attached,
+            HasArg::Required => match attached {
+                Some(value) => Some(value),
+                None => {
+                    let value = args.get(self.index).ok_or_else(|| {
+                        self.usage_error(&format!(
+                            "option '--{}' requires an argument",
+                            option.name
+                        ))
+                    })?;
+                    self.index += 1;
+                    Some(value.as_str())
+                }
+            },
+        };
+
+        let name = option
+            .short
+            .and_then(|letter| self.find_short(letter))
+            .map_or(option.name, |(name, _)| name);
+        Ok(Opt { name, value })
+    }
+
+    // The declared name of a short option, and whether it takes an argument
+    fn find_short(&self, letter: char) -> Option<(&'static str, bool)> {
+        if letter == ':' {
+            return None;
+        }
+        self.shortopts.iter().find_map(|&shortopts| {
+            let position = shortopts.find(letter)?;
+            let end = position + letter.len_utf8();
+            Some((&shortopts[position..end], shortopts[end..].starts_with(':')))
+        })
+    }
+
+    // An exact match, or else the only option the given prefix could mean
+    fn find_long(&self, given: &str, word: &str) -> Result<&'static LongOption> {
+        let options = || self.longopts.iter().flat_map(|&table| table.iter());
+        if let Some(option) = options().find(|option| option.name == given) {
+            return Ok(option);
+        }
+
+        let candidates: Vec<&'static LongOption> = options()
+            .filter(|option| !given.is_empty() && option.name.starts_with(given))
+            .collect();
+        match candidates.as_slice() {
+            [] => Err(self.usage_error(&format!("unrecognized option '{}'", word))),
+            [option] => Ok(option),
+            [first, rest @ ..]
+                if rest
+                    .iter()
+                    .all(|option| option.short.is_some() && option.short == first.short) =>
+            {
+                Ok(first)
+            }
+            _ => {
+                let names: Vec<String> = candidates
+                    .iter()
+                    .map(|option| format!("'--{}'", option.name))
+                    .collect();
+                Err(self.usage_error(&format!(
+                    "option '--{}' is ambiguous; possibilities: {}",
+                    given,
+                    names.join(" ")
+                )))
+            }
+        }
+    }
+
+    fn usage_error(&self, message: &str) -> ShellError {
+        ShellError::invalid_arguments(&format!("{}: {}\n{}", self.program, message, self.usage()))
+    }
+
+    // "usage: mkdir [-pv] [-m ARG] [--parents] [--mode=ARG] ..." from the declarations
+    fn usage(&self) -> String {
+        let mut flags = String::new();
+        let mut with_args = Vec::new();
+        for &shortopts in &self.shortopts {
+            let mut letters = shortopts.chars().peekable();
+            while let Some(letter) = letters.next() {
+                if letters.peek() == Some(&':') {
+                    letters.next();
+                    with_args.push(format!("[-{} ARG]", letter));
+                } else if letter != ':' {
+                    flags.push(letter);
+                }
+            }
+        }
+
+        let mut usage = format!("usage: {}", self.program);
+        if !flags.is_empty() {
+            usage.push_str(&format!(" [-{}]", flags));
+        }
+        for option in with_args {
+            usage.push(' ');
+            usage.push_str(&option);
+        }
+        for option in self.longopts.iter().flat_map(|&table| table.iter()) {
+            usage.push_str(&match option.has_arg {
+                HasArg::No => format!(" [--{}]", option.name),
+                HasArg::Required => format!(" [--{}=ARG]", option.name),
+                HasArg::Optional => format!(" [--{}[=ARG]]", option.name),
+            });
+        }
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONG_OPTIONS: &[LongOption] = &[
+        long("all", HasArg::No, Some('a')),
+        long("almost-all", HasArg::No, Some('A')),
+        long("color", HasArg::Optional, None),
+        long("colour", HasArg::Optional, None),
+        long("mode", HasArg::Required, Some('m')),
+        long("parents", HasArg::No, Some('p')),
+        long("verbose", HasArg::No, Some('v')),
+    ];
+
+    type Parsed = (Vec<String>, Vec<String>);
+
+    // Every option as "name" or "name=value", then the operands
+    fn parse(args: &[&str]) -> Parsed {
+        parse_with("aAlFm:pv", args).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn parse_with(shortopts: &'static str, args: &[&str]) -> Result<Parsed> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut getopt = Getopt::new("test", &args, shortopts, LONG_OPTIONS);
+        let mut options = Vec::new();
+        while let Some(option) = getopt.next_option()? {
+            options.push(match option.value {
+                Some(value) => format!("{}={}", option.name, value),
+                None => option.name.to_string(),
+            });
+        }
+        Ok((options, getopt.operands()))
+    }
+
+    fn ok(options: &[&str], operands: &[&str]) -> Parsed {
+        let strings = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+        (strings(options), strings(operands))
+    }
+
+    // The first line of a usage error
+    fn error(args: &[&str]) -> String {
+        match parse_with("aAlFm:pv", args) {
+            Err(ShellError::InvalidArguments(message)) => {
+                message.lines().next().unwrap_or_default().to_string()
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(parsed) => panic!("parsed as {:?}", parsed),
+        }
+    }
+
+    #[test]
+    fn combined_short_options() {
+        assert_eq!(parse(&["-laF", "dir"]), ok(&["l", "a", "F"], &["dir"]));
+        assert_eq!(parse(&["-l", "-a", "-F"]), ok(&["l", "a", "F"], &[]));
+        assert_eq!(parse(&["-pvm755", "d"]), ok(&["p", "v", "m=755"], &["d"]));
+    }
+
+    #[test]
+    fn short_option_arguments() {
+        assert_eq!(parse(&["-m755", "d"]), ok(&["m=755"], &["d"]));
+        assert_eq!(parse(&["-m", "755", "d"]), ok(&["m=755"], &["d"]));
+        // The next word is taken whatever it looks like
+        assert_eq!(parse(&["-m", "-p", "d"]), ok(&["m=-p"], &["d"]));
+        assert_eq!(parse(&["-pm", "u=rwx"]), ok(&["p", "m=u=rwx"], &[]));
+    }
+
+    #[test]
+    fn long_options() {
+        assert_eq!(parse(&["--mode=755", "d"]), ok(&["m=755"], &["d"]));
+        assert_eq!(parse(&["--mode", "755", "d"]), ok(&["m=755"], &["d"]));
+        assert_eq!(parse(&["--mode="]), ok(&["m="], &[]));
+        assert_eq!(parse(&["--verbose"]), ok(&["v"], &[]));
+        // Optional arguments are only ever attached
+        assert_eq!(parse(&["--color", "always"]), ok(&["color"], &["always"]));
+        assert_eq!(parse(&["--color=never"]), ok(&["color=never"], &[]));
+    }
+
+    #[test]
+    fn long_option_abbreviations() {
+        assert_eq!(parse(&["--verb"]), ok(&["v"], &[]));
+        assert_eq!(parse(&["--p"]), ok(&["p"], &[]));
+        assert_eq!(parse(&["--mo=700"]), ok(&["m=700"], &[]));
+        assert_eq!(parse(&["--alm"]), ok(&["A"], &[]));
+        // An exact name wins over the longer names it is a prefix of
+        assert_eq!(parse(&["--all"]), ok(&["a"], &[]));
+        assert_eq!(
+            error(&["--al"]),
+            "test: option '--al' is ambiguous; possibilities: '--all' '--almost-all'"
+        );
+        assert_eq!(
+            error(&["--col"]),
+            "test: option '--col' is ambiguous; possibilities: '--color' '--colour'"
+        );
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        assert_eq!(
+            parse(&["-a", "--", "-l", "--mode=1"]),
+            ok(&["a"], &["-l", "--mode=1"])
+        );
+        assert_eq!(parse(&["--", "--"]), ok(&[], &["--"]));
+        assert_eq!(parse(&["-", "-a"]), ok(&["a"], &["-"]));
+    }
+
+    #[test]
+    fn options_and_operands_mix() {
+        assert_eq!(
+            parse(&["x", "-a", "y", "--verbose"]),
+            ok(&["a", "v"], &["x", "y"])
+        );
+        // Unless the short options start with '+'
+        assert_eq!(
+            parse_with("+aAlFm:pv", &["-a", "x", "-l"]).unwrap_or_else(|e| panic!("{}", e)),
+            ok(&["a"], &["x", "-l"])
+        );
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert_eq!(error(&["-x"]), "test: invalid option -- 'x'");
+        assert_eq!(error(&["-ax"]), "test: invalid option -- 'x'");
+        assert_eq!(error(&["-:"]), "test: invalid option -- ':'");
+        assert_eq!(error(&["-m"]), "test: option requires an argument -- 'm'");
+        assert_eq!(
+            error(&["--mode"]),
+            "test: option '--mode' requires an argument"
+        );
+        assert_eq!(error(&["--nope"]), "test: unrecognized option '--nope'");
+        assert_eq!(error(&["--="]), "test: unrecognized option '--='");
+        assert_eq!(
+            error(&["--verbose=1"]),
+            "test: option '--verbose' doesn't allow an argument"
+        );
+    }
+
+    #[test]
+    fn usage_line_comes_from_the_declarations() {
+        let args = vec!["-z".to_string()];
+        let mut getopt = Getopt::new("mkdir", &args, "pvm:", &LONG_OPTIONS[4..6]);
+        let Err(ShellError::InvalidArguments(message)) = getopt.next_option() else {
+            panic!("expected a usage error");
+        };
+        assert_eq!(
+            message,
+            "mkdir: invalid option -- 'z'\nusage: mkdir [-pv] [-m ARG] [--mode=ARG] [--parents]"
+        );
+    }
+}
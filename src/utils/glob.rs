@@ -0,0 +1,94 @@
+// Shell wildcard patterns, as fnmatch() matches them without flags: * matches any
+// run of characters, ? any one, [...] a set ("[!...]" or "[^...]" for the others),
+// and a backslash makes the next character literal. A leading dot isn't special.
+
+// Whether the whole text matches. On a mismatch after a *, the * takes one more
+// character and matching resumes, so only the most recent * is ever retried and
+// matching stays quadratic at worst.
+pub fn matches(pattern: &str, text: &str, ignore_case: bool) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let same = |a: char, b: char| a == b || (ignore_case && a.to_lowercase().eq(b.to_lowercase()));
+
+    let (mut p, mut t) = (0, 0);
+    // Where the last * was, and how much of the text it has taken
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match_set(&pattern, p, text[t], ignore_case),
+            Some('\\') if p + 1 < pattern.len() => same(pattern[p + 1], text[t]).then_some(2),
+            Some(&c) => same(c, text[t]).then_some(1),
+            None => None,
+        };
+        match step {
+            Some(length) => {
+                p += length;
+                t += 1;
+            }
+            None => {
+                let Some((star_p, star_t)) = star else {
+                    return false;
+                };
+                star = Some((star_p, star_t + 1));
+                p = star_p + 1;
+                t = star_t + 1;
+            }
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Match c against the set at pattern[start] == '['. Returns the set's length in
+// the pattern when c is in it. A '[' with no closing ']' is a literal.
+fn match_set(pattern: &[char], start: usize, c: char, ignore_case: bool) -> Option<usize> {
+    let candidates: Vec<char> = if ignore_case {
+        c.to_lowercase()
+            .chain(c.to_uppercase())
+            .chain([c])
+            .collect()
+    } else {
+        vec![c]
+    };
+    match parse_set(pattern, start, &candidates) {
+        Some((found, end)) => found.then_some(end - start),
+        None => (c == '[').then_some(1),
+    }
+}
+
+// Whether any candidate is in the set, and where the set ends; None when the
+// closing ']' is missing
+fn parse_set(pattern: &[char], start: usize, candidates: &[char]) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut found = false;
+    let mut first = true;
+    loop {
+        let mut low = *pattern.get(i)?;
+        if low == ']' && !first {
+            return Some((found != negated, i + 1));
+        }
+        first = false;
+        if low == '\\' {
+            i += 1;
+            low = *pattern.get(i)?;
+        }
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|&high| high != ']') {
+            let high = pattern[i + 2];
+            found |= candidates.iter().any(|c| (low..=high).contains(c));
+            i += 3;
+        } else {
+            found |= candidates.contains(&low);
+            i += 1;
+        }
+    }
+}
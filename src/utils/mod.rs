@@ -1 +1,18 @@
-pub mod error;
\ No newline at end of file
+pub mod colors;
+pub mod copy;
+pub mod error;
+pub mod getopt;
+pub mod glob;
+pub mod mode;
+pub mod overwrite;
+pub mod prompt;
+pub mod regex;
+#[cfg(test)]
+pub mod scratch;
+pub mod signals;
+pub mod size;
+pub mod sys;
+pub mod terminal;
+pub mod time;
+pub mod users;
+pub mod workdir;
\ No newline at end of file
@@ -0,0 +1,269 @@
+// File mode specifications as taken by mkdir -m and chmod: an octal number, or
+// symbolic clauses like "u+x", "go-w", "a=rX" or "u=g,o-rwx".
+
+const SET_ID: u32 = 0o6000; // set-user-ID and set-group-ID
+const STICKY: u32 = 0o1000;
+const USER: u32 = 0o4700;
+const GROUP: u32 = 0o2070;
+const OTHER: u32 = 0o1007;
+const ALL: u32 = 0o7777;
+const READ: u32 = 0o444;
+const WRITE: u32 = 0o222;
+const EXECUTE: u32 = 0o111;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Ordinary,
+    CopyExisting, // "g=u": take the bits from another class
+    ExecuteIfAny, // "X": execute only for directories or files already executable by someone
+}
+
+struct Change {
+    op: char, // '+', '-' or '='
+    kind: Kind,
+    affected: u32,  // the classes named before the operator, 0 when none were
+    value: u32,     // the bits named after it
+    mentioned: u32, // bits the change explicitly talks about
+}
+
+pub struct ModeChange {
+    changes: Vec<Change>,
+}
+
+impl ModeChange {
+    // None when the specification is malformed
+    pub fn parse(spec: &str) -> Option<ModeChange> {
+        if !spec.is_empty() && spec.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+            let mode = u32::from_str_radix(spec, 8).ok().filter(|&m| m <= ALL)?;
+            // Short octal modes leave a directory's set-id bits alone unless they set them
+            let mentioned = if spec.len() < 5 {
+                (mode & SET_ID) | STICKY | 0o777
+            } else {
+                ALL
+            };
+            return Some(ModeChange {
+                changes: vec![Change {
+                    op: '=',
+                    kind: Kind::Ordinary,
+                    affected: ALL,
+                    value: mode,
+                    mentioned,
+                }],
+            });
+        }
+
+        let mut changes = Vec::new();
+        for clause in spec.split(',') {
+            let mut chars = clause.chars().peekable();
+
+            let mut affected = 0;
+            while let Some(&c) = chars.peek() {
+                affected |= match c {
+                    'u' => USER,
+                    'g' => GROUP,
+                    'o' => OTHER,
+                    'a' => ALL,
+                    _ => break,
+                };
+                chars.next();
+            }
+
+            // At least one operator, each followed by permission letters or one class
+            chars.peek()?;
+            while let Some(op) = chars.next() {
+                if !matches!(op, '+' | '-' | '=') {
+                    return None;
+                }
+
+                let mut kind = Kind::Ordinary;
+                let mut value = 0;
+                match chars.peek() {
+                    Some(&class @ ('u' | 'g' | 'o')) => {
+                        chars.next();
+                        kind = Kind::CopyExisting;
+                        value = match class {
+                            'u' => 0o700,
+                            'g' => 0o070,
+                            _ => 0o007,
+                        };
+                    }
+                    _ => {
+                        while let Some(&c) = chars.peek() {
+                            match c {
+                                'r' => value |= READ,
+                                'w' => value |= WRITE,
+                                'x' => value |= EXECUTE,
+                                'X' => kind = Kind::ExecuteIfAny,
+                                's' => value |= SET_ID,
+                                't' => value |= STICKY,
+                                _ => break,
+                            }
+                            chars.next();
+                        }
+                    }
+                }
+
+                changes.push(Change {
+                    op,
+                    kind,
+                    affected,
+                    value,
+                    mentioned: if affected != 0 {
+                        affected & value
+                    } else {
+                        value
+                    },
+                });
+            }
+        }
+
+        Some(ModeChange { changes })
+    }
+
+    // The mode that results from applying the changes to mode. Clauses that name no
+    // class ("+x", "=r") leave alone the bits set in umask, as chmod does.
+    pub fn apply(&self, mode: u32, is_dir: bool, umask: u32) -> u32 {
+        let mut mode = mode & ALL;
+        for change in &self.changes {
+            // Directories keep set-id bits a change doesn't explicitly mention
+            let omit = if is_dir {
+                SET_ID & !change.mentioned
+            } else {
+                0
+            };
+
+            let mut value = change.value;
+            match change.kind {
+                Kind::Ordinary => {}
+                Kind::CopyExisting => {
+                    // Spread the copied rwx bits across all three classes; the
+                    // affected mask below narrows them to the ones being changed
+                    let copied = value & mode;
+                    let spread = |bits: u32| if copied & bits != 0 { bits } else { 0 };
+                    value = copied | spread(READ) | spread(WRITE) | spread(EXECUTE);
+                }
+                Kind::ExecuteIfAny => {
+                    if mode & EXECUTE != 0 || is_dir {
+                        value |= EXECUTE;
+                    }
+                }
+            }
+
+            let limit = if change.affected != 0 {
+                change.affected
+            } else {
+                !umask & ALL
+            };
+            value &= limit & !omit;
+
+            mode = match change.op {
+                '+' => mode | value,
+                '-' => mode & !value,
+                _ => {
+                    // "=" without classes resets everything; with them only those classes
+                    let preserved = if change.affected != 0 {
+                        !change.affected
+                    } else {
+                        0
+                    };
+                    (mode & (preserved | omit)) | value
+                }
+            };
+        }
+        mode
+    }
+}
+
+// "rwxr-xr-x" for the permission bits of a mode, with s/S and t/T for the set-id
+// and sticky bits, as ls -l and chmod -v show them
+pub fn permission_string(mode: u32) -> String {
+    let mut result = String::with_capacity(9);
+    // (read bit, write bit, execute bit, special bit, special char)
+    let triples = [
+        (0o400, 0o200, 0o100, 0o4000, 's'),
+        (0o040, 0o020, 0o010, 0o2000, 's'),
+        (0o004, 0o002, 0o001, 0o1000, 't'),
+    ];
+    for (read, write, exec, special, special_char) in triples {
+        result.push(if mode & read != 0 { 'r' } else { '-' });
+        result.push(if mode & write != 0 { 'w' } else { '-' });
+        result.push(match (mode & exec != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // spec applied to a file (or directory) with mode, under umask
+    fn apply(spec: &str, mode: u32, is_dir: bool, umask: u32) -> u32 {
+        ModeChange::parse(spec)
+            .unwrap_or_else(|| panic!("{} didn't parse", spec))
+            .apply(mode, is_dir, umask)
+    }
+
+    #[test]
+    fn execute_if_any() {
+        // X adds execute only where someone could already execute, or to directories
+        assert_eq!(apply("a+X", 0o644, false, 0), 0o644);
+        assert_eq!(apply("a+X", 0o744, false, 0), 0o755);
+        assert_eq!(apply("a+X", 0o644, true, 0), 0o755);
+        assert_eq!(apply("go+rX", 0o600, false, 0), 0o644);
+        assert_eq!(apply("a=rX", 0o700, false, 0), 0o555);
+        assert_eq!(apply("a-X", 0o755, false, 0), 0o644);
+    }
+
+    #[test]
+    fn assignment() {
+        // = replaces the named classes' bits and leaves the others
+        assert_eq!(apply("u=rw", 0o777, false, 0), 0o677);
+        assert_eq!(apply("go=", 0o777, false, 0), 0o700);
+        assert_eq!(apply("u=rw,go=r", 0o7777, false, 0), 0o644);
+        assert_eq!(apply("a=", 0o4755, false, 0), 0);
+        // Copying another class's bits
+        assert_eq!(apply("g=u", 0o750, false, 0), 0o770);
+        assert_eq!(apply("o=g", 0o750, false, 0), 0o755);
+        assert_eq!(apply("go=u-w", 0o754, false, 0), 0o755);
+        // Several operators in one clause apply in turn
+        assert_eq!(apply("u=r+x", 0o600, false, 0), 0o500);
+    }
+
+    #[test]
+    fn umask_applies_only_without_a_class() {
+        assert_eq!(apply("+x", 0o644, false, 0o022), 0o755);
+        assert_eq!(apply("+x", 0o644, false, 0o077), 0o744);
+        assert_eq!(apply("a+x", 0o644, false, 0o077), 0o755);
+        assert_eq!(apply("=rw", 0o777, false, 0o022), 0o644);
+        assert_eq!(apply("=rw", 0o777, false, 0o077), 0o600);
+        // Taking away is limited the same way, so -w with umask 022 keeps g+w and o+w
+        assert_eq!(apply("-w", 0o666, false, 0o022), 0o466);
+        assert_eq!(apply("a-w", 0o666, false, 0o022), 0o444);
+        // Octal modes aren't affected
+        assert_eq!(apply("666", 0o777, false, 0o077), 0o666);
+    }
+
+    #[test]
+    fn directories_keep_set_id_bits() {
+        assert_eq!(apply("755", 0o2700, true, 0), 0o2755);
+        assert_eq!(apply("755", 0o2700, false, 0), 0o755);
+        assert_eq!(apply("00755", 0o2700, true, 0), 0o755);
+        assert_eq!(apply("a=rx", 0o2700, true, 0), 0o2555);
+        assert_eq!(apply("g-s", 0o2700, true, 0), 0o700);
+        assert_eq!(apply("u+s,+t", 0o755, false, 0), 0o5755);
+    }
+
+    #[test]
+    fn malformed() {
+        for spec in ["", "u", "u+q", "x+r", "8", "77777", "u+r,", "+u+x,o"] {
+            assert!(ModeChange::parse(spec).is_none(), "{:?} parsed", spec);
+        }
+        assert_eq!(permission_string(0o4751), "rwsr-x--x");
+        assert_eq!(permission_string(0o3644), "rw-r-Sr-T");
+    }
+}
@@ -0,0 +1,348 @@
+use crate::utils::error::{Result, ShellError};
+use crate::utils::getopt::{HasArg, LongOption, Opt, long};
+use crate::utils::prompt;
+use std::env;
+use std::fs::{self, Metadata};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+// What cp and mv do when the destination already exists. Both commands take their
+// options from here so that -i, -n, -u, -f and the backup flags behave the same.
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Mode {
+    #[default]
+    Replace, // overwrite without asking
+    Force,       // -f: also remove destinations that can't be opened
+    Interactive, // -i: ask first
+    NoClobber,   // -n: never overwrite
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Backup {
+    #[default]
+    Off,
+    Simple,   // file~
+    Numbered, // file.~1~, file.~2~, ...
+    Existing, // numbered if numbered backups already exist, else simple
+}
+
+#[derive(Default)]
+pub struct OverwritePolicy {
+    pub mode: Mode,
+    pub update: bool, // -u: only replace destinations older than the source
+    pub backup: Backup,
+    pub suffix: Option<String>,
+}
+
+// The options cp and mv both accept, for Getopt::with
+pub const SHORT_OPTIONS: &str = "bfinuS:";
+pub const LONG_OPTIONS: &[LongOption] = &[
+    long("backup", HasArg::Optional, None),
+    long("force", HasArg::No, Some('f')),
+    long("interactive", HasArg::No, Some('i')),
+    long("no-clobber", HasArg::No, Some('n')),
+    long("suffix", HasArg::Required, Some('S')),
+    long("update", HasArg::No, Some('u')),
+];
+
+impl OverwritePolicy {
+    // Apply an option if it is one of the shared ones. Of -f, -i and -n the last wins.
+    pub fn apply(&mut self, option: &Opt, program: &str) -> Result<bool> {
+        match option.name {
+            "f" => self.mode = Mode::Force,
+            "i" => self.mode = Mode::Interactive,
+            "n" => self.mode = Mode::NoClobber,
+            "u" => self.update = true,
+            "b" => self.backup = backup_from_env(program)?,
+            "backup" => {
+                self.backup = match option.value {
+                    Some(control) => parse_backup(control, program)?,
+                    None => backup_from_env(program)?,
+                }
+            }
+            "S" => {
+                // Giving a suffix asks for backups, as it does for cp and mv
+                self.suffix = option.value.map(str::to_string);
+                if self.backup == Backup::Off {
+                    self.backup = backup_from_env(program)?;
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // Decide whether src may replace the existing dest, asking on stdin under -i.
+    // Returns false when dest should be left alone; a missing dest is always fine.
+    pub fn allows(
+        &self,
+        src: &Metadata,
+        dest: &Path,
+        program: &str,
+        stdin: &mut dyn Read,
+        stderr: &mut dyn Write,
+    ) -> io::Result<bool> {
+        let Ok(existing) = fs::symlink_metadata(dest) else {
+            return Ok(true);
+        };
+
+        if self.mode == Mode::NoClobber {
+            return Ok(false);
+        }
+        // With -u a dest that isn't older stays, so there's nothing to ask about
+        if self.update
+            && (src.mtime(), src.mtime_nsec()) <= (existing.mtime(), existing.mtime_nsec())
+        {
+            return Ok(false);
+        }
+        if self.mode == Mode::Interactive {
+            let question = format!("{}: overwrite '{}'? ", program, dest.display());
+            return prompt::confirm(stdin, stderr, &question);
+        }
+        Ok(true)
+    }
+
+    // Move an existing dest aside according to the backup setting, returning where it
+    // went. Nothing happens when backups are off or dest doesn't exist.
+    pub fn back_up(&self, dest: &Path) -> io::Result<Option<PathBuf>> {
+        if self.backup == Backup::Off || fs::symlink_metadata(dest).is_err() {
+            return Ok(None);
+        }
+
+        let numbered = match self.backup {
+            Backup::Numbered => true,
+            Backup::Existing => highest_backup(dest)? > 0,
+            _ => false,
+        };
+        let name = if numbered {
+            format!(".~{}~", highest_backup(dest)? + 1)
+        } else {
+            self.suffix
+                .clone()
+                .or_else(|| env::var("SIMPLE_BACKUP_SUFFIX").ok())
+                .filter(|suffix| !suffix.is_empty() && !suffix.contains('/'))
+                .unwrap_or_else(|| "~".to_string())
+        };
+
+        let mut backup = dest.as_os_str().to_os_string();
+        backup.push(name);
+        let backup = PathBuf::from(backup);
+        fs::rename(dest, &backup)?;
+        Ok(Some(backup))
+    }
+}
+
+// -b and a bare --backup take their method from $VERSION_CONTROL, defaulting to "existing"
+fn backup_from_env(program: &str) -> Result<Backup> {
+    match env::var("VERSION_CONTROL") {
+        Ok(control) if !control.is_empty() => parse_backup(&control, program),
+        _ => Ok(Backup::Existing),
+    }
+}
+
+fn parse_backup(control: &str, program: &str) -> Result<Backup> {
+    match control {
+        "none" | "off" => Ok(Backup::Off),
+        "simple" | "never" => Ok(Backup::Simple),
+        "numbered" | "t" => Ok(Backup::Numbered),
+        "existing" | "nil" => Ok(Backup::Existing),
+        _ => Err(ShellError::invalid_arguments(&format!(
+            "{}: invalid argument '{}' for 'backup type'",
+            program, control
+        ))),
+    }
+}
+
+// The largest N among dest's existing "dest.~N~" backups, or 0
+fn highest_backup(dest: &Path) -> io::Result<u64> {
+    let Some(name) = dest.file_name() else {
+        return Ok(0);
+    };
+    let prefix = format!("{}.~", name.to_string_lossy());
+    let dir = match dest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut highest = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let entry_name = entry.file_name();
+        let entry_name = entry_name.to_string_lossy();
+        let number = entry_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix('~'))
+            .and_then(|digits| digits.parse::<u64>().ok());
+        if let Some(number) = number {
+            highest = highest.max(number);
+        }
+    }
+    Ok(highest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::getopt::Getopt;
+    use crate::utils::scratch::Scratch;
+    use crate::utils::sys;
+
+    fn policy(args: &[&str]) -> OverwritePolicy {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut policy = OverwritePolicy::default();
+        let mut getopt = Getopt::new("cp", &args, SHORT_OPTIONS, LONG_OPTIONS);
+        while let Some(option) = getopt.next_option().unwrap_or_else(|e| panic!("{}", e)) {
+            assert!(
+                policy
+                    .apply(&option, "cp")
+                    .unwrap_or_else(|e| panic!("{}", e))
+            );
+        }
+        policy
+    }
+
+    // Whether old may be replaced by new given these answers on stdin, along with
+    // what was asked and what is left of the answers
+    fn allows(policy: &OverwritePolicy, dir: &Scratch, answers: &str) -> (bool, String, String) {
+        let src = fs::metadata(dir.path("new")).unwrap();
+        let mut stdin = answers.as_bytes();
+        let mut stderr = Vec::new();
+        let allowed = policy
+            .allows(&src, &dir.path("old"), "cp", &mut stdin, &mut stderr)
+            .unwrap();
+        let asked = String::from_utf8(stderr).unwrap().replace(&dir.arg(""), "");
+        (allowed, asked, String::from_utf8(stdin.to_vec()).unwrap())
+    }
+
+    fn scratch_with_times(name: &str, old: i64, new: i64) -> Scratch {
+        let dir = Scratch::new(name, &["old", "new"]);
+        sys::set_times(&dir.path("old"), None, Some((old, 0)), true).unwrap();
+        sys::set_times(&dir.path("new"), None, Some((new, 0)), true).unwrap();
+        dir
+    }
+
+    #[test]
+    fn interactive_asks_one_line_at_a_time() {
+        let dir = scratch_with_times("overwrite-interactive", 100, 200);
+        let question = "cp: overwrite 'old'? ".to_string();
+        let interactive = policy(&["-i"]);
+        assert_eq!(
+            allows(&interactive, &dir, "y\nn\n"),
+            (true, question.clone(), "n\n".to_string())
+        );
+        assert_eq!(
+            allows(&interactive, &dir, " Yes please\n"),
+            (true, question.clone(), String::new())
+        );
+        assert_eq!(
+            allows(&interactive, &dir, "n\ny\n"),
+            (false, question.clone(), "y\n".to_string())
+        );
+        // End of input is a no
+        assert_eq!(
+            allows(&interactive, &dir, ""),
+            (false, question, String::new())
+        );
+
+        // Nothing is asked about a destination that isn't there
+        fs::remove_file(dir.path("old")).unwrap();
+        assert_eq!(
+            allows(&interactive, &dir, "n\n"),
+            (true, String::new(), "n\n".to_string())
+        );
+    }
+
+    #[test]
+    fn update_is_decided_before_asking() {
+        // The destination is newer: -u keeps it without a question
+        let dir = scratch_with_times("overwrite-update-newer", 200, 100);
+        assert_eq!(
+            allows(&policy(&["-u", "-i"]), &dir, "y\n"),
+            (false, String::new(), "y\n".to_string())
+        );
+        assert!(!allows(&policy(&["-u"]), &dir, "").0);
+        // Equal times count as not older
+        let dir = scratch_with_times("overwrite-update-equal", 100, 100);
+        assert!(!allows(&policy(&["-u"]), &dir, "").0);
+
+        // The destination is older: -u lets -i ask
+        let dir = scratch_with_times("overwrite-update-older", 100, 200);
+        assert_eq!(
+            allows(&policy(&["-i", "-u"]), &dir, "n\n"),
+            (false, "cp: overwrite 'old'? ".to_string(), String::new())
+        );
+        assert!(allows(&policy(&["-u"]), &dir, "").0);
+        // -n never replaces, whatever the times
+        assert!(!allows(&policy(&["-u", "-n"]), &dir, "").0);
+    }
+
+    #[test]
+    fn last_of_force_interactive_and_no_clobber_wins() {
+        assert!(policy(&["-i", "-n"]).mode == Mode::NoClobber);
+        assert!(policy(&["-n", "-f"]).mode == Mode::Force);
+        assert!(policy(&["-f", "--interactive"]).mode == Mode::Interactive);
+    }
+
+    #[test]
+    fn backups() {
+        let dir = Scratch::new("overwrite-backups", &["f", "g", "h"]);
+        assert!(policy(&[]).back_up(&dir.path("f")).unwrap().is_none());
+
+        let simple = policy(&["--backup=simple"]);
+        assert_eq!(
+            simple.back_up(&dir.path("f")).unwrap(),
+            Some(dir.path("f~"))
+        );
+        assert!(!dir.path("f").exists());
+        // A missing destination has nothing to back up
+        assert!(simple.back_up(&dir.path("f")).unwrap().is_none());
+        let suffixed = policy(&["--backup=simple", "-S", ".bak"]);
+        assert_eq!(
+            suffixed.back_up(&dir.path("g")).unwrap(),
+            Some(dir.path("g.bak"))
+        );
+
+        let numbered = policy(&["--backup=numbered"]);
+        assert_eq!(
+            numbered.back_up(&dir.path("h")).unwrap(),
+            Some(dir.path("h.~1~"))
+        );
+        dir.write("h", "");
+        assert_eq!(
+            numbered.back_up(&dir.path("h")).unwrap(),
+            Some(dir.path("h.~2~"))
+        );
+
+        // "existing" numbers only what is numbered already
+        let existing = policy(&["--backup=existing"]);
+        dir.write("h", "");
+        assert_eq!(
+            existing.back_up(&dir.path("h")).unwrap(),
+            Some(dir.path("h.~3~"))
+        );
+        dir.write("g", "");
+        assert_eq!(
+            existing.back_up(&dir.path("g")).unwrap(),
+            Some(dir.path("g~"))
+        );
+    }
+
+    #[test]
+    fn invalid_backup_types() {
+        let args = vec!["--backup=sometimes".to_string()];
+        let mut getopt = Getopt::new("cp", &args, SHORT_OPTIONS, LONG_OPTIONS);
+        let option = getopt
+            .next_option()
+            .unwrap_or_else(|e| panic!("{}", e))
+            .unwrap();
+        match OverwritePolicy::default().apply(&option, "cp") {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Invalid arguments: cp: invalid argument 'sometimes' for 'backup type'"
+            ),
+            Ok(_) => panic!("--backup=sometimes was accepted"),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+// Ask a yes/no question on stderr and read the answer from the command's stdin,
+// which may be a redirected file rather than the terminal. Only one line is
+// consumed, so a file of answers serves several prompts in turn. Anything
+// starting with y or Y is a yes; end of input is a no.
+pub fn confirm(stdin: &mut dyn Read, stderr: &mut dyn Write, question: &str) -> io::Result<bool> {
+    write!(stderr, "{}", question)?;
+    stderr.flush()?;
+
+    // Byte by byte, so nothing past the end of the line is taken from the input
+    let mut answer = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        match stdin.read(&mut byte) {
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) => answer.push(byte[0]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    let answer = String::from_utf8_lossy(&answer);
+    Ok(answer.trim_start().starts_with(['y', 'Y']))
+}
@@ -0,0 +1,1032 @@
+// POSIX regular expressions, basic (BRE) and extended (ERE), with the GNU
+// extensions grep users expect: \w \W \s \S \b \B \< \>, and \+ \? \| in BREs.
+//
+// A pattern is parsed into a small syntax tree and compiled into a program for a
+// Pike VM: all the ways the pattern can match are followed side by side, one input
+// character at a time, so matching takes time proportional to the length of the
+// text times the size of the program and never backtracks. Matches are leftmost-
+// longest, as POSIX asks. Back-references would need backtracking and are refused.
+
+// Repetition counts above this are refused, like RE_DUP_MAX in glibc
+const DUP_MAX: u32 = 32767;
+
+// Programs are capped so that "(a{1000}){1000}" fails to compile instead of
+// exhausting memory
+const MAX_PROGRAM: usize = 1 << 20;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Syntax {
+    Basic,
+    Extended,
+    Fixed, // every character stands for itself
+}
+
+#[derive(Clone, Copy)]
+pub struct RegexOptions {
+    pub syntax: Syntax,
+    pub ignore_case: bool,
+    pub whole_words: bool, // matches may not touch a word character on either side
+    pub whole_lines: bool, // matches must span the whole text
+}
+
+pub struct Regex {
+    program: Vec<Inst>,
+    classes: Vec<Class>,
+    ignore_case: bool,
+}
+
+enum Inst {
+    Char(char), // already case-folded when ignoring case
+    Any,        // any character but newline
+    Class(usize),
+    Assert(Assertion),
+    Split(usize, usize),
+    Jmp(usize),
+    Match,
+}
+
+#[derive(Clone, Copy)]
+enum Assertion {
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    NotWordBoundary,
+    WordStart,
+    WordEnd,
+    NoWordBefore, // -w, on the left of the match
+    NoWordAfter,  // -w, on the right
+}
+
+#[derive(Clone)]
+enum Node {
+    Literal(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+// A bracket expression, or one of the \w \s shorthands
+#[derive(Clone)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Clone)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Named(NamedClass),
+}
+
+#[derive(Clone, Copy)]
+enum NamedClass {
+    Alnum,
+    Alpha,
+    Blank,
+    Cntrl,
+    Digit,
+    Graph,
+    Lower,
+    Print,
+    Punct,
+    Space,
+    Upper,
+    Xdigit,
+    Word, // alnum and '_', for \w
+}
+
+impl Regex {
+    // Compile patterns that each may match, as grep -e takes several. Errors are
+    // messages in the words glibc uses, such as "Unmatched ( or \(".
+    pub fn new(patterns: &[String], options: &RegexOptions) -> Result<Regex, String> {
+        let mut branches = Vec::new();
+        for pattern in patterns {
+            let node = match options.syntax {
+                Syntax::Fixed => Node::Concat(pattern.chars().map(Node::Literal).collect()),
+                syntax => Parser::new(pattern, syntax).parse()?,
+            };
+            branches.push(node);
+        }
+
+        let mut node = if branches.len() == 1 {
+            branches.pop().unwrap_or(Node::Concat(Vec::new()))
+        } else {
+            Node::Alternate(branches)
+        };
+        if options.whole_lines {
+            node = Node::Concat(vec![
+                Node::Assert(Assertion::LineStart),
+                node,
+                Node::Assert(Assertion::LineEnd),
+            ]);
+        } else if options.whole_words {
+            node = Node::Concat(vec![
+                Node::Assert(Assertion::NoWordBefore),
+                node,
+                Node::Assert(Assertion::NoWordAfter),
+            ]);
+        }
+
+        let mut compiler = Compiler {
+            program: Vec::new(),
+            classes: Vec::new(),
+            ignore_case: options.ignore_case,
+        };
+        compiler.compile(node)?;
+        compiler.emit(Inst::Match)?;
+        Ok(Regex {
+            program: compiler.program,
+            classes: compiler.classes,
+            ignore_case: options.ignore_case,
+        })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.search(text, 0, true).is_some()
+    }
+
+    // The leftmost-longest match starting at or after byte offset start, as a
+    // byte range. Whatever precedes start still counts for ^, \b and the like.
+    pub fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        self.search(text, start, false)
+    }
+
+    fn search(&self, text: &str, start: usize, any_match: bool) -> Option<(usize, usize)> {
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut stack = Vec::new();
+        let mut best: Option<(usize, usize)> = None;
+
+        let mut position = start;
+        let mut previous = text[..start].chars().next_back();
+        loop {
+            let here = text[position..].chars().next();
+            // New attempts stop once a match is found: they could only start later
+            if best.is_none() {
+                self.add_thread(&mut current, &mut stack, 0, position, previous, here);
+            }
+            if current.list.is_empty() && (best.is_some() || here.is_none()) {
+                break;
+            }
+
+            let after = here.and_then(|c| text[position + c.len_utf8()..].chars().next());
+            // Threads are kept in order of where they started, earliest first
+            for index in 0..current.list.len() {
+                let (pc, thread_start) = current.list[index];
+                if let Some((best_start, _)) = best
+                    && thread_start > best_start
+                {
+                    break;
+                }
+                match &self.program[pc] {
+                    Inst::Match => {
+                        let longer = best.is_none_or(|(best_start, best_end)| {
+                            thread_start < best_start || position > best_end
+                        });
+                        if longer {
+                            best = Some((thread_start, position));
+                        }
+                        if any_match {
+                            return best;
+                        }
+                    }
+                    inst => {
+                        if let Some(c) = here
+                            && self.consumes(inst, c)
+                        {
+                            self.add_thread(
+                                &mut next,
+                                &mut stack,
+                                pc + 1,
+                                thread_start,
+                                here,
+                                after,
+                            );
+                        }
+                    }
+                }
+            }
+
+            let Some(c) = here else {
+                break;
+            };
+            position += c.len_utf8();
+            previous = here;
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        best
+    }
+
+    // Follow jumps, splits and assertions from pc, adding the threads that wait on
+    // a character. previous and here are the characters around the position.
+    fn add_thread(
+        &self,
+        threads: &mut Threads,
+        stack: &mut Vec<usize>,
+        pc: usize,
+        start: usize,
+        previous: Option<char>,
+        here: Option<char>,
+    ) {
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if threads.marks[pc] == threads.generation {
+                continue;
+            }
+            threads.marks[pc] = threads.generation;
+            match &self.program[pc] {
+                Inst::Jmp(target) => stack.push(*target),
+                Inst::Split(first, second) => {
+                    stack.push(*second);
+                    stack.push(*first);
+                }
+                Inst::Assert(assertion) => {
+                    if assertion.holds(previous, here) {
+                        stack.push(pc + 1);
+                    }
+                }
+                _ => threads.list.push((pc, start)),
+            }
+        }
+    }
+
+    fn consumes(&self, inst: &Inst, c: char) -> bool {
+        match inst {
+            Inst::Char(expected) if self.ignore_case => fold_case(c) == *expected,
+            Inst::Char(expected) => c == *expected,
+            Inst::Any => c != '\n',
+            Inst::Class(index) => self.classes[*index].matches(c, self.ignore_case),
+            _ => false,
+        }
+    }
+}
+
+// The threads alive at one position: (pc, where the match attempt started).
+// marks keeps each pc from being added twice in the same generation.
+struct Threads {
+    list: Vec<(usize, usize)>,
+    marks: Vec<usize>,
+    generation: usize,
+}
+
+impl Threads {
+    fn new(size: usize) -> Self {
+        Threads {
+            list: Vec::new(),
+            marks: vec![0; size],
+            generation: 1,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.generation += 1;
+    }
+}
+
+impl Assertion {
+    fn holds(self, previous: Option<char>, here: Option<char>) -> bool {
+        let (before, after) = (is_word_char(previous), is_word_char(here));
+        match self {
+            Assertion::LineStart => previous.is_none(),
+            Assertion::LineEnd => here.is_none(),
+            Assertion::WordBoundary => before != after,
+            Assertion::NotWordBoundary => before == after,
+            Assertion::WordStart => !before && after,
+            Assertion::WordEnd => before && !after,
+            Assertion::NoWordBefore => !before,
+            Assertion::NoWordAfter => !after,
+        }
+    }
+}
+
+fn is_word_char(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn fold_case(c: char) -> char {
+    if c.is_uppercase() {
+        c.to_lowercase().next().unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+impl Class {
+    fn matches(&self, c: char, ignore_case: bool) -> bool {
+        let mut found = self.items.iter().any(|item| item.matches(c));
+        if !found && ignore_case {
+            found = c
+                .to_lowercase()
+                .chain(c.to_uppercase())
+                .any(|other| self.items.iter().any(|item| item.matches(other)));
+        }
+        found != self.negated
+    }
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ClassItem::Char(expected) => c == *expected,
+            ClassItem::Range(low, high) => (*low..=*high).contains(&c),
+            ClassItem::Named(named) => named.matches(c),
+        }
+    }
+}
+
+impl NamedClass {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "alnum" => NamedClass::Alnum,
+            "alpha" => NamedClass::Alpha,
+            "blank" => NamedClass::Blank,
+            "cntrl" => NamedClass::Cntrl,
+            "digit" => NamedClass::Digit,
+            "graph" => NamedClass::Graph,
+            "lower" => NamedClass::Lower,
+            "print" => NamedClass::Print,
+            "punct" => NamedClass::Punct,
+            "space" => NamedClass::Space,
+            "upper" => NamedClass::Upper,
+            "xdigit" => NamedClass::Xdigit,
+            _ => return None,
+        })
+    }
+
+    fn matches(self, c: char) -> bool {
+        match self {
+            NamedClass::Alnum => c.is_alphanumeric(),
+            NamedClass::Alpha => c.is_alphabetic(),
+            NamedClass::Blank => c == ' ' || c == '\t',
+            NamedClass::Cntrl => c.is_control(),
+            NamedClass::Digit => c.is_ascii_digit(),
+            NamedClass::Graph => !c.is_whitespace() && !c.is_control(),
+            NamedClass::Lower => c.is_lowercase(),
+            NamedClass::Print => !c.is_control(),
+            NamedClass::Punct => c.is_ascii_punctuation(),
+            NamedClass::Space => c.is_whitespace(),
+            NamedClass::Upper => c.is_uppercase(),
+            NamedClass::Xdigit => c.is_ascii_hexdigit(),
+            NamedClass::Word => c.is_alphanumeric() || c == '_',
+        }
+    }
+}
+
+// Recursive descent over the pattern. BREs and EREs differ in which characters
+// need a backslash to be special, so each syntax question goes through a helper.
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    syntax: Syntax,
+}
+
+impl Parser {
+    fn new(pattern: &str, syntax: Syntax) -> Self {
+        Parser {
+            chars: pattern.chars().collect(),
+            position: 0,
+            syntax,
+        }
+    }
+
+    fn parse(mut self) -> Result<Node, String> {
+        let node = self.parse_alternation()?;
+        if self.position < self.chars.len() {
+            // Only a closing parenthesis stops the top level early
+            return Err("Unmatched ) or \\)".to_string());
+        }
+        Ok(node)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    // The character that is special when escaped in a BRE and bare in an ERE
+    fn at_operator(&self, c: char) -> bool {
+        match self.syntax {
+            Syntax::Extended => self.peek() == Some(c),
+            _ => self.peek() == Some('\\') && self.peek_at(1) == Some(c),
+        }
+    }
+
+    fn skip_operator(&mut self) {
+        self.position += if self.syntax == Syntax::Extended {
+            1
+        } else {
+            2
+        };
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.parse_branch()?];
+        while self.at_operator('|') {
+            self.skip_operator();
+            branches.push(self.parse_branch()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            Node::Alternate(branches)
+        })
+    }
+
+    fn parse_branch(&mut self) -> Result<Node, String> {
+        let mut items = Vec::new();
+        while self.peek().is_some() && !self.at_operator('|') && !self.at_operator(')') {
+            let atom = self.parse_atom(items.is_empty())?;
+            // A quantifier right after an anchor is taken literally, as in "^*"
+            let atom = match atom {
+                Node::Assert(_) => atom,
+                _ => self.parse_quantifiers(atom)?,
+            };
+            items.push(atom);
+        }
+        Ok(Node::Concat(items))
+    }
+
+    fn parse_atom(&mut self, branch_start: bool) -> Result<Node, String> {
+        if self.at_operator('(') {
+            self.skip_operator();
+            let node = self.parse_alternation()?;
+            if !self.at_operator(')') {
+                return Err("Unmatched ( or \\(".to_string());
+            }
+            self.skip_operator();
+            return Ok(node);
+        }
+
+        let Some(c) = self.peek() else {
+            return Err("Invalid regular expression".to_string());
+        };
+        self.position += 1;
+        let extended = self.syntax == Syntax::Extended;
+        Ok(match c {
+            '.' => Node::Any,
+            '[' => Node::Class(self.parse_bracket()?),
+            // In a BRE, ^ is an anchor only at the start and $ only at the end
+            '^' if extended || branch_start => Node::Assert(Assertion::LineStart),
+            '$' if extended
+                || self.peek().is_none()
+                || self.at_operator(')')
+                || self.at_operator('|') =>
+            {
+                Node::Assert(Assertion::LineEnd)
+            }
+            '\\' => self.parse_escape(branch_start)?,
+            // Nothing to repeat: * and the like stand for themselves
+            _ => Node::Literal(c),
+        })
+    }
+
+    // After a backslash
+    fn parse_escape(&mut self, branch_start: bool) -> Result<Node, String> {
+        let Some(c) = self.peek() else {
+            return Err("Trailing backslash".to_string());
+        };
+        self.position += 1;
+        let shorthand = |named, negated| {
+            Node::Class(Class {
+                negated,
+                items: vec![ClassItem::Named(named)],
+            })
+        };
+        Ok(match c {
+            '1'..='9' => return Err("Back-references are not supported".to_string()),
+            '{' if self.syntax == Syntax::Basic && branch_start => {
+                return Err("Invalid preceding regular expression".to_string());
+            }
+            'w' => shorthand(NamedClass::Word, false),
+            'W' => shorthand(NamedClass::Word, true),
+            's' => shorthand(NamedClass::Space, false),
+            'S' => shorthand(NamedClass::Space, true),
+            'b' => Node::Assert(Assertion::WordBoundary),
+            'B' => Node::Assert(Assertion::NotWordBoundary),
+            '<' => Node::Assert(Assertion::WordStart),
+            '>' => Node::Assert(Assertion::WordEnd),
+            '`' => Node::Assert(Assertion::LineStart),
+            '\'' => Node::Assert(Assertion::LineEnd),
+            _ => Node::Literal(c),
+        })
+    }
+
+    fn parse_quantifiers(&mut self, mut atom: Node) -> Result<Node, String> {
+        loop {
+            let (min, max) = if self.peek() == Some('*') {
+                self.position += 1;
+                (0, None)
+            } else if self.at_operator('+') {
+                self.skip_operator();
+                (1, None)
+            } else if self.at_operator('?') {
+                self.skip_operator();
+                (0, Some(1))
+            } else if self.at_operator('{') {
+                // An ERE brace that doesn't start a valid interval is a literal
+                if self.syntax == Syntax::Extended && !self.interval_follows() {
+                    return Ok(atom);
+                }
+                self.skip_operator();
+                self.parse_interval()?
+            } else {
+                return Ok(atom);
+            };
+            atom = Node::Repeat {
+                node: Box::new(atom),
+                min,
+                max,
+            };
+        }
+    }
+
+    // Whether a valid "{m,n}" starts here, looking ahead without consuming it. Only
+    // EREs ask: there a brace that doesn't start one is a literal.
+    fn interval_follows(&self) -> bool {
+        // The number starting at index, if any, and the index after it
+        let number_at = |mut index: usize| {
+            let start = index;
+            let mut value: u64 = 0;
+            while let Some(digit) = self.chars.get(index).and_then(|c| c.to_digit(10)) {
+                value = (value * 10 + u64::from(digit)).min(u64::from(u32::MAX));
+                index += 1;
+            }
+            ((index > start).then_some(value), index)
+        };
+
+        let (min, index) = number_at(self.position + 1);
+        let (max, index) = match self.chars.get(index) {
+            Some(',') => number_at(index + 1),
+            _ if min.is_none() => return false,
+            _ => (min, index),
+        };
+        self.chars.get(index) == Some(&'}') && max.is_none_or(|max| min.unwrap_or(0) <= max)
+    }
+
+    // "m}", "m,}", ",n}" or "m,n}", after the opening brace
+    fn parse_interval(&mut self) -> Result<(u32, Option<u32>), String> {
+        let invalid = || "Invalid content of \\{\\}".to_string();
+        let min = self.parse_number();
+        let max = if self.peek() == Some(',') {
+            self.position += 1;
+            self.parse_number()
+        } else {
+            Some(min.ok_or_else(invalid)?)
+        };
+        if !self.at_operator('}') {
+            return Err(if self.peek().is_none() {
+                "Unmatched \\{".to_string()
+            } else {
+                invalid()
+            });
+        }
+        self.skip_operator();
+
+        let min = min.unwrap_or(0);
+        if max.is_some_and(|max| max < min) {
+            return Err(invalid());
+        }
+        if min > DUP_MAX || max.is_some_and(|max| max > DUP_MAX) {
+            return Err("Regular expression too big".to_string());
+        }
+        Ok((min, max))
+    }
+
+    fn parse_number(&mut self) -> Option<u32> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let digits: String = self.chars[start..self.position].iter().collect();
+        // Too many digits is as good as too big
+        digits
+            .parse()
+            .ok()
+            .or((!digits.is_empty()).then_some(u32::MAX))
+    }
+
+    // After '[': items up to the closing ']', which is literal when it comes first.
+    // Backslashes are ordinary inside brackets.
+    fn parse_bracket(&mut self) -> Result<Class, String> {
+        let unmatched = || "Unmatched [, [^, [:, [., or [=".to_string();
+        let open = self.position;
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.position += 1;
+        }
+
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.peek().ok_or_else(unmatched)?;
+            self.position += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+
+            let low = if c == '[' && matches!(self.peek(), Some(':' | '=' | '.')) {
+                let kind = self.peek().unwrap_or(':');
+                self.position += 1;
+                let name = self.bracket_name(kind).ok_or_else(unmatched)?;
+                if kind == ':' {
+                    let named = NamedClass::from_name(&name)
+                        .ok_or_else(|| "Invalid character class name".to_string())?;
+                    items.push(ClassItem::Named(named));
+                    continue;
+                }
+                // [=c=] and [.c.] name single characters
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err("Invalid collation character".to_string()),
+                }
+            } else {
+                c
+            };
+
+            if self.peek() == Some('-') && self.peek_at(1).is_some_and(|c| c != ']') {
+                self.position += 1;
+                let high = self.peek().ok_or_else(unmatched)?;
+                self.position += 1;
+                let high = if high == '[' && self.peek() == Some('.') {
+                    self.position += 1;
+                    let name = self.bracket_name('.').ok_or_else(unmatched)?;
+                    name.chars().next().ok_or_else(unmatched)?
+                } else {
+                    high
+                };
+                if high < low {
+                    return Err("Invalid range end".to_string());
+                }
+                items.push(ClassItem::Range(low, high));
+            } else {
+                items.push(ClassItem::Char(low));
+            }
+        }
+
+        // "[:space:]" is almost always a mistake for "[[:space:]]". GNU grep's message
+        // names [:space:] whatever the class.
+        let body: String = self.chars[open..self.position - 1].iter().collect();
+        if body.len() > 2 && body.starts_with(':') && body.ends_with(':') {
+            return Err("character class syntax is [[:space:]], not [:space:]".to_string());
+        }
+        Ok(Class { negated, items })
+    }
+
+    // The name in "[:name:]" and the like, up to the closing "kind]"
+    fn bracket_name(&mut self, kind: char) -> Option<String> {
+        let start = self.position;
+        while self.position + 1 < self.chars.len() {
+            if self.chars[self.position] == kind && self.chars[self.position + 1] == ']' {
+                let name = self.chars[start..self.position].iter().collect();
+                self.position += 2;
+                return Some(name);
+            }
+            self.position += 1;
+        }
+        None
+    }
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+    classes: Vec<Class>,
+    ignore_case: bool,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.program.len() >= MAX_PROGRAM {
+            return Err("Regular expression too big".to_string());
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    // Splits and jumps are emitted before their targets are known and patched after
+    fn patch(&mut self, at: usize, inst: Inst) {
+        self.program[at] = inst;
+    }
+
+    fn compile(&mut self, node: Node) -> Result<(), String> {
+        match node {
+            Node::Literal(c) => {
+                let c = if self.ignore_case { fold_case(c) } else { c };
+                self.emit(Inst::Char(c))?;
+            }
+            Node::Any => {
+                self.emit(Inst::Any)?;
+            }
+            Node::Class(class) => {
+                self.classes.push(class);
+                self.emit(Inst::Class(self.classes.len() - 1))?;
+            }
+            Node::Assert(assertion) => {
+                self.emit(Inst::Assert(assertion))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            }
+            Node::Alternate(mut branches) => {
+                // split L1, next; L1: first; jmp end; next: split L2, ... ; last
+                let last = branches.pop();
+                let mut jumps = Vec::new();
+                for branch in branches {
+                    let split = self.emit(Inst::Jmp(0))?;
+                    self.compile(branch)?;
+                    jumps.push(self.emit(Inst::Jmp(0))?);
+                    let next = self.program.len();
+                    self.patch(split, Inst::Split(split + 1, next));
+                }
+                if let Some(last) = last {
+                    self.compile(last)?;
+                }
+                let end = self.program.len();
+                for jump in jumps {
+                    self.patch(jump, Inst::Jmp(end));
+                }
+            }
+            Node::Repeat { node, min, max } => self.compile_repeat(*node, min, max)?,
+        }
+        Ok(())
+    }
+
+    // The node min times, then either a loop or up to max - min optional copies.
+    // Each copy is compiled afresh from the syntax tree.
+    fn compile_repeat(&mut self, node: Node, min: u32, max: Option<u32>) -> Result<(), String> {
+        for _ in 0..min {
+            self.compile(node.clone())?;
+        }
+        match max {
+            None => {
+                // loop: split body, end; body; jmp loop
+                let split = self.emit(Inst::Jmp(0))?;
+                self.compile(node.clone())?;
+                self.emit(Inst::Jmp(split))?;
+                let end = self.program.len();
+                self.patch(split, Inst::Split(split + 1, end));
+            }
+            Some(max) => {
+                // Nested optionals: split body, end; body; split body, end; ...
+                let mut splits = Vec::new();
+                for _ in min..max {
+                    splits.push(self.emit(Inst::Jmp(0))?);
+                    self.compile(node.clone())?;
+                }
+                let end = self.program.len();
+                for split in splits {
+                    self.patch(split, Inst::Split(split + 1, end));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn options(syntax: Syntax) -> RegexOptions {
+        RegexOptions {
+            syntax,
+            ignore_case: false,
+            whole_words: false,
+            whole_lines: false,
+        }
+    }
+
+    fn compile(pattern: &str, options: &RegexOptions) -> Result<Regex, String> {
+        Regex::new(&[pattern.to_string()], options)
+    }
+
+    // The leftmost-longest match of pattern in text
+    fn find<'t>(pattern: &str, syntax: Syntax, text: &'t str) -> Option<&'t str> {
+        let regex = compile(pattern, &options(syntax)).unwrap();
+        regex.find_at(text, 0).map(|(start, end)| &text[start..end])
+    }
+
+    fn error(pattern: &str, syntax: Syntax) -> String {
+        compile(pattern, &options(syntax)).err().unwrap()
+    }
+
+    #[test]
+    fn operators_need_a_backslash_in_bres_and_none_in_eres() {
+        assert_eq!(find("a+", Syntax::Basic, "aaa a+"), Some("a+"));
+        assert_eq!(find("a\\+", Syntax::Basic, "aaa a+"), Some("aaa"));
+        assert_eq!(find("a+", Syntax::Extended, "aaa a+"), Some("aaa"));
+        assert_eq!(find("a\\+", Syntax::Extended, "aaa a+"), Some("a+"));
+
+        assert_eq!(find("\\(ab\\)*c", Syntax::Basic, "xababc"), Some("ababc"));
+        assert_eq!(find("(ab)*c", Syntax::Basic, "x(ab))c"), Some("(ab))c"));
+        assert_eq!(find("(ab)*c", Syntax::Extended, "xababc"), Some("ababc"));
+        assert_eq!(find("\\(ab\\)", Syntax::Extended, "ab (ab)"), Some("(ab)"));
+
+        assert_eq!(find("x\\|y", Syntax::Basic, "--y"), Some("y"));
+        assert_eq!(find("x|y", Syntax::Basic, "y x|y"), Some("x|y"));
+        assert_eq!(find("x|y", Syntax::Extended, "--y"), Some("y"));
+        assert_eq!(find("a?", Syntax::Basic, "a?"), Some("a?"));
+        assert_eq!(find("a\\?b", Syntax::Basic, "b"), Some("b"));
+        assert_eq!(find("a.c", Syntax::Fixed, "abc a.c"), Some("a.c"));
+    }
+
+    #[test]
+    fn anchors_and_stars_that_stand_for_themselves() {
+        // In a BRE, ^ and $ are anchors only at the ends, and a leading * is literal
+        assert_eq!(find("a^b$c", Syntax::Basic, "a^b$c"), Some("a^b$c"));
+        assert_eq!(find("^ab$", Syntax::Basic, "ab"), Some("ab"));
+        assert_eq!(find("*a", Syntax::Basic, "b*a"), Some("*a"));
+        assert_eq!(find("^*", Syntax::Basic, "*x"), Some("*"));
+        assert_eq!(find("a^b", Syntax::Extended, "a^b"), None);
+        assert_eq!(find("\\<is\\>", Syntax::Basic, "this is"), Some("is"));
+    }
+
+    #[test]
+    fn intervals() {
+        assert_eq!(find("a\\{2,3\\}", Syntax::Basic, "aaaa"), Some("aaa"));
+        assert_eq!(find("a{2}", Syntax::Extended, "aaaa"), Some("aa"));
+        assert_eq!(find("ba{,2}", Syntax::Extended, "baaaa"), Some("baa"));
+        assert_eq!(find("ba{2,}", Syntax::Extended, "ba baaaa"), Some("baaaa"));
+        assert_eq!(find("(ab){2}", Syntax::Extended, "abab"), Some("abab"));
+        assert_eq!(find("a{2}", Syntax::Basic, "aa a{2}"), Some("a{2}"));
+
+        // An ERE brace that doesn't start an interval is a literal
+        assert_eq!(find("a{", Syntax::Extended, "a{"), Some("a{"));
+        assert_eq!(find("a{x}", Syntax::Extended, "a{x}"), Some("a{x}"));
+        assert_eq!(find("a{2,1}", Syntax::Extended, "a{2,1}"), Some("a{2,1}"));
+
+        assert_eq!(
+            error("a\\{2,1\\}", Syntax::Basic),
+            "Invalid content of \\{\\}"
+        );
+        assert_eq!(
+            error("a\\{x\\}", Syntax::Basic),
+            "Invalid content of \\{\\}"
+        );
+        assert_eq!(error("a\\{2", Syntax::Basic), "Unmatched \\{");
+        assert_eq!(
+            error("a\\{40000\\}", Syntax::Basic),
+            "Regular expression too big"
+        );
+        assert_eq!(
+            error("a{40000}", Syntax::Extended),
+            "Regular expression too big"
+        );
+        assert_eq!(
+            error("\\{1\\}", Syntax::Basic),
+            "Invalid preceding regular expression"
+        );
+    }
+
+    #[test]
+    fn bracket_expressions() {
+        assert_eq!(find("[]a]*", Syntax::Basic, "x]a]"), Some(""));
+        assert_eq!(find("[]a]\\+", Syntax::Basic, "x]a]"), Some("]a]"));
+        assert_eq!(find("[^a-c]", Syntax::Basic, "abcd"), Some("d"));
+        assert_eq!(find("[a-]", Syntax::Basic, "x-"), Some("-"));
+        assert_eq!(find("[\\n]", Syntax::Basic, "n\\"), Some("n"));
+        assert_eq!(
+            find("[[:digit:]]+", Syntax::Extended, "ab123c"),
+            Some("123")
+        );
+        assert_eq!(
+            find("[[:alpha:]_]+", Syntax::Extended, "1a_b2"),
+            Some("a_b")
+        );
+        assert_eq!(find("[^[:space:]]+", Syntax::Extended, "  x y"), Some("x"));
+        assert_eq!(find("[[.-.]a]+", Syntax::Extended, "x-a-"), Some("-a-"));
+        assert_eq!(find("[[=e=]]", Syntax::Extended, "xe"), Some("e"));
+        assert_eq!(
+            find("\\w+\\s\\S", Syntax::Extended, "--ab_1 c"),
+            Some("ab_1 c")
+        );
+
+        assert_eq!(error("[a", Syntax::Basic), "Unmatched [, [^, [:, [., or [=");
+        assert_eq!(
+            error("[[:alpha:]", Syntax::Basic),
+            "Unmatched [, [^, [:, [., or [="
+        );
+        assert_eq!(
+            error("[[:nope:]]", Syntax::Basic),
+            "Invalid character class name"
+        );
+        assert_eq!(error("[z-a]", Syntax::Basic), "Invalid range end");
+        assert_eq!(
+            error("[:digit:]", Syntax::Basic),
+            "character class syntax is [[:space:]], not [:space:]"
+        );
+    }
+
+    #[test]
+    fn matches_are_leftmost_then_longest() {
+        assert_eq!(find("a|ab|abc", Syntax::Extended, "xabcd"), Some("abc"));
+        assert_eq!(
+            find("(a|ab)(c|bcd)", Syntax::Extended, "abcd"),
+            Some("abcd")
+        );
+        assert_eq!(find("b+|a", Syntax::Extended, "abbb"), Some("a"));
+        assert_eq!(find("x*", Syntax::Extended, "axx"), Some(""));
+
+        let regex = compile("o+", &options(Syntax::Extended)).unwrap();
+        assert_eq!(regex.find_at("foo boooo", 0), Some((1, 3)));
+        assert_eq!(regex.find_at("foo boooo", 3), Some((5, 9)));
+    }
+
+    #[test]
+    fn context_before_start_counts_for_anchors() {
+        let regex = compile("^a|\\<b", &options(Syntax::Extended)).unwrap();
+        assert_eq!(regex.find_at("aab", 1), None);
+        assert_eq!(regex.find_at("a b", 1), Some((2, 3)));
+    }
+
+    #[test]
+    fn whole_words_and_lines() {
+        let words = RegexOptions {
+            whole_words: true,
+            ..options(Syntax::Basic)
+        };
+        let regex = compile("foo", &words).unwrap();
+        assert_eq!(regex.find_at("foobar foo_x (foo)", 0), Some((14, 17)));
+        assert!(!regex.is_match("foobar"));
+        assert!(regex.is_match("foo"));
+
+        let lines = RegexOptions {
+            whole_lines: true,
+            ..options(Syntax::Extended)
+        };
+        let regex = Regex::new(&["fo*".to_string(), "bar".to_string()], &lines).unwrap();
+        assert!(regex.is_match("fooo"));
+        assert!(regex.is_match("bar"));
+        assert!(!regex.is_match("foo "));
+        assert!(!regex.is_match("a bar"));
+    }
+
+    #[test]
+    fn ignore_case() {
+        let folded = RegexOptions {
+            ignore_case: true,
+            ..options(Syntax::Extended)
+        };
+        let regex = compile("hello [a-c]+", &folded).unwrap();
+        assert_eq!(regex.find_at("say HeLLo ABCd", 0), Some((4, 13)));
+    }
+
+    #[test]
+    fn empty_matches() {
+        // What grep -o steps over: an empty match where the pattern can match nothing
+        let regex = compile("b*", &options(Syntax::Basic)).unwrap();
+        assert_eq!(regex.find_at("abba", 0), Some((0, 0)));
+        assert_eq!(regex.find_at("abba", 1), Some((1, 3)));
+        assert_eq!(regex.find_at("abba", 4), Some((4, 4)));
+
+        let regex = compile("", &options(Syntax::Basic)).unwrap();
+        assert!(regex.is_match(""));
+        assert_eq!(regex.find_at("ab", 1), Some((1, 1)));
+    }
+
+    #[test]
+    fn nested_repetition_stays_linear() {
+        // A backtracking matcher takes exponential time to fail on these
+        let text = "a".repeat(5000);
+        let start = Instant::now();
+        for pattern in ["(a*)*b", "(a|aa)*c", "(a+)+b"] {
+            let regex = compile(pattern, &options(Syntax::Extended)).unwrap();
+            assert!(!regex.is_match(&text));
+            assert_eq!(regex.find_at(&text, 0), None);
+        }
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn refused_patterns() {
+        assert_eq!(error("(a", Syntax::Extended), "Unmatched ( or \\(");
+        assert_eq!(error("\\(a", Syntax::Basic), "Unmatched ( or \\(");
+        assert_eq!(error("a)", Syntax::Extended), "Unmatched ) or \\)");
+        assert_eq!(error("a\\", Syntax::Basic), "Trailing backslash");
+        assert_eq!(
+            error("\\(a\\)\\1", Syntax::Basic),
+            "Back-references are not supported"
+        );
+        assert_eq!(
+            error("(a{1000}){1000}{1000}", Syntax::Extended),
+            "Regular expression too big"
+        );
+    }
+}
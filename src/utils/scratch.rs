@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Throwaway directory trees for tests, made under the temp directory and removed
+// again when dropped. Names carry the process ID so parallel runs don't collide.
+pub struct Scratch {
+    root: PathBuf,
+}
+
+impl Scratch {
+    // Build a tree from relative paths; those ending in '/' are directories and the
+    // rest empty files, with any missing parents created along the way
+    pub fn new(name: &str, paths: &[&str]) -> Scratch {
+        let root = std::env::temp_dir().join(format!("shell{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let scratch = Scratch { root };
+        for path in paths {
+            match path.strip_suffix('/') {
+                Some(dir) => fs::create_dir_all(scratch.path(dir)).unwrap(),
+                None => scratch.write(path, ""),
+            }
+        }
+        scratch
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.root.join(relative)
+    }
+
+    // The path as a command-line argument
+    pub fn arg(&self, relative: &str) -> String {
+        self.path(relative).to_string_lossy().into_owned()
+    }
+
+    pub fn write(&self, relative: &str, contents: &str) {
+        let path = self.path(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    pub fn read(&self, relative: &str) -> String {
+        fs::read_to_string(self.path(relative)).unwrap()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        // Tests may leave directories without write permission behind
+        let _ = make_writable(&self.root);
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn make_writable(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            make_writable(&entry.path())?;
+        }
+    }
+    Ok(())
+}
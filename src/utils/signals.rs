@@ -0,0 +1,40 @@
+use crate::utils::sys;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Ctrl+C during a builtin that runs until the user stops it, such as tail -f, has
+// to end that command and not the shell around it. While such a command holds an
+// InterruptGuard, SIGINT only raises a flag for it to poll; dropping the guard
+// puts the previous disposition back.
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn record_interrupt(_signal: c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+pub struct InterruptGuard {
+    previous: sys::SigHandler,
+}
+
+impl InterruptGuard {
+    pub fn new() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        let handler = record_interrupt as extern "C" fn(c_int) as sys::SigHandler;
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+        let previous = unsafe { sys::signal(sys::SIGINT, handler) };
+        InterruptGuard { previous }
+    }
+
+    // Whether Ctrl+C was pressed since the guard was taken
+    pub fn interrupted(&self) -> bool {
+        INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        // SAFETY: previous is the disposition signal returned, so it is valid to restore
+        unsafe { sys::signal(sys::SIGINT, self.previous) };
+    }
+}
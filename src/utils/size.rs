@@ -0,0 +1,33 @@
+// Counts with the multiplier suffixes coreutils accepts, as in "head -c 10K":
+// b is 512, K (or k, KiB) 1024, KB (or kB) 1000, and so on up through M, G, T, P and E.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let digits_end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (digits, suffix) = text.split_at(digits_end);
+    let number: u64 = digits.parse().ok()?;
+
+    let multiplier = match suffix {
+        "" => 1,
+        "b" => 512,
+        _ => {
+            let mut chars = suffix.chars();
+            let power = match chars.next()? {
+                'k' | 'K' => 1,
+                'M' => 2,
+                'G' => 3,
+                'T' => 4,
+                'P' => 5,
+                'E' => 6,
+                _ => return None,
+            };
+            let base: u64 = match chars.as_str() {
+                "" | "iB" => 1024,
+                "B" => 1000,
+                _ => return None,
+            };
+            base.checked_pow(power)?
+        }
+    };
+    number.checked_mul(multiplier)
+}
@@ -0,0 +1,214 @@
+// Declarations for the few libc calls that std does not wrap
+use std::ffi::CString;
+use std::fs::File;
+use std::io;
+use std::os::fd::FromRawFd;
+use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_ushort};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::ExitStatus;
+
+#[repr(C)]
+#[derive(Default)]
+pub struct WinSize {
+    pub ws_row: c_ushort,
+    pub ws_col: c_ushort,
+    pub ws_xpixel: c_ushort,
+    pub ws_ypixel: c_ushort,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub const TIOCGWINSZ: c_ulong = 0x5413;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub const TIOCGWINSZ: c_ulong = 0x4008_7468;
+
+// Open flags for opening without blocking on a FIFO or taking over a terminal
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub const O_NONBLOCK: c_int = 0o4000;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub const O_NOCTTY: c_int = 0o400;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub const O_NONBLOCK: c_int = 0x4;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub const O_NOCTTY: c_int = 0x20000;
+
+#[repr(C)]
+struct Timespec {
+    tv_sec: i64,
+    tv_nsec: c_long,
+}
+
+// utimensat's "relative to the working directory" fd, its flag for acting on a
+// symlink itself, and the nanoseconds value that leaves a time alone. These differ
+// between systems.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const AT_FDCWD: c_int = -100;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const AT_SYMLINK_NOFOLLOW: c_int = 0x100;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const UTIME_OMIT: c_long = (1 << 30) - 2;
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const AT_FDCWD: c_int = -2;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const AT_SYMLINK_NOFOLLOW: c_int = 0x20;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const UTIME_OMIT: c_long = -2;
+
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+const AT_FDCWD: c_int = -100;
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+const AT_SYMLINK_NOFOLLOW: c_int = 0x200;
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+const UTIME_OMIT: c_long = -2;
+
+#[cfg(target_os = "netbsd")]
+const AT_FDCWD: c_int = -100;
+#[cfg(target_os = "netbsd")]
+const AT_SYMLINK_NOFOLLOW: c_int = 0x200;
+#[cfg(target_os = "netbsd")]
+const UTIME_OMIT: c_long = (1 << 30) - 2;
+
+#[cfg(target_os = "openbsd")]
+const AT_FDCWD: c_int = -100;
+#[cfg(target_os = "openbsd")]
+const AT_SYMLINK_NOFOLLOW: c_int = 0x02;
+#[cfg(target_os = "openbsd")]
+const UTIME_OMIT: c_long = -1;
+
+const W_OK: c_int = 2;
+const F_SETFD: c_int = 2;
+const FD_CLOEXEC: c_int = 1;
+const EINTR: i32 = 4;
+// rename(2) fails with this when source and destination are on different filesystems
+pub const EXDEV: i32 = 18;
+
+pub const SIGINT: c_int = 2;
+pub const SIGPIPE: c_int = 13;
+
+// A signal disposition: a handler's address, or SIG_DFL (0) / SIG_IGN (1)
+pub type SigHandler = usize;
+pub const SIG_DFL: SigHandler = 0;
+
+unsafe extern "C" {
+    pub fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    pub fn getuid() -> c_uint;
+    pub fn signal(signum: c_int, handler: SigHandler) -> SigHandler;
+    fn access(path: *const c_char, mode: c_int) -> c_int;
+    fn fork() -> c_int;
+    fn pipe(fds: *mut c_int) -> c_int;
+    fn fcntl(fd: c_int, command: c_int, ...) -> c_int;
+    fn waitpid(pid: c_int, status: *mut c_int, options: c_int) -> c_int;
+    fn umask(mask: c_uint) -> c_uint;
+    fn mkfifo(path: *const c_char, mode: c_uint) -> c_int;
+    fn utimensat(dirfd: c_int, path: *const c_char, times: *const Timespec, flags: c_int) -> c_int;
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+// Whether the current user may write to path, as the kernel would decide it
+// (root passes regardless of the mode bits)
+pub fn is_writable(path: &Path) -> bool {
+    let Ok(path) = c_path(path) else {
+        return false;
+    };
+    // SAFETY: path is a valid NUL-terminated string for the duration of the call
+    unsafe { access(path.as_ptr(), W_OK) == 0 }
+}
+
+// The process umask. There is no way to read it without setting it, so put it back.
+pub fn current_umask() -> u32 {
+    // SAFETY: umask only swaps an integer in the process state
+    unsafe {
+        let mask = umask(0o022);
+        umask(mask);
+        mask
+    }
+}
+
+pub fn make_fifo(path: &Path, mode: u32) -> io::Result<()> {
+    let path = c_path(path)?;
+    // SAFETY: path is a valid NUL-terminated string for the duration of the call
+    if unsafe { mkfifo(path.as_ptr(), mode) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+// Set access and/or modification times as (seconds, nanoseconds); None leaves that
+// time alone. Unlike File::set_times this works on symlinks themselves when !follow.
+pub fn set_times(
+    path: &Path,
+    accessed: Option<(i64, i64)>,
+    modified: Option<(i64, i64)>,
+    follow: bool,
+) -> io::Result<()> {
+    let path = c_path(path)?;
+    let spec = |time: Option<(i64, i64)>| match time {
+        Some((tv_sec, tv_nsec)) => Timespec {
+            tv_sec,
+            tv_nsec: tv_nsec as c_long,
+        },
+        None => Timespec {
+            tv_sec: 0,
+            tv_nsec: UTIME_OMIT,
+        },
+    };
+    let times = [spec(accessed), spec(modified)];
+    let flags = if follow { 0 } else { AT_SYMLINK_NOFOLLOW };
+    // SAFETY: path is NUL-terminated and times points at two timespecs, as utimensat expects
+    if unsafe { utimensat(AT_FDCWD, path.as_ptr(), times.as_ptr(), flags) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+// A pipe as its (read, write) ends. Both are close-on-exec, so programs started
+// while it is open don't keep it alive.
+pub fn make_pipe() -> io::Result<(File, File)> {
+    let mut fds = [0 as c_int; 2];
+    // SAFETY: fds has room for the two descriptors pipe writes
+    if unsafe { pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: pipe succeeded, so both descriptors are open and owned by nothing else
+    let ends = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    for fd in fds {
+        // SAFETY: fd is open, and F_SETFD only changes its flags
+        if unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(ends)
+}
+
+// Split the process in two, like fork(2): 0 in the child, the child's pid in the
+// parent. Only safe while the shell runs a single thread.
+pub fn fork_process() -> io::Result<c_int> {
+    // SAFETY: the shell has no other threads, so the child gets consistent state
+    match unsafe { fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        pid => Ok(pid),
+    }
+}
+
+// Wait for a forked child to end
+pub fn wait_for(pid: c_int) -> io::Result<ExitStatus> {
+    let mut status: c_int = 0;
+    loop {
+        // SAFETY: status is a valid place for waitpid to store the child's status
+        if unsafe { waitpid(pid, &mut status, 0) } == pid {
+            return Ok(ExitStatus::from_raw(status));
+        }
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(EINTR) {
+            return Err(error);
+        }
+    }
+}
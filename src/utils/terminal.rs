@@ -0,0 +1,151 @@
+use crate::utils::sys;
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::process::Stdio;
+
+// Where a command's stdout ends up. Builtins like ls change their layout depending on
+// whether they write to a terminal, a pipe buffer or a redirected file.
+pub trait OutputStream: Write {
+    fn is_tty(&self) -> bool {
+        false
+    }
+
+    // Width of the terminal in columns, if this stream is one
+    fn columns(&self) -> Option<usize> {
+        None
+    }
+
+    // The stream as a program's stdout, when it is a file descriptor the program can
+    // write to directly instead of through the shell
+    fn as_stdio(&mut self) -> Option<Stdio> {
+        None
+    }
+}
+
+impl OutputStream for io::Stdout {
+    fn is_tty(&self) -> bool {
+        self.is_terminal()
+    }
+
+    fn columns(&self) -> Option<usize> {
+        window_columns(self.as_raw_fd())
+    }
+
+    fn as_stdio(&mut self) -> Option<Stdio> {
+        // What was printed before has to come out before the program's output
+        self.flush().ok()?;
+        Some(Stdio::inherit())
+    }
+}
+
+impl OutputStream for File {
+    fn is_tty(&self) -> bool {
+        self.is_terminal()
+    }
+
+    fn columns(&self) -> Option<usize> {
+        window_columns(self.as_raw_fd())
+    }
+
+    fn as_stdio(&mut self) -> Option<Stdio> {
+        self.try_clone().ok().map(Stdio::from)
+    }
+}
+
+// In-memory pipe buffers are never terminals
+impl<T> OutputStream for io::Cursor<T> where io::Cursor<T>: Write {}
+
+impl OutputStream for Vec<u8> {}
+
+fn window_columns(fd: RawFd) -> Option<usize> {
+    let mut size = sys::WinSize::default();
+    // SAFETY: TIOCGWINSZ only writes a winsize struct through the pointer we pass
+    let result = unsafe { sys::ioctl(fd, sys::TIOCGWINSZ, &mut size as *mut sys::WinSize) };
+    if result == 0 && size.ws_col > 0 {
+        Some(size.ws_col as usize)
+    } else {
+        None
+    }
+}
+
+// Line width for column layouts: the terminal size, then $COLUMNS, then 80
+pub fn line_width(stdout: &dyn OutputStream) -> usize {
+    stdout
+        .columns()
+        .or_else(|| {
+            std::env::var("COLUMNS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|&width| width > 0)
+        })
+        .unwrap_or(80)
+}
+
+// Number of terminal cells a string occupies: wide CJK and emoji take two,
+// combining marks and other zero-width characters take none
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+fn char_width(c: char) -> usize {
+    let code = c as u32;
+    if c.is_control() {
+        return 0;
+    }
+
+    const ZERO_WIDTH: [(u32, u32); 9] = [
+        (0x0300, 0x036F),
+        (0x0483, 0x0489),
+        (0x0591, 0x05BD),
+        (0x1AB0, 0x1AFF),
+        (0x1DC0, 0x1DFF),
+        (0x200B, 0x200F),
+        (0x20D0, 0x20FF),
+        (0xFE00, 0xFE0F),
+        (0xFE20, 0xFE2F),
+    ];
+    const WIDE: [(u32, u32); 15] = [
+        (0x1100, 0x115F),
+        (0x2E80, 0x303E),
+        (0x3041, 0x33FF),
+        (0x3400, 0x4DBF),
+        (0x4E00, 0x9FFF),
+        (0xA000, 0xA4CF),
+        (0xAC00, 0xD7A3),
+        (0xF900, 0xFAFF),
+        (0xFE30, 0xFE4F),
+        (0xFF00, 0xFF60),
+        (0xFFE0, 0xFFE6),
+        (0x1F300, 0x1F64F),
+        (0x1F900, 0x1F9FF),
+        (0x20000, 0x2FFFD),
+        (0x30000, 0x3FFFD),
+    ];
+
+    let in_ranges =
+        |ranges: &[(u32, u32)]| ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(&code));
+    if in_ranges(&ZERO_WIDTH) {
+        0
+    } else if in_ranges(&WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_widths() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("한글"), 4);
+        // A combining accent adds nothing to the letter before it
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("a\tb\u{7}"), 2);
+        assert_eq!(display_width("🦀x"), 3);
+    }
+}
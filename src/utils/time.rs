@@ -0,0 +1,1044 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// std can't turn a SystemTime into local wall-clock time, so we read the zone
+// ourselves: a TZif file (/etc/localtime or $TZ) plus its POSIX TZ footer rule
+// for instants past the last recorded transition.
+pub struct TimeZone {
+    transitions: Vec<i64>, // UTC instants where the local time type changes
+    type_indices: Vec<u8>, // local time type in effect from each transition
+    types: Vec<LocalType>,
+    rule: Option<PosixRule>,
+}
+
+#[derive(Clone)]
+struct LocalType {
+    offset: i32, // seconds east of UTC
+    is_dst: bool,
+}
+
+// A POSIX TZ string such as "EST5EDT,M3.2.0,M11.1.0"
+struct PosixRule {
+    std_offset: i32, // seconds east of UTC
+    dst: Option<DstRule>,
+}
+
+struct DstRule {
+    offset: i32,
+    start: (RuleDate, i32), // date and seconds after local midnight
+    end: (RuleDate, i32),
+}
+
+enum RuleDate {
+    Julian(u16),              // Jn: 1..=365, February 29th never counted
+    ZeroBased(u16),           // n: 0..=365, leap days counted
+    MonthWeekDay(u8, u8, u8), // Mm.w.d
+}
+
+// A broken-down local time
+#[derive(Clone, Copy, Debug)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32, // 1..=12
+    pub day: u32,   // 1..=31
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanosecond: u32,
+    pub offset: i32, // seconds east of UTC
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+impl TimeZone {
+    pub fn utc() -> Self {
+        TimeZone {
+            transitions: Vec::new(),
+            type_indices: Vec::new(),
+            types: vec![LocalType {
+                offset: 0,
+                is_dst: false,
+            }],
+            rule: None,
+        }
+    }
+
+    // The session's local zone: $TZ if set, otherwise /etc/localtime, otherwise UTC
+    pub fn local() -> &'static TimeZone {
+        static LOCAL: OnceLock<TimeZone> = OnceLock::new();
+        LOCAL.get_or_init(|| match std::env::var("TZ") {
+            Ok(tz) => Self::from_tz_variable(&tz).unwrap_or_else(Self::utc),
+            Err(_) => Self::from_file(Path::new("/etc/localtime")).unwrap_or_else(Self::utc),
+        })
+    }
+
+    // $TZ may name a zoneinfo file (optionally prefixed with ':') or be a POSIX rule
+    fn from_tz_variable(tz: &str) -> Option<Self> {
+        let name = tz.strip_prefix(':').unwrap_or(tz);
+        if name.is_empty() {
+            return Some(Self::utc());
+        }
+
+        let from_file = if name.starts_with('/') {
+            Self::from_file(Path::new(name))
+        } else if !name.contains("..") {
+            Self::from_file(&Path::new("/usr/share/zoneinfo").join(name))
+        } else {
+            None
+        };
+
+        from_file.or_else(|| {
+            let rule = parse_posix_rule(name)?;
+            Some(TimeZone {
+                rule: Some(rule),
+                ..Self::utc()
+            })
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Option<Self> {
+        Self::parse(&fs::read(path).ok()?)
+    }
+
+    // Parse TZif data (RFC 8536). Version 2+ files carry a second, 64-bit body and a
+    // footer with the rule for future times; version 1 files only have the 32-bit body.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let header = TzifHeader::read(data)?;
+        if header.version == 0 {
+            return header.body(data, 44, 4).map(|(zone, _)| zone);
+        }
+
+        let v1_len = header.body_len(4);
+        let second = data.get(44 + v1_len..)?;
+        let header = TzifHeader::read(second)?;
+        let (mut zone, end) = header.body(second, 44, 8)?;
+
+        // The footer is "\n<POSIX TZ string>\n"
+        let footer = second.get(end..).unwrap_or_default();
+        if let Some(text) = footer
+            .strip_prefix(b"\n")
+            .and_then(|rest| rest.split(|&b| b == b'\n').next())
+            .and_then(|text| std::str::from_utf8(text).ok())
+        {
+            zone.rule = parse_posix_rule(text);
+        }
+        Some(zone)
+    }
+
+    // Seconds east of UTC in effect at the given UTC instant
+    pub fn offset_at(&self, utc: i64) -> i32 {
+        if let Some(&last) = self.transitions.last()
+            && utc < last
+        {
+            let index = self.transitions.partition_point(|&t| t <= utc);
+            return match index {
+                // Before the first transition the first standard-time type applies
+                0 => self.initial_type().offset,
+                _ => self.types[self.type_indices[index - 1] as usize].offset,
+            };
+        }
+
+        if let Some(rule) = &self.rule {
+            return rule.offset_at(utc);
+        }
+
+        match self.type_indices.last() {
+            Some(&index) => self.types[index as usize].offset,
+            None => self.initial_type().offset,
+        }
+    }
+
+    fn initial_type(&self) -> &LocalType {
+        self.types
+            .iter()
+            .find(|t| !t.is_dst)
+            .unwrap_or(&self.types[0])
+    }
+
+    pub fn to_local(&self, time: SystemTime) -> DateTime {
+        let (seconds, nanosecond) = split_time(time);
+        DateTime::from_unix(seconds, nanosecond, self.offset_at(seconds))
+    }
+
+    // The UTC instant of a local wall-clock time given as seconds since the local
+    // epoch. A time skipped or repeated by a DST change gets the offset in effect
+    // just before it.
+    pub fn to_utc(&self, local: i64) -> i64 {
+        let guess = local - self.offset_at(local) as i64;
+        local - self.offset_at(guess) as i64
+    }
+}
+
+struct TzifHeader {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl TzifHeader {
+    fn read(data: &[u8]) -> Option<Self> {
+        if data.get(..4)? != b"TZif" {
+            return None;
+        }
+        let count = |index: usize| -> Option<usize> {
+            let start = 20 + index * 4;
+            Some(u32::from_be_bytes(data.get(start..start + 4)?.try_into().ok()?) as usize)
+        };
+        Some(TzifHeader {
+            version: match data[4] {
+                0 => 0,
+                v => v.saturating_sub(b'0'),
+            },
+            isutcnt: count(0)?,
+            isstdcnt: count(1)?,
+            leapcnt: count(2)?,
+            timecnt: count(3)?,
+            typecnt: count(4)?,
+            charcnt: count(5)?,
+        })
+    }
+
+    fn body_len(&self, time_size: usize) -> usize {
+        self.timecnt * time_size
+            + self.timecnt
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+
+    // Decode the body that starts at `start`; returns the zone and where the body ends
+    fn body(&self, data: &[u8], start: usize, time_size: usize) -> Option<(TimeZone, usize)> {
+        let end = start + self.body_len(time_size);
+        let body = data.get(start..end)?;
+
+        let (times, rest) = body.split_at(self.timecnt * time_size);
+        let (indices, rest) = rest.split_at(self.timecnt);
+        let (types, _) = rest.split_at(self.typecnt * 6);
+
+        let transitions = times
+            .chunks_exact(time_size)
+            .map(|chunk| match time_size {
+                8 => i64::from_be_bytes(chunk.try_into().unwrap()),
+                _ => i32::from_be_bytes(chunk.try_into().unwrap()) as i64,
+            })
+            .collect();
+
+        let types: Vec<LocalType> = types
+            .chunks_exact(6)
+            .map(|chunk| LocalType {
+                offset: i32::from_be_bytes(chunk[..4].try_into().unwrap()),
+                is_dst: chunk[4] != 0,
+            })
+            .collect();
+
+        if types.is_empty() || indices.iter().any(|&i| i as usize >= types.len()) {
+            return None;
+        }
+
+        Some((
+            TimeZone {
+                transitions,
+                type_indices: indices.to_vec(),
+                types,
+                rule: None,
+            },
+            end,
+        ))
+    }
+}
+
+impl PosixRule {
+    fn offset_at(&self, utc: i64) -> i32 {
+        let Some(dst) = &self.dst else {
+            return self.std_offset;
+        };
+
+        // Work out the year in local standard time, then both switch instants in UTC.
+        // The start time is given in standard time and the end time in daylight time.
+        let year = DateTime::from_unix(utc, 0, self.std_offset).year;
+        let start = dst.start.0.day_of(year) * 86_400 + dst.start.1 as i64 - self.std_offset as i64;
+        let end = dst.end.0.day_of(year) * 86_400 + dst.end.1 as i64 - dst.offset as i64;
+
+        let in_dst = if start < end {
+            start <= utc && utc < end
+        } else {
+            // Southern hemisphere: daylight time spans the new year
+            !(end <= utc && utc < start)
+        };
+        if in_dst { dst.offset } else { self.std_offset }
+    }
+}
+
+impl RuleDate {
+    // Days since the epoch of this date in `year`
+    fn day_of(&self, year: i64) -> i64 {
+        let jan1 = days_from_civil(year, 1, 1);
+        match *self {
+            RuleDate::Julian(n) => {
+                let mut day = jan1 + n as i64 - 1;
+                if is_leap_year(year) && n >= 60 {
+                    day += 1;
+                }
+                day
+            }
+            RuleDate::ZeroBased(n) => jan1 + n as i64,
+            RuleDate::MonthWeekDay(month, week, weekday) => {
+                let first = days_from_civil(year, month as u32, 1);
+                // 1970-01-01 was a Thursday (weekday 4)
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day = first + (weekday as i64 - first_weekday).rem_euclid(7);
+                day += (week as i64 - 1) * 7;
+                // Week 5 means the last such weekday of the month
+                while week == 5 && day >= first + days_in_month(year, month as u32) as i64 {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+fn parse_posix_rule(text: &str) -> Option<PosixRule> {
+    let mut rest = text;
+    skip_zone_name(&mut rest)?;
+    let std_offset = -parse_offset(&mut rest)?;
+
+    if rest.is_empty() {
+        return Some(PosixRule {
+            std_offset,
+            dst: None,
+        });
+    }
+
+    skip_zone_name(&mut rest)?;
+    let dst_offset = if rest.is_empty() || rest.starts_with(',') {
+        std_offset + 3600
+    } else {
+        -parse_offset(&mut rest)?
+    };
+
+    // Without explicit dates the US rules are the conventional default
+    let (start, end) = match rest.strip_prefix(',') {
+        Some(dates) => {
+            let (start, end) = dates.split_once(',')?;
+            (parse_rule_date(start)?, parse_rule_date(end)?)
+        }
+        None => (
+            (RuleDate::MonthWeekDay(3, 2, 0), 7200),
+            (RuleDate::MonthWeekDay(11, 1, 0), 7200),
+        ),
+    };
+
+    Some(PosixRule {
+        std_offset,
+        dst: Some(DstRule {
+            offset: dst_offset,
+            start,
+            end,
+        }),
+    })
+}
+
+// Zone abbreviations are either alphabetic ("EST") or quoted ("<+03>")
+fn skip_zone_name(rest: &mut &str) -> Option<()> {
+    let len = if let Some(quoted) = rest.strip_prefix('<') {
+        quoted.find('>')? + 2
+    } else {
+        rest.find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len())
+    };
+    if len < 3 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+// [+-]hh[:mm[:ss]] in seconds, with POSIX's sign (positive means west of UTC)
+fn parse_offset(rest: &mut &str) -> Option<i32> {
+    let sign = match rest.chars().next()? {
+        '-' => {
+            *rest = &rest[1..];
+            -1
+        }
+        '+' => {
+            *rest = &rest[1..];
+            1
+        }
+        _ => 1,
+    };
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit() && c != ':')
+        .unwrap_or(rest.len());
+    let (value, tail) = rest.split_at(end);
+    *rest = tail;
+    Some(sign * parse_hms(value)?)
+}
+
+fn parse_hms(value: &str) -> Option<i32> {
+    let mut seconds = 0;
+    let mut parts = 0;
+    for (index, part) in value.split(':').enumerate() {
+        let number: i32 = part.parse().ok()?;
+        seconds += number * [3600, 60, 1].get(index)?;
+        parts += 1;
+    }
+    (parts > 0).then_some(seconds)
+}
+
+// "M3.2.0/2", "J60" or "59", each with an optional "/time" (default 02:00)
+fn parse_rule_date(text: &str) -> Option<(RuleDate, i32)> {
+    let (date, time) = match text.split_once('/') {
+        Some((date, time)) => {
+            let (sign, time) = match time.strip_prefix('-') {
+                Some(time) => (-1, time),
+                None => (1, time.strip_prefix('+').unwrap_or(time)),
+            };
+            (date, sign * parse_hms(time)?)
+        }
+        None => (text, 7200),
+    };
+
+    let date = if let Some(mwd) = date.strip_prefix('M') {
+        let mut fields = mwd.split('.').map(|f| f.parse::<u8>().ok());
+        let (month, week, weekday) = (fields.next()??, fields.next()??, fields.next()??);
+        if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+            return None;
+        }
+        RuleDate::MonthWeekDay(month, week, weekday)
+    } else if let Some(julian) = date.strip_prefix('J') {
+        RuleDate::Julian(julian.parse().ok()?)
+    } else {
+        RuleDate::ZeroBased(date.parse().ok()?)
+    };
+
+    Some((date, time))
+}
+
+impl DateTime {
+    pub fn from_unix(seconds: i64, nanosecond: u32, offset: i32) -> Self {
+        let local = seconds + offset as i64;
+        let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+        let secs_of_day = local.rem_euclid(86_400) as u32;
+        DateTime {
+            year,
+            month,
+            day,
+            hour: secs_of_day / 3600,
+            minute: secs_of_day % 3600 / 60,
+            second: secs_of_day % 60,
+            nanosecond,
+            offset,
+        }
+    }
+
+    // A small strftime: %Y %m %d %e %H %M %S %N %b %z %y %T %F and %%
+    pub fn format(&self, pattern: &str) -> String {
+        let mut out = String::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => out.push_str(&self.year.to_string()),
+                Some('y') => out.push_str(&format!("{:02}", self.year.rem_euclid(100))),
+                Some('m') => out.push_str(&format!("{:02}", self.month)),
+                Some('d') => out.push_str(&format!("{:02}", self.day)),
+                Some('e') => out.push_str(&format!("{:>2}", self.day)),
+                Some('H') => out.push_str(&format!("{:02}", self.hour)),
+                Some('M') => out.push_str(&format!("{:02}", self.minute)),
+                Some('S') => out.push_str(&format!("{:02}", self.second)),
+                Some('N') => out.push_str(&format!("{:09}", self.nanosecond)),
+                Some('b') => out.push_str(MONTHS[self.month as usize - 1]),
+                Some('F') => out.push_str(&self.format("%Y-%m-%d")),
+                Some('T') => out.push_str(&self.format("%H:%M:%S")),
+                Some('z') => {
+                    let sign = if self.offset < 0 { '-' } else { '+' };
+                    let offset = self.offset.unsigned_abs();
+                    out.push_str(&format!(
+                        "{}{:02}{:02}",
+                        sign,
+                        offset / 3600,
+                        offset % 3600 / 60
+                    ));
+                }
+                Some('%') => out.push('%'),
+                Some(other) => {
+                    out.push('%');
+                    out.push(other);
+                }
+                None => out.push('%'),
+            }
+        }
+        out
+    }
+}
+
+// A relative item of a date string: "3 days", "2 hours ago"
+enum Relative {
+    Months(i64),
+    Days(i64),
+    Seconds(i64),
+}
+
+// Parse a date the way touch -d and friends take one, relative to now (seconds and
+// nanoseconds since the epoch). Understood, case-insensitively:
+//
+//     @1700000000.5                      seconds since the epoch
+//     2024-03-01, 2024-03-01 14:30:00    calendar date and time of day, either optional
+//     2024-03-01T14:30Z, 14:30 +0100     with a zone: Z, UTC, GMT or a numeric offset
+//     now, today, yesterday, tomorrow
+//     3 days ago, +2 hours, next week    relative items, added last
+//
+// A date without a time means midnight; a time without a date means today.
+pub fn parse_date(text: &str, now: (i64, u32), zone: &TimeZone) -> Option<(i64, u32)> {
+    let text = text.trim().to_ascii_lowercase();
+    if let Some(seconds) = text.strip_prefix('@') {
+        return parse_epoch(seconds);
+    }
+
+    let mut date = None;
+    let mut time = None;
+    let mut offset = None;
+    let mut relative = Vec::new();
+
+    let mut words = text.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        match word {
+            "now" | "today" => {}
+            "yesterday" => relative.push(Relative::Days(-1)),
+            "tomorrow" => relative.push(Relative::Days(1)),
+            "utc" | "gmt" | "z" => offset = Some(0),
+            "ago" => match relative.last_mut()? {
+                Relative::Months(n) | Relative::Days(n) | Relative::Seconds(n) => *n = -*n,
+            },
+            _ => {
+                // A date may carry its time after a 'T', and a time its zone
+                let (day, rest) = match parse_ymd(word) {
+                    Some(ymd) => (Some(ymd), ""),
+                    None => match word.split_once('t') {
+                        Some((day, rest)) if parse_ymd(day).is_some() => (parse_ymd(day), rest),
+                        _ => (None, word),
+                    },
+                };
+                if let Some(day) = day {
+                    if date.replace(day).is_some() {
+                        return None;
+                    }
+                    if rest.is_empty() {
+                        continue;
+                    }
+                }
+                if let Some((hms, zone_offset)) = parse_time_of_day(rest) {
+                    if time.replace(hms).is_some() {
+                        return None;
+                    }
+                    offset = zone_offset.or(offset);
+                    continue;
+                }
+                if time.is_some()
+                    && offset.is_none()
+                    && let Some(zone_offset) = parse_zone_offset(word)
+                {
+                    offset = Some(zone_offset);
+                    continue;
+                }
+
+                // "3 days", "+2 hours", "next week", or a bare unit meaning one of it
+                let (count, unit) = match word {
+                    "next" => (1, words.next()?),
+                    "last" => (-1, words.next()?),
+                    _ => match word.parse::<i64>() {
+                        Ok(count) => (count, words.next()?),
+                        Err(_) => (1, word),
+                    },
+                };
+                relative.push(parse_unit(unit, count)?);
+            }
+        }
+    }
+
+    let local_now = DateTime::from_unix(now.0, now.1, zone.offset_at(now.0));
+    let (mut year, mut month, day) =
+        date.unwrap_or((local_now.year, local_now.month, local_now.day));
+    let (hour, minute, second, nanosecond) = match (date, time) {
+        (_, Some(time)) => time,
+        (Some(_), None) => (0, 0, 0, 0),
+        (None, None) => (
+            local_now.hour,
+            local_now.minute,
+            local_now.second,
+            local_now.nanosecond,
+        ),
+    };
+
+    // Months and days move along the calendar, so "1 month" from January 31st is
+    // March 3rd or 2nd, the way date(1) counts it
+    let (mut days, mut seconds) = (0, 0);
+    for item in relative {
+        match item {
+            Relative::Months(n) => {
+                let months = year * 12 + month as i64 - 1 + n;
+                year = months.div_euclid(12);
+                month = months.rem_euclid(12) as u32 + 1;
+            }
+            Relative::Days(n) => days += n,
+            Relative::Seconds(n) => seconds += n,
+        }
+    }
+
+    let local = (days_from_civil(year, month, day) + days) * 86_400
+        + (hour * 3600 + minute * 60 + second) as i64;
+    let utc = match offset {
+        Some(offset) => local - offset as i64,
+        None => zone.to_utc(local),
+    };
+    Some((utc + seconds, nanosecond))
+}
+
+// "1700000000" or "-1.25": whole seconds and a fraction, as (seconds, nanoseconds)
+fn parse_epoch(text: &str) -> Option<(i64, u32)> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let negative = whole.starts_with('-');
+    let seconds: i64 = whole.parse().ok()?;
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits: String = fraction
+        .chars()
+        .chain("000000000".chars())
+        .take(9)
+        .collect();
+    let nanos: u32 = digits.parse().ok()?;
+    match (negative, nanos) {
+        (true, 1..) => Some((seconds - 1, 1_000_000_000 - nanos)),
+        _ => Some((seconds, nanos)),
+    }
+}
+
+// "2024-03-01"
+fn parse_ymd(text: &str) -> Option<(i64, u32, u32)> {
+    let mut parts = text.splitn(3, '-');
+    let year: i64 = parts.next().filter(|p| p.len() >= 4)?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    let valid = (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day);
+    valid.then_some((year, month, day))
+}
+
+// "14:30", "14:30:05.25", "14:30Z", "14:30:05+01:00"; the zone is seconds east of UTC
+type TimeOfDay = (u32, u32, u32, u32);
+
+fn parse_time_of_day(text: &str) -> Option<(TimeOfDay, Option<i32>)> {
+    let zone_start = text.find(['z', '+', '-']).unwrap_or(text.len());
+    let (hms, zone) = text.split_at(zone_start);
+    let offset = match zone {
+        "" => None,
+        "z" => Some(0),
+        _ => Some(parse_zone_offset(zone)?),
+    };
+
+    let mut parts = hms.splitn(3, ':');
+    let hour: u32 = parts.next()?.parse().ok()?;
+    let minute: u32 = parts.next()?.parse().ok()?;
+    let (second, nanosecond) = match parts.next() {
+        Some(seconds) => {
+            let (second, nanos) = parse_epoch(seconds)?;
+            (u32::try_from(second).ok()?, nanos)
+        }
+        None => (0, 0),
+    };
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    Some(((hour, minute, second, nanosecond), offset))
+}
+
+// "+0100", "-05:30", "+02"
+fn parse_zone_offset(text: &str) -> Option<i32> {
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits = text[1..].replace(':', "");
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (digits[..2].parse().ok()?, digits[2..].parse().ok()?),
+        _ => return None,
+    };
+    (hours <= 24 && minutes < 60).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn parse_unit(unit: &str, count: i64) -> Option<Relative> {
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+    Some(match unit {
+        "sec" | "second" => Relative::Seconds(count),
+        "min" | "minute" => Relative::Seconds(count * 60),
+        "hour" => Relative::Seconds(count * 3600),
+        "day" => Relative::Days(count),
+        "week" => Relative::Days(count * 7),
+        "fortnight" => Relative::Days(count * 14),
+        "month" => Relative::Months(count),
+        "year" => Relative::Months(count * 12),
+        _ => return None,
+    })
+}
+
+// Whole seconds since the epoch (negative before 1970) and the nanosecond part
+pub fn split_time(time: SystemTime) -> (i64, u32) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            match d.subsec_nanos() {
+                0 => (-(d.as_secs() as i64), 0),
+                nanos => (-(d.as_secs() as i64) - 1, 1_000_000_000 - nanos),
+            }
+        }
+    }
+}
+
+// Whether ls shows a file time as recent: within the last six months (half an average
+// Gregorian year) and not in the future. Both are (seconds, nanoseconds) since the epoch.
+pub fn is_recent(time: (i64, u32), now: (i64, u32)) -> bool {
+    const SIX_MONTHS: i64 = 31_556_952 / 2;
+    (now.0 - SIX_MONTHS, now.1) < time && time < now
+}
+
+pub fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 to (year, month, day), after Howard Hinnant's algorithm
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// The inverse of civil_from_days
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EST: i32 = -5 * 3600;
+    const EDT: i32 = -4 * 3600;
+
+    // DST in New York in 2023, as UTC instants
+    const START_2023: i64 = 1_678_604_400; // 2023-03-12 07:00Z
+    const END_2023: i64 = 1_699_164_000; // 2023-11-05 06:00Z
+    const TRANSITIONS: [(i64, u8); 2] = [(START_2023, 1), (END_2023, 0)];
+    const TYPES: [(i32, bool); 2] = [(EST, false), (EDT, true)];
+
+    // One TZif data block: header and body, with times of time_size bytes
+    fn tzif_block(
+        version: u8,
+        transitions: &[(i64, u8)],
+        types: &[(i32, bool)],
+        time_size: usize,
+    ) -> Vec<u8> {
+        let names = b"EST\0EDT\0";
+        let mut data = b"TZif".to_vec();
+        data.push(version);
+        data.extend([0; 15]);
+        for count in [0, 0, 0, transitions.len(), types.len(), names.len()] {
+            data.extend((count as u32).to_be_bytes());
+        }
+        for &(time, _) in transitions {
+            match time_size {
+                8 => data.extend(time.to_be_bytes()),
+                _ => data.extend((time as i32).to_be_bytes()),
+            }
+        }
+        data.extend(transitions.iter().map(|&(_, index)| index));
+        for &(offset, is_dst) in types {
+            data.extend(offset.to_be_bytes());
+            data.push(is_dst as u8);
+            data.push(if is_dst { 4 } else { 0 });
+        }
+        data.extend(names);
+        data
+    }
+
+    fn local_seconds(year: i64, month: u32, day: u32, hour: u32, minute: u32) -> i64 {
+        days_from_civil(year, month, day) * 86_400 + (hour * 3600 + minute * 60) as i64
+    }
+
+    fn rule(text: &str) -> TimeZone {
+        TimeZone {
+            rule: Some(parse_posix_rule(text).unwrap()),
+            ..TimeZone::utc()
+        }
+    }
+
+    // The offset just before and at a UTC instant
+    fn around(zone: &TimeZone, instant: i64) -> (i32, i32) {
+        (zone.offset_at(instant - 1), zone.offset_at(instant))
+    }
+
+    #[test]
+    fn tzif_version_1() {
+        let zone = TimeZone::parse(&tzif_block(0, &TRANSITIONS, &TYPES, 4)).unwrap();
+        assert_eq!(zone.offset_at(0), EST);
+        assert_eq!(around(&zone, START_2023), (EST, EDT));
+        assert_eq!(around(&zone, END_2023), (EDT, EST));
+        // Past the last transition, with no rule, its type stays in effect
+        assert_eq!(zone.offset_at(END_2023 + 365 * 86_400), EST);
+    }
+
+    #[test]
+    fn tzif_version_2_uses_the_64_bit_body_and_the_footer() {
+        // The version 1 body is what old readers see; this one is deliberately wrong
+        let mut data = tzif_block(b'2', &[], &[(3600, false)], 4);
+        data.extend(tzif_block(b'2', &TRANSITIONS, &TYPES, 8));
+        data.extend(b"\nEST5EDT,M3.2.0,M11.1.0\n");
+
+        let zone = TimeZone::parse(&data).unwrap();
+        assert_eq!(around(&zone, START_2023), (EST, EDT));
+        assert_eq!(around(&zone, END_2023), (EDT, EST));
+        // Later years come from the footer rule
+        assert_eq!(around(&zone, 1_899_356_400), (EST, EDT)); // 2030-03-10 07:00Z
+        assert_eq!(around(&zone, 1_919_916_000), (EDT, EST)); // 2030-11-03 06:00Z
+    }
+
+    #[test]
+    fn tzif_garbage_is_refused() {
+        assert!(TimeZone::parse(b"").is_none());
+        assert!(TimeZone::parse(b"TZjf2").is_none());
+
+        let data = tzif_block(0, &TRANSITIONS, &TYPES, 4);
+        assert!(TimeZone::parse(&data[..data.len() - 3]).is_none());
+        // A transition to a type that doesn't exist
+        assert!(TimeZone::parse(&tzif_block(0, &[(0, 2)], &TYPES, 4)).is_none());
+    }
+
+    #[test]
+    fn month_week_day_rules() {
+        let zone = rule("EST5EDT,M3.2.0,M11.1.0");
+        assert_eq!(zone.offset_at(1_705_320_000), EST); // 2024-01-15
+        assert_eq!(zone.offset_at(1_719_835_200), EDT); // 2024-07-01
+        assert_eq!(around(&zone, 1_710_054_000), (EST, EDT)); // 2024-03-10 02:00 EST
+        assert_eq!(around(&zone, 1_730_613_600), (EDT, EST)); // 2024-11-03 02:00 EDT
+
+        // Week 5 is the last such weekday, and "/3" moves the change to 03:00
+        let zone = rule("CET-1CEST,M3.5.0,M10.5.0/3");
+        assert_eq!(around(&zone, 1_711_846_800), (3600, 7200)); // 2024-03-31 01:00Z
+        assert_eq!(around(&zone, 1_729_990_800), (7200, 3600)); // 2024-10-27 01:00Z
+
+        // Without dates the US rules apply
+        assert_eq!(rule("EST5EDT").offset_at(1_719_835_200), EDT);
+    }
+
+    #[test]
+    fn southern_hemisphere_rules_span_the_new_year() {
+        let zone = rule("AEST-10AEDT,M10.1.0,M4.1.0/3");
+        assert_eq!(zone.offset_at(1_705_320_000), 11 * 3600); // January
+        assert_eq!(zone.offset_at(1_719_835_200), 10 * 3600); // July
+        assert_eq!(around(&zone, 1_712_419_200), (11 * 3600, 10 * 3600)); // 2024-04-07 03:00
+        assert_eq!(around(&zone, 1_728_144_000), (10 * 3600, 11 * 3600)); // 2024-10-06 02:00
+    }
+
+    #[test]
+    fn julian_and_zero_based_days() {
+        // J60 is March 1st whether or not the year has a February 29th
+        let zone = rule("AAA0BBB,J60,J300");
+        assert_eq!(around(&zone, 1_709_258_400), (0, 3600)); // 2024-03-01 02:00Z
+        assert_eq!(around(&zone, 1_677_636_000), (0, 3600)); // 2023-03-01 02:00Z
+
+        // Zero-based day 59 is February 29th in a leap year, March 1st otherwise
+        let zone = rule("AAA0BBB,59,300");
+        assert_eq!(around(&zone, 1_709_172_000), (0, 3600)); // 2024-02-29 02:00Z
+        assert_eq!(around(&zone, 1_677_636_000), (0, 3600)); // 2023-03-01 02:00Z
+    }
+
+    #[test]
+    fn posix_offsets_and_names() {
+        assert_eq!(rule("IST-5:30").offset_at(0), 5 * 3600 + 1800);
+        assert_eq!(rule("<+0545>-5:45").offset_at(0), 5 * 3600 + 45 * 60);
+        assert_eq!(rule("<-03>3").offset_at(0), -3 * 3600);
+        assert_eq!(rule("XXX+1:02:03").offset_at(0), -3723);
+        // An explicit daylight offset instead of an hour ahead
+        let zone = rule("LHST-10:30LHDT-11,M10.1.0,M4.1.0");
+        assert_eq!(zone.offset_at(1_705_320_000), 11 * 3600);
+
+        for invalid in [
+            "EST",
+            "E5",
+            "EST5EDT,M13.1.0,M11.1.0",
+            "EST5EDT,M3.6.0,M11.1.0",
+            "EST5EDT,M3.2.0",
+        ] {
+            assert!(parse_posix_rule(invalid).is_none(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn tz_variable() {
+        assert_eq!(TimeZone::from_tz_variable("").unwrap().offset_at(0), 0);
+        assert_eq!(
+            TimeZone::from_tz_variable(":<+03>-3").unwrap().offset_at(0),
+            3 * 3600
+        );
+        assert!(TimeZone::from_tz_variable("../etc/passwd").is_none());
+    }
+
+    #[test]
+    fn local_times_around_changes() {
+        let zone = rule("EST5EDT,M3.2.0,M11.1.0");
+        let noon = local_seconds(2024, 7, 1, 8, 0);
+        assert_eq!(zone.to_utc(noon), 1_719_835_200);
+        let winter = local_seconds(2024, 1, 15, 7, 0);
+        assert_eq!(zone.to_utc(winter), 1_705_320_000);
+        // 01:30 happens twice on 2024-11-03; like date(1), the first one is meant
+        assert_eq!(
+            zone.to_utc(local_seconds(2024, 11, 3, 1, 30)),
+            1_730_611_800
+        );
+
+        let local = DateTime::from_unix(1_710_054_000, 0, zone.offset_at(1_710_054_000));
+        assert_eq!(local.format("%F %T %z"), "2024-03-10 03:00:00 -0400");
+        let local = DateTime::from_unix(1_710_053_999, 0, zone.offset_at(1_710_053_999));
+        assert_eq!(local.format("%F %T %z"), "2024-03-10 01:59:59 -0500");
+    }
+
+    #[test]
+    fn formatting() {
+        let time = DateTime::from_unix(-1, 5, 0);
+        assert_eq!(
+            time.format("%Y-%m-%d %H:%M:%S.%N"),
+            "1969-12-31 23:59:59.000000005"
+        );
+        let time = DateTime::from_unix(1_709_172_000, 0, 19_800);
+        assert_eq!(
+            time.format("%b %e %y %T %z %% %q"),
+            "Feb 29 24 07:30:00 +0530 % %q"
+        );
+    }
+
+    #[test]
+    fn six_month_cutoff() {
+        let now = (1_719_835_200, 500);
+        let six_months = 31_556_952 / 2;
+        assert!(is_recent((now.0 - 60, 0), now));
+        assert!(is_recent((now.0 - six_months, 501), now));
+        assert!(!is_recent((now.0 - six_months, 500), now));
+        assert!(!is_recent((now.0 - six_months - 1, 999_999_999), now));
+        // The future is never recent, not even a nanosecond of it
+        assert!(!is_recent(now, now));
+        assert!(!is_recent((now.0, 501), now));
+        assert!(!is_recent((now.0 + 86_400, 0), now));
+    }
+
+    const NOW: (i64, u32) = (1_719_835_200, 0); // 2024-07-01 12:00:00Z
+
+    fn date(text: &str) -> Option<(i64, u32)> {
+        parse_date(text, NOW, &TimeZone::utc())
+    }
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(date("2024-03-01"), Some((1_709_251_200, 0)));
+        assert_eq!(date("2024-03-01 14:30:00"), Some((1_709_303_400, 0)));
+        assert_eq!(date("2024-03-01T14:30Z"), Some((1_709_303_400, 0)));
+        assert_eq!(date("2024-07-01 14:30 +0100"), Some((1_719_840_600, 0)));
+        assert_eq!(date("14:30:05.25"), Some((1_719_844_205, 250_000_000)));
+        assert_eq!(date("  NOW "), Some(NOW));
+        assert_eq!(date("@1700000000.5"), Some((1_700_000_000, 500_000_000)));
+        assert_eq!(date("@-1.25"), Some((-2, 750_000_000)));
+
+        // Without a zone the date is local time
+        let zone = rule("EST5EDT,M3.2.0,M11.1.0");
+        assert_eq!(
+            parse_date("2024-03-01 14:30", NOW, &zone),
+            Some((1_709_321_400, 0))
+        );
+        assert_eq!(
+            parse_date("2024-07-01 14:30", NOW, &zone),
+            Some((1_719_858_600, 0))
+        );
+    }
+
+    #[test]
+    fn relative_dates() {
+        let seconds = |text| date(text).map(|(seconds, _)| seconds);
+        assert_eq!(seconds("yesterday"), Some(1_719_748_800));
+        assert_eq!(seconds("tomorrow"), Some(1_719_921_600));
+        assert_eq!(seconds("3 days ago"), Some(1_719_576_000));
+        assert_eq!(seconds("+2 hours"), Some(1_719_842_400));
+        assert_eq!(seconds("2 hours ago"), Some(1_719_828_000));
+        assert_eq!(seconds("90 min ago"), Some(1_719_829_800));
+        assert_eq!(seconds("next week"), Some(1_720_440_000));
+        assert_eq!(seconds("last month"), Some(1_717_243_200));
+        assert_eq!(seconds("fortnight"), Some(1_721_044_800));
+        // Months move along the calendar: January 31st plus a month overflows into March
+        assert_eq!(
+            seconds("2024-01-31 1 month"),
+            date("2024-03-02").map(|(seconds, _)| seconds)
+        );
+        assert_eq!(
+            seconds("2024-03-01 1 day ago"),
+            date("2024-02-29").map(|(seconds, _)| seconds)
+        );
+    }
+
+    #[test]
+    fn invalid_dates() {
+        for text in [
+            "2024-02-30",
+            "2023-02-29",
+            "2024-13-01",
+            "24:00",
+            "12:60",
+            "bogus",
+            "3 parsecs",
+            "ago",
+            "next",
+            "2024-01-01 2024-01-02",
+            "@12x",
+        ] {
+            assert_eq!(date(text), None, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use crate::utils::sys;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// User and group names read straight from /etc/passwd and /etc/group, so name lookups
+// work in minimal containers without going through libc's NSS machinery
+pub struct UserDb {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+    homes: HashMap<String, PathBuf>,
+}
+
+impl UserDb {
+    // Missing or unreadable files just leave the database empty
+    pub fn load(passwd: &Path, group: &Path) -> Self {
+        Self::parse(
+            &fs::read_to_string(passwd).unwrap_or_default(),
+            &fs::read_to_string(group).unwrap_or_default(),
+        )
+    }
+
+    pub fn parse(passwd: &str, group: &str) -> Self {
+        let mut db = UserDb {
+            users: HashMap::new(),
+            groups: HashMap::new(),
+            homes: HashMap::new(),
+        };
+
+        // name:password:uid:gid:gecos:home:shell
+        for fields in records(passwd) {
+            let (Some(name), Some(uid)) = (fields.first(), fields.get(2)) else {
+                continue;
+            };
+            let Ok(uid) = uid.parse::<u32>() else {
+                continue;
+            };
+            // Like getpwuid, the first entry for an id wins
+            db.users.entry(uid).or_insert_with(|| name.to_string());
+            if let Some(home) = fields.get(5) {
+                db.homes
+                    .entry(name.to_string())
+                    .or_insert_with(|| PathBuf::from(home));
+            }
+        }
+
+        // name:password:gid:members
+        for fields in records(group) {
+            let (Some(name), Some(gid)) = (fields.first(), fields.get(2)) else {
+                continue;
+            };
+            if let Ok(gid) = gid.parse::<u32>() {
+                db.groups.entry(gid).or_insert_with(|| name.to_string());
+            }
+        }
+
+        db
+    }
+
+    // Unknown ids fall back to the number, as ls does
+    pub fn user_name(&self, uid: u32) -> String {
+        self.users
+            .get(&uid)
+            .cloned()
+            .unwrap_or_else(|| uid.to_string())
+    }
+
+    pub fn group_name(&self, gid: u32) -> String {
+        self.groups
+            .get(&gid)
+            .cloned()
+            .unwrap_or_else(|| gid.to_string())
+    }
+
+    pub fn home_dir(&self, user: &str) -> Option<&Path> {
+        self.homes.get(user).map(PathBuf::as_path)
+    }
+}
+
+// Non-empty, non-comment lines split on ':'
+fn records(content: &str) -> impl Iterator<Item = Vec<&str>> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| line.split(':').collect())
+}
+
+// The system database, read once and cached for the rest of the session
+pub fn system() -> &'static UserDb {
+    static DB: OnceLock<UserDb> = OnceLock::new();
+    DB.get_or_init(|| UserDb::load(Path::new("/etc/passwd"), Path::new("/etc/group")))
+}
+
+// Expand a leading "~" or "~user" in a word. Returns None when the word doesn't start
+// with a tilde or names an unknown user, in which case it should be left as typed.
+pub fn expand_tilde(word: &str) -> Option<String> {
+    expand_tilde_with(word, system())
+}
+
+pub fn expand_tilde_with(word: &str, db: &UserDb) -> Option<String> {
+    let rest = word.strip_prefix('~')?;
+    let (user, tail) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+
+    let home = if user.is_empty() {
+        match std::env::var("HOME") {
+            Ok(home) => PathBuf::from(home),
+            Err(_) => {
+                // SAFETY: getuid has no preconditions and cannot fail
+                let uid = unsafe { sys::getuid() };
+                db.home_dir(&db.user_name(uid))?.to_path_buf()
+            }
+        }
+    } else {
+        db.home_dir(user)?.to_path_buf()
+    };
+
+    Some(format!("{}{}", home.to_string_lossy(), tail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWD: &str = "\
+# comment:x:5:5::/nowhere:/bin/sh
+root:x:0:0:root:/root:/bin/bash
+alice:x:1000:1000:Alice:/home/alice:/bin/sh
+
+nobody:x:not-a-number:65534::/nonexistent:/usr/sbin/nologin
+short:x
+toor:x:0:0:second root:/root2:/bin/sh
+alice:x:1001:1001:Another alice:/home/other:/bin/sh
+nohome:x:1002:1002
+";
+
+    const GROUP: &str = "\
+root:x:0:
+staff:x:50:alice,bob
+wheel:x:0:
+broken
+users:x:100
+";
+
+    fn db() -> UserDb {
+        UserDb::parse(PASSWD, GROUP)
+    }
+
+    #[test]
+    fn names_by_id() {
+        let db = db();
+        assert_eq!(db.user_name(0), "root");
+        assert_eq!(db.user_name(1000), "alice");
+        assert_eq!(db.user_name(1001), "alice");
+        assert_eq!(db.group_name(50), "staff");
+        assert_eq!(db.group_name(100), "users");
+    }
+
+    #[test]
+    fn first_entry_for_an_id_wins() {
+        let db = db();
+        assert_eq!(db.user_name(0), "root");
+        assert_eq!(db.group_name(0), "root");
+        assert_eq!(db.home_dir("alice"), Some(Path::new("/home/alice")));
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let db = db();
+        assert_eq!(db.home_dir("nobody"), None);
+        assert_eq!(db.home_dir("short"), None);
+        assert_eq!(db.home_dir("nohome"), None);
+        assert_eq!(db.user_name(1002), "nohome");
+        assert_eq!(db.user_name(5), "5");
+        assert_eq!(db.home_dir("# comment"), None);
+    }
+
+    #[test]
+    fn unknown_ids_fall_back_to_numbers() {
+        let db = db();
+        assert_eq!(db.user_name(4242), "4242");
+        assert_eq!(db.group_name(65534), "65534");
+
+        let empty = UserDb::parse("", "");
+        assert_eq!(empty.user_name(0), "0");
+        assert_eq!(empty.group_name(0), "0");
+    }
+
+    #[test]
+    fn tilde_expansion() {
+        let db = db();
+        assert_eq!(
+            expand_tilde_with("~alice", &db).as_deref(),
+            Some("/home/alice")
+        );
+        assert_eq!(
+            expand_tilde_with("~alice/notes.txt", &db).as_deref(),
+            Some("/home/alice/notes.txt")
+        );
+        assert_eq!(expand_tilde_with("~root/", &db).as_deref(), Some("/root/"));
+        assert_eq!(expand_tilde_with("~unknown", &db), None);
+        assert_eq!(expand_tilde_with("~unknown/x", &db), None);
+        assert_eq!(expand_tilde_with("~nohome", &db), None);
+        assert_eq!(expand_tilde_with("alice", &db), None);
+        assert_eq!(expand_tilde_with("a~alice", &db), None);
+    }
+}
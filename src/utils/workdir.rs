@@ -0,0 +1,108 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+
+// The shell keeps a logical working directory in $PWD, which remembers the symlinks
+// the user went through, alongside the physical one the kernel knows about.
+
+// $PWD if it is an absolute path without . or .. components that still names the
+// current directory; otherwise None and callers should use the physical path
+pub fn logical_cwd() -> Option<PathBuf> {
+    let pwd = PathBuf::from(env::var_os("PWD")?);
+    if !pwd.is_absolute()
+        || pwd
+            .components()
+            .any(|c| matches!(c, Component::CurDir | Component::ParentDir))
+    {
+        return None;
+    }
+
+    let logical = fs::metadata(&pwd).ok()?;
+    let physical = fs::metadata(".").ok()?;
+    (logical.dev() == physical.dev() && logical.ino() == physical.ino()).then_some(pwd)
+}
+
+pub fn physical_cwd() -> io::Result<PathBuf> {
+    env::current_dir()
+}
+
+// The logical directory when $PWD is trustworthy, else the physical one
+pub fn current() -> io::Result<PathBuf> {
+    match logical_cwd() {
+        Some(pwd) => Ok(pwd),
+        None => physical_cwd(),
+    }
+}
+
+// Resolve . and .. purely textually, so "/a/link/.." becomes "/a" no matter where
+// the link points
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            // ".." takes back the name before it. At the root it stays at the root,
+            // and a relative path keeps the ".." it can't take back.
+            Component::ParentDir => match result.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    result.pop();
+                }
+                Some(Component::RootDir) => {}
+                _ => result.push(".."),
+            },
+            other => result.push(other),
+        }
+    }
+    if result.as_os_str().is_empty() {
+        result.push(if path.is_absolute() { "/" } else { "." });
+    }
+    result
+}
+
+pub fn set_pwd(pwd: &Path, old_pwd: Option<&Path>) {
+    // SAFETY: the shell is single-threaded, nothing else reads the environment concurrently
+    unsafe {
+        if let Some(old_pwd) = old_pwd {
+            env::set_var("OLDPWD", old_pwd);
+        }
+        env::set_var("PWD", pwd);
+    }
+}
+
+// Make $PWD agree with the real working directory when the shell starts
+pub fn sync_pwd() {
+    if logical_cwd().is_none()
+        && let Ok(physical) = physical_cwd()
+    {
+        set_pwd(&physical, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(path: &str) -> PathBuf {
+        normalize(Path::new(path))
+    }
+
+    #[test]
+    fn dots_are_resolved_textually() {
+        assert_eq!(normalized("/a/link/.."), Path::new("/a"));
+        assert_eq!(normalized("/a/./b/../c/"), Path::new("/a/c"));
+        assert_eq!(normalized("/.."), Path::new("/"));
+        assert_eq!(normalized("/a/../../b"), Path::new("/b"));
+        assert_eq!(normalized("a/.."), Path::new("."));
+        assert_eq!(normalized("./"), Path::new("."));
+    }
+
+    #[test]
+    fn relative_paths_keep_leading_parents() {
+        assert_eq!(normalized(".."), Path::new(".."));
+        assert_eq!(normalized("../.."), Path::new("../.."));
+        assert_eq!(normalized("a/../../b"), Path::new("../b"));
+        assert_eq!(normalized("../a/../../b/./c"), Path::new("../../b/c"));
+    }
+}
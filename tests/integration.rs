@@ -0,0 +1,145 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{self, Command, Stdio};
+
+// Feed script to the shell on stdin, one command per line, and give back what it
+// wrote, with the prompts taken out, and the status it ended with
+fn run(script: &str) -> (String, String, i32) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_shell"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap().replace("$ ", ""),
+        String::from_utf8(output.stderr).unwrap(),
+        output.status.code().unwrap_or(-1),
+    )
+}
+
+#[test]
+fn only_command_output_is_printed() {
+    let (stdout, stderr, status) = run("echo hello\nexit\n");
+    assert_eq!(
+        (stdout.as_str(), stderr.as_str(), status),
+        ("hello\n", "", 0)
+    );
+}
+
+#[test]
+fn syntax_errors_do_not_end_the_shell() {
+    let (stdout, stderr, status) = run("echo 'open\necho ok |\necho after\nexit\n");
+    assert_eq!(stdout, "after\n");
+    assert_eq!(
+        stderr,
+        "Error: Lexer error at position 5: Unclosed quote\nError: Parser error: Expected command name\n"
+    );
+    assert_eq!(status, 0);
+
+    // A syntax error leaves status 2 behind
+    let (_, _, status) = run("echo 'open\nexit\n");
+    assert_eq!(status, 2);
+}
+
+#[test]
+fn exit_statuses() {
+    // A bare exit ends with the last command's status
+    assert_eq!(run("cd /nonexistent\nexit\n").2, 1);
+    assert_eq!(run("cd /nonexistent\necho\nexit\n").2, 0);
+    assert_eq!(run("exit 7\n").2, 7);
+    assert_eq!(run("exit 300\n").2, 44);
+
+    let (_, stderr, status) = run("exit abc\n");
+    assert_eq!(stderr, "exit: abc: numeric argument required\n");
+    assert_eq!(status, 2);
+
+    // Too many arguments is an error, and the shell carries on
+    let (stdout, stderr, status) = run("exit 1 2\necho still here\nexit\n");
+    assert_eq!(stdout, "still here\n");
+    assert_eq!(
+        stderr,
+        "Error: Invalid arguments: exit: too many arguments\n"
+    );
+    assert_eq!(status, 0);
+}
+
+// A directory under the temp directory for one test, removed at the end
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("shell-it{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn path(&self) -> String {
+        self.0.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn cd_follows_the_logical_path() {
+    let dir = TempDir::new("cd");
+    fs::create_dir_all(dir.0.join("real/deep")).unwrap();
+    fs::create_dir_all(dir.0.join("real/sibling")).unwrap();
+    std::os::unix::fs::symlink("real/deep", dir.0.join("link")).unwrap();
+    let root = dir.path();
+
+    // ".." goes back the way the link was entered, and -P the way it points
+    let (stdout, _, _) = run(&format!("cd {root}/link\ncd ..\npwd\nexit\n"));
+    assert_eq!(stdout, format!("{root}\n"));
+    let (stdout, _, _) = run(&format!("cd {root}/link\ncd -P ..\npwd\nexit\n"));
+    assert_eq!(stdout, format!("{root}/real\n"));
+
+    // A directory that only exists physically is not entered by -L
+    let (stdout, stderr, _) = run(&format!("cd {root}/link\ncd ../sibling\npwd\nexit\n"));
+    assert_eq!(stdout, format!("{root}/link\n"));
+    assert_eq!(
+        stderr,
+        "Error: Execution error: cd: ../sibling: No such file or directory\n"
+    );
+    let (stdout, _, _) = run(&format!("cd {root}/link\ncd -P ../sibling\npwd\nexit\n"));
+    assert_eq!(stdout, format!("{root}/real/sibling\n"));
+}
+
+#[test]
+fn pipeline_stages_cannot_exit_the_shell() {
+    let (stdout, _, status) = run("echo x | exit 4\necho after\nexit\n");
+    assert_eq!((stdout.as_str(), status), ("after\n", 0));
+    let (stdout, _, status) = run("exit 3 | echo y\necho after\nexit\n");
+    assert_eq!((stdout.as_str(), status), ("y\nafter\n", 0));
+
+    // The pipeline's status is the last stage's, whatever came before it
+    let (_, _, status) = run("echo x | exit 4\nexit\n");
+    assert_eq!(status, 4);
+    let (_, _, status) = run("exit 3 | echo y\nexit\n");
+    assert_eq!(status, 0);
+}
+
+#[test]
+fn pipeline_stages_cannot_change_directory() {
+    let dir = TempDir::new("pipe-cd");
+    let root = dir.path();
+    let (stdout, stderr, _) = run(&format!(
+        "cd {root}\ncd / | cat\npwd\necho x | cd /\npwd\nexit\n"
+    ));
+    assert_eq!(stdout, format!("{root}\n{root}\n"));
+    assert_eq!(stderr, "");
+}